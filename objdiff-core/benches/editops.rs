@@ -0,0 +1,154 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use objdiff_core::diff::editops::editops_find;
+
+/// The full-matrix implementation before large inputs were split, as a baseline.
+mod original {
+    use objdiff_core::diff::editops::{Affix, LevEditOp, LevEditType};
+
+    pub fn editops_find<T>(query: &[T], choice: &[T]) -> Vec<LevEditOp>
+    where T: PartialEq {
+        let Affix { prefix_len, suffix_len } = Affix::find(query, choice);
+
+        let first_string = &query[prefix_len..query.len() - suffix_len];
+        let second_string = &choice[prefix_len..choice.len() - suffix_len];
+
+        let matrix_columns = first_string.len() + 1;
+        let matrix_rows = second_string.len() + 1;
+
+        // TODO maybe use an actual matrix for readability
+        let mut cache_matrix: Vec<usize> = vec![0; matrix_rows * matrix_columns];
+        for (i, elem) in cache_matrix.iter_mut().enumerate().take(matrix_rows) {
+            *elem = i;
+        }
+        for i in 1..matrix_columns {
+            cache_matrix[matrix_rows * i] = i;
+        }
+
+        for (i, char1) in first_string.iter().enumerate() {
+            let mut prev = i * matrix_rows;
+            let current = prev + matrix_rows;
+            let mut x = i + 1;
+            for (p, char2p) in second_string.iter().enumerate() {
+                let mut c3 = cache_matrix[prev] + (char1 != char2p) as usize;
+                prev += 1;
+                x += 1;
+                if x >= c3 {
+                    x = c3;
+                }
+                c3 = cache_matrix[prev] + 1;
+                if x > c3 {
+                    x = c3;
+                }
+                cache_matrix[current + 1 + p] = x;
+            }
+        }
+        editops_from_cost_matrix(matrix_columns, matrix_rows, prefix_len, cache_matrix)
+    }
+
+    fn editops_from_cost_matrix(
+        len1: usize,
+        len2: usize,
+        prefix_len: usize,
+        cache_matrix: Vec<usize>,
+    ) -> Vec<LevEditOp> {
+        let mut ops = Vec::with_capacity(cache_matrix[len1 * len2 - 1]);
+        let mut dir = 0;
+        let mut i = len1 - 1;
+        let mut j = len2 - 1;
+        let mut p = len1 * len2 - 1;
+
+        //TODO this is still pretty ugly
+        while i > 0 || j > 0 {
+            let current_value = cache_matrix[p];
+
+            // More than one operation can be possible at a time. We use `dir` to
+            // decide when ambiguous.
+            let is_insert = j > 0 && current_value == cache_matrix[p - 1] + 1;
+            let is_delete = i > 0 && current_value == cache_matrix[p - len2] + 1;
+            let is_replace = i > 0 && j > 0 && current_value == cache_matrix[p - len2 - 1] + 1;
+
+            let (op_type, new_dir) = match (dir, is_insert, is_delete, is_replace) {
+                (_, false, false, false) => (None, 0),
+                (-1, true, _, _) => (Some(LevEditType::Insert), -1),
+                (1, _, true, _) => (Some(LevEditType::Delete), 1),
+                (_, _, _, true) => (Some(LevEditType::Replace), 0),
+                (0, true, _, _) => (Some(LevEditType::Insert), -1),
+                (0, _, true, _) => (Some(LevEditType::Delete), 1),
+                _ => panic!("something went terribly wrong"),
+            };
+
+            match new_dir {
+                -1 => {
+                    j -= 1;
+                    p -= 1;
+                }
+                1 => {
+                    i -= 1;
+                    p -= len2;
+                }
+                0 => {
+                    i -= 1;
+                    j -= 1;
+                    p -= len2 + 1;
+                }
+                _ => panic!("something went terribly wrong"),
+            };
+            dir = new_dir;
+
+            if let Some(op_type) = op_type {
+                ops.insert(0, LevEditOp {
+                    op_type,
+                    first_start: i + prefix_len,
+                    second_start: j + prefix_len,
+                });
+            }
+        }
+
+        ops
+    }
+}
+
+/// Generates a pseudo-random opcode sequence and a copy with roughly 10% of it edited.
+fn generate(len: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut state = 0x2545f491u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+    let query = (0..len).map(|_| next() as u8 % 32).collect::<Vec<_>>();
+    let mut choice = Vec::with_capacity(len);
+    for &op in &query {
+        match next() % 30 {
+            0 => {}
+            1 => choice.extend([op, next() as u8 % 32]),
+            2 => choice.push(next() as u8 % 32),
+            _ => choice.push(op),
+        }
+    }
+    (query, choice)
+}
+
+fn bench_editops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("editops");
+    group.sample_size(10);
+    for len in [500, 2000, 4000] {
+        let (query, choice) = generate(len);
+        group.bench_with_input(BenchmarkId::new("original", len), &len, |b, _| {
+            b.iter(|| original::editops_find(black_box(&query), black_box(&choice)))
+        });
+        group.bench_with_input(BenchmarkId::new("linear", len), &len, |b, _| {
+            b.iter(|| editops_find(black_box(&query), black_box(&choice)))
+        });
+    }
+    // Too large for the full matrix
+    let (query, choice) = generate(20000);
+    group.bench_function(BenchmarkId::new("linear", 20000), |b| {
+        b.iter(|| editops_find(black_box(&query), black_box(&choice)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_editops);
+criterion_main!(benches);
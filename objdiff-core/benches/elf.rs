@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use objdiff_core::{
+    arch::find_reloc,
+    diff::{diff_objs, DiffAlg, DiffObjConfig},
+    obj::{elf, ObjInfo, ObjSectionKind},
+};
+use object::{
+    elf::{R_MIPS_HI16, R_MIPS_LO16, R_PPC_ADDR16_HA, R_PPC_ADDR16_LO},
+    write::{Object, Relocation, SectionId, StandardSection, Symbol, SymbolSection},
+    Architecture, BinaryFormat, Endianness, Object as _, ObjectSection, RelocationEncoding,
+    RelocationKind, SymbolFlags, SymbolKind, SymbolScope,
+};
+
+/// The linear scans used before relocations and section symbols were indexed, as a baseline.
+mod original {
+    use objdiff_core::obj::ObjReloc;
+    use object::{File, Object, ObjectSymbol, SectionIndex, Symbol, SymbolIndex, SymbolKind};
+
+    pub fn find_reloc(relocs: &[ObjReloc], address: u64) -> Option<&ObjReloc> {
+        relocs.iter().find(|r| (r.address as u32 & !3) == address as u32)
+    }
+
+    pub fn find_section_symbol(
+        obj_file: &File<'_>,
+        section_index: SectionIndex,
+        address: u64,
+    ) -> Option<SymbolIndex> {
+        let mut closest_symbol: Option<Symbol<'_, '_>> = None;
+        for symbol in obj_file.symbols() {
+            if !matches!(symbol.section_index(), Some(idx) if idx == section_index) {
+                continue;
+            }
+            if symbol.kind() == SymbolKind::Section || symbol.address() != address {
+                if symbol.address() < address
+                    && symbol.size() != 0
+                    && (closest_symbol.is_none()
+                        || matches!(&closest_symbol, Some(s) if s.address() <= symbol.address()))
+                {
+                    closest_symbol = Some(symbol);
+                }
+                continue;
+            }
+            return Some(symbol.index());
+        }
+        closest_symbol.map(|s| s.index())
+    }
+}
+
+/// Number of `%hi`/`%lo` pairs per generated function.
+const FUNCTION_PAIRS: usize = 32;
+
+/// Writes an object with `count` local data symbols and functions loading the
+/// address of each with a `%hi`/`%lo` pair against the section symbol, as
+/// compilers do for statics.
+fn generate(architecture: Architecture, count: usize) -> PathBuf {
+    let mut obj = Object::new(BinaryFormat::Elf, architecture, Endianness::Big);
+    let data_section = obj.section_id(StandardSection::Data);
+    let text_section = obj.section_id(StandardSection::Text);
+    for i in 0..count {
+        let offset = obj.append_section_data(data_section, &[0; 8], 8);
+        add_symbol(&mut obj, format!("data_{i}"), offset, 8, SymbolKind::Data, data_section);
+    }
+    let mut text = Vec::with_capacity(count * 8);
+    let mut relocs = Vec::with_capacity(count * 2);
+    for i in 0..count {
+        let target = i as u32 * 8;
+        let offset = text.len() as u64;
+        match architecture {
+            Architecture::PowerPc => {
+                // lis r3, target@ha; addi r3, r3, target@l
+                text.extend_from_slice(&0x3c60_0000u32.to_be_bytes());
+                text.extend_from_slice(&0x3863_0000u32.to_be_bytes());
+                relocs.push((offset + 2, R_PPC_ADDR16_HA, target as i64));
+                relocs.push((offset + 6, R_PPC_ADDR16_LO, target as i64));
+            }
+            Architecture::Mips => {
+                // lui a0, %hi(target); addiu a0, a0, %lo(target), with implicit addends
+                let hi = (target + 0x8000) >> 16;
+                let lo = target & 0xffff;
+                text.extend_from_slice(&(0x3c04_0000 | hi).to_be_bytes());
+                text.extend_from_slice(&(0x2484_0000 | lo).to_be_bytes());
+                relocs.push((offset, R_MIPS_HI16, 0));
+                relocs.push((offset + 4, R_MIPS_LO16, 0));
+            }
+            _ => unreachable!(),
+        }
+    }
+    let text_offset = obj.append_section_data(text_section, &text, 16);
+    for (i, function) in text.chunks(FUNCTION_PAIRS * 8).enumerate() {
+        add_symbol(
+            &mut obj,
+            format!("func_{i}"),
+            text_offset + (i * FUNCTION_PAIRS * 8) as u64,
+            function.len() as u64,
+            SymbolKind::Text,
+            text_section,
+        );
+    }
+    let section_symbol = obj.section_symbol(data_section);
+    for (offset, r_type, addend) in relocs {
+        obj.add_relocation(text_section, Relocation {
+            offset: text_offset + offset,
+            size: 16,
+            kind: RelocationKind::Elf(r_type),
+            encoding: RelocationEncoding::Generic,
+            symbol: section_symbol,
+            addend,
+        })
+        .unwrap();
+    }
+    let path = std::env::temp_dir().join(format!("objdiff-bench-{architecture:?}-{count}.o"));
+    std::fs::write(&path, obj.write().unwrap()).unwrap();
+    path
+}
+
+fn add_symbol(
+    obj: &mut Object<'_>,
+    name: String,
+    value: u64,
+    size: u64,
+    kind: SymbolKind,
+    section: SectionId,
+) {
+    obj.add_symbol(Symbol {
+        name: name.into_bytes(),
+        value,
+        size,
+        kind,
+        scope: SymbolScope::Compilation,
+        weak: false,
+        section: SymbolSection::Section(section),
+        flags: SymbolFlags::None,
+    });
+}
+
+fn config() -> DiffObjConfig {
+    DiffObjConfig { code_alg: DiffAlg::Patience, data_alg: DiffAlg::Patience, ..Default::default() }
+}
+
+/// Calls `lookup` for every instruction address of every function, with the
+/// relocations of its section, as [`ObjArch::process_code`] does.
+///
+/// [`ObjArch::process_code`]: objdiff_core::arch::ObjArch::process_code
+fn lookup_relocs(obj: &ObjInfo, lookup: impl Fn(&[objdiff_core::obj::ObjReloc], u64) -> bool) {
+    for section in obj.sections.iter().filter(|s| s.kind == ObjSectionKind::Code) {
+        for symbol in &section.symbols {
+            for address in (symbol.address..symbol.address + symbol.size).step_by(4) {
+                black_box(lookup(&section.relocations, address));
+            }
+        }
+    }
+}
+
+fn bench_elf(c: &mut Criterion) {
+    let config = config();
+    for architecture in [Architecture::PowerPc, Architecture::Mips] {
+        let mut group = c.benchmark_group(format!("elf/{architecture:?}"));
+        group.sample_size(10);
+        for count in [500, 2000, 8000] {
+            let path = generate(architecture, count);
+            group.bench_with_input(BenchmarkId::new("read", count), &count, |b, _| {
+                b.iter(|| elf::read(&path, &config).unwrap())
+            });
+            // The previous loader's section symbol scans alone, to compare against
+            // the whole indexed load above
+            let data = std::fs::read(&path).unwrap();
+            let obj_file = object::File::parse(data.as_slice()).unwrap();
+            let data_section = obj_file.section_by_name(".data").unwrap().index();
+            let obj = elf::read(&path, &config).unwrap();
+            let targets = obj
+                .sections
+                .iter()
+                .flat_map(|s| &s.relocations)
+                .map(|r| r.target.section_address)
+                .collect::<Vec<_>>();
+            group.bench_with_input(
+                BenchmarkId::new("section_symbols_linear", count),
+                &count,
+                |b, _| {
+                    b.iter(|| {
+                        for &address in &targets {
+                            black_box(original::find_section_symbol(
+                                &obj_file,
+                                data_section,
+                                address,
+                            ));
+                        }
+                    })
+                },
+            );
+            group.bench_with_input(BenchmarkId::new("find_reloc_linear", count), &count, |b, _| {
+                b.iter(|| {
+                    lookup_relocs(&obj, |relocs, address| {
+                        original::find_reloc(relocs, address).is_some()
+                    })
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("find_reloc", count), &count, |b, _| {
+                b.iter(|| {
+                    lookup_relocs(&obj, |relocs, address| {
+                        find_reloc(relocs, address, address + 4).is_some()
+                    })
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("process_code", count), &count, |b, _| {
+                b.iter_batched(
+                    || elf::read(&path, &config).unwrap(),
+                    |mut obj| diff_objs(&config, Some(&mut obj), None).unwrap(),
+                    criterion::BatchSize::LargeInput,
+                )
+            });
+            let _ = std::fs::remove_file(&path);
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_elf);
+criterion_main!(benches);
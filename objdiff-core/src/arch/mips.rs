@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use object::{elf, File, Relocation, RelocationKind, RelocationTarget};
+use rabbitizer::{config, Abi, InstrCategory, Instruction, OperandType};
+
+use crate::{
+    arch::{find_reloc, ObjArch, PendingReloc, RelocFormat},
+    diff::ProcessCodeResult,
+    obj::{
+        ObjDelaySlot, ObjIns, ObjInsArg, ObjInsArgValue, ObjLineInfo, ObjReloc, ObjSdaBase,
+        ObjSection,
+    },
+};
+
+/// General purpose register names, as rabbitizer displays them.
+const GPR_NAMES: [&str; 32] = [
+    "$zero", "$at", "$v0", "$v1", "$a0", "$a1", "$a2", "$a3", "$t0", "$t1", "$t2", "$t3", "$t4",
+    "$t5", "$t6", "$t7", "$s0", "$s1", "$s2", "$s3", "$s4", "$s5", "$s6", "$s7", "$t8", "$t9",
+    "$k0", "$k1", "$gp", "$sp", "$fp", "$ra",
+];
+
+/// Floating point register names under the O32 ABI, as rabbitizer displays them.
+const FPR_NAMES: [&str; 32] = [
+    "$fv0", "$fv0f", "$fv1", "$fv1f", "$ft0", "$ft0f", "$ft1", "$ft1f", "$ft2", "$ft2f", "$ft3",
+    "$ft3f", "$fa0", "$fa0f", "$fa1", "$fa1f", "$ft4", "$ft4f", "$ft5", "$ft5f", "$fs0", "$fs0f",
+    "$fs1", "$fs1f", "$fs2", "$fs2f", "$fs3", "$fs3f", "$fs4", "$fs4f", "$fs5", "$fs5f",
+];
+
+fn configure_rabbitizer() {
+    unsafe {
+        config::RabbitizerConfig_Cfg.reg_names.fpr_abi_names = Abi::O32;
+    }
+}
+
+pub struct ObjArchMips {}
+
+impl ObjArchMips {
+    pub fn new(_file: &File<'_>) -> Result<Self> {
+        configure_rabbitizer();
+        Ok(Self {})
+    }
+}
+
+impl ObjArch for ObjArchMips {
+    fn process_code(
+        &self,
+        data: &[u8],
+        start_address: u64,
+        _section_index: usize,
+        relocs: &[ObjReloc],
+        line_info: &Option<ObjLineInfo>,
+    ) -> Result<ProcessCodeResult> {
+        let end_address = start_address + data.len() as u64;
+        let ins_count = data.len() / 4;
+        let mut ops = Vec::<u8>::with_capacity(ins_count);
+        let mut insts = Vec::<ObjIns>::with_capacity(ins_count);
+        let mut cur_addr = start_address as u32;
+        // Delay slot following the previous instruction
+        let mut delay_slot = None;
+        for chunk in data.chunks_exact(4) {
+            let reloc = find_reloc(relocs, cur_addr as u64, cur_addr as u64 + 4);
+            let code = u32::from_be_bytes(chunk.try_into()?);
+            let instruction = Instruction::new(code, cur_addr, InstrCategory::CPU);
+
+            let op = instruction.unique_id as u8;
+            ops.push(op);
+
+            let mnemonic = instruction.opcode_name().to_string();
+            let is_branch = instruction.is_branch();
+            let branch_offset = instruction.branch_offset();
+            let branch_dest =
+                if is_branch { Some((cur_addr as i32 + branch_offset) as u32) } else { None };
+
+            let operands = instruction.get_operands_slice();
+            let mut args = Vec::with_capacity(operands.len() + 1);
+            for op in operands {
+                match op {
+                    OperandType::cpu_immediate
+                    | OperandType::cpu_label
+                    | OperandType::cpu_branch_target_label => {
+                        if is_branch {
+                            args.push(ObjInsArg::BranchOffset(branch_offset));
+                        } else if let Some(reloc) = reloc {
+                            if matches!(&reloc.target_section, Some(s) if s == ".text")
+                                && reloc.target.address > start_address
+                                && reloc.target.address < end_address
+                            {
+                                // Inter-function reloc, convert to branch offset
+                                args.push(ObjInsArg::BranchOffset(
+                                    reloc.target.address as i32 - cur_addr as i32,
+                                ));
+                            } else {
+                                args.push(ObjInsArg::Reloc);
+                            }
+                        } else {
+                            args.push(ObjInsArg::Arg(ObjInsArgValue::Opaque(
+                                op.disassemble(&instruction, None),
+                            )));
+                        }
+                    }
+                    OperandType::cpu_immediate_base => {
+                        if reloc.is_some() {
+                            args.push(ObjInsArg::RelocWithBase);
+                        } else {
+                            args.push(ObjInsArg::ArgWithBase(ObjInsArgValue::Opaque(
+                                OperandType::cpu_immediate.disassemble(&instruction, None),
+                            )));
+                        }
+                        args.push(ObjInsArg::Arg(ObjInsArgValue::Register(
+                            OperandType::cpu_rs.disassemble(&instruction, None),
+                        )));
+                    }
+                    _ => {
+                        let text = op.disassemble(&instruction, None);
+                        // Register names are prefixed with `$`, other operands are immediates
+                        args.push(ObjInsArg::Arg(if text.starts_with('$') {
+                            ObjInsArgValue::Register(text)
+                        } else {
+                            ObjInsArgValue::Opaque(text)
+                        }));
+                    }
+                }
+            }
+            let line = line_info.as_ref().and_then(|info| info.line(cur_addr as u64));
+            insts.push(ObjIns {
+                address: cur_addr,
+                code,
+                op,
+                mnemonic,
+                args,
+                reloc: reloc.cloned(),
+                branch_dest,
+                line,
+                orig: None,
+                delay_slot,
+            });
+            delay_slot = if !instruction.has_delay_slot() {
+                None
+            } else if instruction.is_branch_likely() {
+                Some(ObjDelaySlot::Likely)
+            } else {
+                Some(ObjDelaySlot::Always)
+            };
+            cur_addr += 4;
+        }
+        Ok(ProcessCodeResult { ops, insts })
+    }
+
+    fn implicit_addend(
+        &self,
+        section: &ObjSection,
+        address: u64,
+        reloc: &Relocation,
+    ) -> Result<i64> {
+        let data = section.data[address as usize..address as usize + 4].try_into()?;
+        let addend = u32::from_be_bytes(data);
+        Ok(match reloc.kind() {
+            RelocationKind::Absolute => addend as i64,
+            RelocationKind::Elf(kind) => match kind {
+                elf::R_MIPS_HI16 => ((addend & 0x0000FFFF) << 16) as i32 as i64,
+                elf::R_MIPS_LO16 | elf::R_MIPS_GOT16 | elf::R_MIPS_CALL16 | elf::R_MIPS_GPREL16 => {
+                    (addend & 0x0000FFFF) as i16 as i64
+                }
+                elf::R_MIPS_GPREL32 => addend as i32 as i64,
+                elf::R_MIPS_26 => ((addend & 0x03FFFFFF) << 2) as i64,
+                _ => bail!("Unsupported MIPS implicit relocation {kind}"),
+            },
+            kind => bail!("Unsupported MIPS implicit relocation {kind:?}"),
+        })
+    }
+
+    fn pair_relocs(&self, relocs: &mut [PendingReloc]) {
+        // %hi relocations waiting for their %lo
+        let mut pending = Vec::<usize>::new();
+        // High half of the last pair per target symbol, shared by any further %lo
+        let mut last_hi = HashMap::new();
+        for i in 0..relocs.len() {
+            if !relocs[i].reloc.has_implicit_addend() {
+                continue;
+            }
+            let RelocationTarget::Symbol(symbol) = relocs[i].reloc.target() else {
+                continue;
+            };
+            match relocs[i].reloc.kind() {
+                RelocationKind::Elf(elf::R_MIPS_HI16 | elf::R_MIPS_GOT16) => pending.push(i),
+                RelocationKind::Elf(elf::R_MIPS_LO16) => {
+                    let lo = relocs[i].addend;
+                    let mut hi = last_hi.get(&symbol).copied();
+                    pending.retain(|&idx| {
+                        if relocs[idx].reloc.target() != RelocationTarget::Symbol(symbol) {
+                            return true;
+                        }
+                        let ahi = relocs[idx].addend;
+                        // GOT16 holds the high half unshifted
+                        let ahi =
+                            if relocs[idx].reloc.kind() == RelocationKind::Elf(elf::R_MIPS_GOT16) {
+                                ((ahi & 0xFFFF) << 16) as i32 as i64
+                            } else {
+                                ahi
+                            };
+                        relocs[idx].addend = ahi + lo;
+                        hi = Some(ahi);
+                        false
+                    });
+                    if let Some(hi) = hi {
+                        relocs[i].addend = hi + lo;
+                        last_hi.insert(symbol, hi);
+                    } else {
+                        // No %hi of the same symbol precedes this %lo
+                        relocs[i].unpaired = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        // GOT16 against a global symbol has no %lo
+        for idx in pending {
+            if relocs[idx].reloc.kind() == RelocationKind::Elf(elf::R_MIPS_HI16) {
+                relocs[idx].unpaired = true;
+            }
+        }
+    }
+
+    fn reloc_name(&self, kind: RelocationKind) -> Option<&str> {
+        match kind {
+            RelocationKind::Elf(kind) => match kind {
+                elf::R_MIPS_26 => Some("R_MIPS_26"),
+                elf::R_MIPS_HI16 => Some("R_MIPS_HI16"),
+                elf::R_MIPS_LO16 => Some("R_MIPS_LO16"),
+                elf::R_MIPS_GOT16 => Some("R_MIPS_GOT16"),
+                elf::R_MIPS_CALL16 => Some("R_MIPS_CALL16"),
+                elf::R_MIPS_GPREL16 => Some("R_MIPS_GPREL16"),
+                elf::R_MIPS_GPREL32 => Some("R_MIPS_GPREL32"),
+                _ => None,
+            },
+            RelocationKind::Absolute => Some("R_MIPS_32"),
+            _ => None,
+        }
+    }
+
+    fn display_reloc(&self, kind: RelocationKind) -> Option<RelocFormat> {
+        let prefix = match kind {
+            RelocationKind::Elf(elf::R_MIPS_HI16) => "%hi(",
+            RelocationKind::Elf(elf::R_MIPS_LO16) => "%lo(",
+            RelocationKind::Elf(elf::R_MIPS_GOT16) => "%got(",
+            RelocationKind::Elf(elf::R_MIPS_CALL16) => "%call16(",
+            RelocationKind::Elf(elf::R_MIPS_GPREL16) => "%gp_rel(",
+            RelocationKind::Elf(elf::R_MIPS_26) => return Some(RelocFormat::default()),
+            _ => return None,
+        };
+        Some(RelocFormat { prefix, suffix: ")" })
+    }
+
+    // All small data (.sdata, .sbss and .scommon) is addressed through $gp
+    fn sda_base(&self, kind: RelocationKind, _section: Option<&str>) -> Option<ObjSdaBase> {
+        match kind {
+            RelocationKind::Elf(elf::R_MIPS_GPREL16 | elf::R_MIPS_GPREL32) => Some(ObjSdaBase::Gp),
+            _ => None,
+        }
+    }
+
+    fn is_indirect_branch(&self, ins: &ObjIns) -> bool {
+        // `jr $ra` is a return
+        ins.mnemonic == "jr"
+            && !matches!(ins.args.first(), Some(ObjInsArg::Arg(ObjInsArgValue::Register(reg))) if reg == "$ra")
+    }
+
+    // `b` is `beq $zero, $zero`
+    fn ends_block(&self, ins: &ObjIns) -> bool { matches!(ins.mnemonic.as_str(), "j" | "jr" | "b") }
+
+    fn dwarf_register_name(&self, register: u16) -> Option<String> {
+        let register = register as usize;
+        GPR_NAMES
+            .get(register)
+            .or_else(|| FPR_NAMES.get(register.wrapping_sub(32)))
+            .map(|s| s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object::{
+        write::{self, SymbolSection},
+        Architecture, BinaryFormat, Endianness, Object, ObjectSection, SymbolFlags, SymbolKind,
+        SymbolScope,
+    };
+
+    use super::*;
+
+    /// Pairs relocations of the given type, target symbol and addend as read
+    /// from consecutive instructions, returning the resulting addends and
+    /// unpaired flags.
+    fn pair(relocs: &[(u32, &str, i64)]) -> Vec<(i64, bool)> {
+        let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Mips, Endianness::Big);
+        let text = obj.add_section(vec![], b".text".to_vec(), object::SectionKind::Text);
+        obj.append_section_data(text, &vec![0; relocs.len() * 4], 4);
+        for (i, &(r_type, name, _)) in relocs.iter().enumerate() {
+            let symbol = obj.symbol_id(name.as_bytes()).unwrap_or_else(|| {
+                obj.add_symbol(write::Symbol {
+                    name: name.as_bytes().to_vec(),
+                    value: 0,
+                    size: 0,
+                    kind: SymbolKind::Data,
+                    scope: SymbolScope::Dynamic,
+                    weak: false,
+                    section: SymbolSection::Undefined,
+                    flags: SymbolFlags::None,
+                })
+            });
+            obj.add_relocation(text, write::Relocation {
+                offset: i as u64 * 4,
+                size: 16,
+                kind: RelocationKind::Elf(r_type),
+                encoding: object::RelocationEncoding::Generic,
+                symbol,
+                addend: 0,
+            })
+            .unwrap();
+        }
+        let data = obj.write().unwrap();
+        let file = File::parse(data.as_slice()).unwrap();
+        let mut pending = file
+            .section_by_name(".text")
+            .unwrap()
+            .relocations()
+            .zip(relocs)
+            .map(|((address, reloc), &(_, _, addend))| PendingReloc {
+                address,
+                reloc,
+                addend,
+                unpaired: false,
+            })
+            .collect::<Vec<_>>();
+        ObjArchMips {}.pair_relocs(&mut pending);
+        pending.iter().map(|r| (r.addend, r.unpaired)).collect()
+    }
+
+    /// Relocations of the given type, target symbol and addend, and the
+    /// expected addends and unpaired flags after pairing.
+    type Case = (&'static [(u32, &'static str, i64)], &'static [(i64, bool)]);
+
+    #[test]
+    fn pair_relocs() {
+        use elf::{R_MIPS_GOT16 as GOT16, R_MIPS_HI16 as HI16, R_MIPS_LO16 as LO16};
+        #[rustfmt::skip]
+        let cases: &[Case] = &[
+            // %lo >= 0x8000 is negative, carried into %hi
+            (&[(HI16, "a", 0x10000), (LO16, "a", -0x8000)], &[(0x8000, false), (0x8000, false)]),
+            // Several %lo share one %hi, pairs of other symbols interleaved
+            (
+                &[(HI16, "a", 0x10000), (HI16, "b", 0x20000), (LO16, "a", 4), (LO16, "b", 8),
+                    (LO16, "a", 12)],
+                &[(0x10004, false), (0x20008, false), (0x10004, false), (0x20008, false),
+                    (0x1000c, false)],
+            ),
+            // GOT16 of a local symbol holds the unshifted high half and pairs with its %lo
+            (&[(GOT16, "a", 1), (LO16, "a", 0x10)], &[(0x10010, false), (0x10010, false)]),
+            // GOT16 of a global symbol has no %lo
+            (&[(GOT16, "a", 0)], &[(0, false)]),
+            // %lo before any %hi of its symbol, and %hi without a %lo
+            (
+                &[(LO16, "a", 4), (HI16, "a", 0x10000), (LO16, "a", 8), (HI16, "b", 0x10000)],
+                &[(4, true), (0x10008, false), (0x10008, false), (0x10000, true)],
+            ),
+        ];
+        for &(relocs, expected) in cases {
+            assert_eq!(pair(relocs), expected, "{relocs:x?}");
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::RwLock;
+
+use anyhow::Result;
+use object::{File, Object, Relocation, RelocationKind};
+
+use crate::{
+    diff::ProcessCodeResult,
+    obj::{demangle::Demangler, ObjIns, ObjLineInfo, ObjReloc, ObjSdaBase, ObjSection},
+};
+
+#[cfg(feature = "mips")]
+pub mod mips;
+pub mod objdump;
+#[cfg(feature = "ppc")]
+pub mod ppc;
+
+/// Text surrounding a relocation target when displayed as an instruction operand,
+/// e.g. `%hi(` and `)` for MIPS, or `@ha` for PowerPC.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct RelocFormat {
+    pub prefix: &'static str,
+    pub suffix: &'static str,
+}
+
+pub trait ObjArch: Send + Sync {
+    /// Disassembles a function, resolving relocations and line info per instruction.
+    /// `section_index` is the index of the containing section in the object file.
+    /// `relocs` are sorted by address; see [`find_reloc`].
+    fn process_code(
+        &self,
+        data: &[u8],
+        address: u64,
+        section_index: usize,
+        relocs: &[ObjReloc],
+        line_info: &Option<ObjLineInfo>,
+    ) -> Result<ProcessCodeResult>;
+
+    /// Reads the addend stored in the relocated field for REL-style relocations.
+    fn implicit_addend(
+        &self,
+        section: &ObjSection,
+        address: u64,
+        reloc: &Relocation,
+    ) -> Result<i64>;
+
+    /// Combines the addends of relocations that together form one address,
+    /// such as MIPS `%hi`/`%lo` pairs. `relocs` are in section order.
+    fn pair_relocs(&self, _relocs: &mut [PendingReloc]) {}
+
+    /// Returns the name of an architecture-specific relocation type,
+    /// or `None` if the relocation type is unsupported.
+    fn reloc_name(&self, kind: RelocationKind) -> Option<&str>;
+
+    /// Returns how a relocation is displayed in an instruction operand,
+    /// or `None` if the relocation type is not valid in an instruction.
+    fn display_reloc(&self, kind: RelocationKind) -> Option<RelocFormat>;
+
+    /// Returns whether [`ObjIns::op`] is left unset by [`ObjArch::process_code`],
+    /// to be assigned per diff from the mnemonics of both functions.
+    fn ops_from_mnemonics(&self) -> bool { false }
+
+    /// Returns the base register a GP- or SDA-relative relocation is relative to,
+    /// given the section of its target (`None` if extern), or `None` for other
+    /// relocations.
+    fn sda_base(&self, _kind: RelocationKind, _section: Option<&str>) -> Option<ObjSdaBase> { None }
+
+    /// Returns whether the instruction branches to an address held in a register,
+    /// as done to dispatch a jump table.
+    fn is_indirect_branch(&self, _ins: &ObjIns) -> bool { false }
+
+    /// Returns whether control never falls through to the next instruction,
+    /// as with unconditional branches and returns. For architectures with
+    /// delay slots, the slot still executes before the branch is taken.
+    fn ends_block(&self, _ins: &ObjIns) -> bool { false }
+
+    /// Returns whether the instruction may be alignment padding when it
+    /// follows the end of a function.
+    fn is_padding(&self, ins: &ObjIns) -> bool { ins.mnemonic == "nop" }
+
+    /// Returns the mangling scheme of compilers usually targeting the
+    /// architecture, the only one tried by [`Demangler::Auto`] for names that
+    /// aren't Itanium-mangled.
+    fn demangler(&self) -> Demangler { Demangler::GnuV2 }
+
+    /// Returns the name of a register as displayed in operands, given its
+    /// DWARF register number.
+    fn dwarf_register_name(&self, _register: u16) -> Option<String> { None }
+}
+
+/// Relocation read from an object file, before its target is resolved.
+pub struct PendingReloc {
+    pub address: u64,
+    pub reloc: Relocation,
+    pub addend: i64,
+    /// Half of a relocation pair without its counterpart
+    pub unpaired: bool,
+}
+
+/// Finds the first relocation within `start..end` in relocations sorted by address.
+pub fn find_reloc(relocs: &[ObjReloc], start: u64, end: u64) -> Option<&ObjReloc> {
+    let idx = relocs.partition_point(|r| r.address < start);
+    relocs.get(idx).filter(|r| r.address < end)
+}
+
+/// Constructs an [`ObjArch`] for an object file, or `None` if the architecture
+/// is not handled by this factory.
+pub type ObjArchFactory = fn(&File<'_>) -> Option<Result<Box<dyn ObjArch>>>;
+
+static CUSTOM_ARCHES: RwLock<Vec<ObjArchFactory>> = RwLock::new(Vec::new());
+
+/// Registers an architecture implementation from outside of objdiff-core.
+/// Registered factories are tried in order before the built-in architectures.
+pub fn register_arch(factory: ObjArchFactory) { CUSTOM_ARCHES.write().unwrap().push(factory); }
+
+/// Constructs the [`ObjArch`] for an object file from the registered and built-in
+/// architectures, or `None` if none of them handle its architecture.
+pub fn new_arch(obj_file: &File<'_>) -> Option<Result<Box<dyn ObjArch>>> {
+    for factory in CUSTOM_ARCHES.read().unwrap().iter() {
+        if let Some(result) = factory(obj_file) {
+            return Some(result);
+        }
+    }
+    match obj_file.architecture() {
+        #[cfg(feature = "ppc")]
+        object::Architecture::PowerPc => {
+            Some(ppc::ObjArchPpc::new(obj_file).map(|arch| Box::new(arch) as Box<dyn ObjArch>))
+        }
+        #[cfg(feature = "mips")]
+        object::Architecture::Mips => {
+            Some(mips::ObjArchMips::new(obj_file).map(|arch| Box::new(arch) as Box<dyn ObjArch>))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,927 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    str::from_utf8,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use object::{Endianness, File, Object, ObjectSection, Relocation, RelocationKind};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arch::{find_reloc, ObjArch, RelocFormat},
+    diff::ProcessCodeResult,
+    obj::{
+        ObjDelaySlot, ObjIns, ObjInsArg, ObjInsArgValue, ObjLineInfo, ObjReloc, ObjSection,
+        ObjSymbol,
+    },
+};
+
+/// Configuration for disassembling architectures without a native backend
+/// using GNU objdump.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ObjdumpConfig {
+    /// The objdump executable, e.g. `sh-elf-objdump` or `arm-none-eabi-objdump`.
+    pub executable: PathBuf,
+    /// Additional arguments passed after `-dr`, e.g. `["-M", "reg-names-raw"]`.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Additional mnemonics after which control never falls through to the
+    /// next instruction, for instruction sets without a built-in list.
+    #[serde(default)]
+    pub block_ends: Vec<String>,
+}
+
+/// Instruction sets with built-in control flow rules, detected from the file
+/// format printed by objdump, e.g. `elf32-sh` or `elf32-littlearm`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum IsaFamily {
+    X86,
+    Arm,
+    Aarch64,
+    SuperH,
+    M68k,
+    Unknown,
+}
+
+impl IsaFamily {
+    fn from_format(format: &str) -> Self {
+        let Some((_, arch)) = format.split_once('-') else {
+            return Self::Unknown;
+        };
+        if arch.starts_with("x86") || arch == "i386" {
+            Self::X86
+        } else if arch.contains("aarch64") {
+            Self::Aarch64
+        } else if arch.contains("arm") {
+            Self::Arm
+        } else if arch.starts_with("sh") {
+            Self::SuperH
+        } else if arch.starts_with("m68k") {
+            Self::M68k
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// A relocation as printed by `objdump -r`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ObjdumpReloc {
+    pub address: u64,
+    pub name: String,
+    pub target: String,
+    pub addend: i64,
+}
+
+/// An instruction as printed by `objdump -d`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ObjdumpIns {
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub operands: String,
+    pub relocs: Vec<ObjdumpReloc>,
+}
+
+pub struct ObjArchObjdump {
+    endianness: Endianness,
+    family: IsaFamily,
+    /// Block-ending mnemonics from [`ObjdumpConfig::block_ends`]
+    block_ends: Vec<String>,
+    /// Section names by section index
+    section_names: HashMap<usize, String>,
+    /// Disassembly by section name, sorted by address
+    sections: HashMap<String, Vec<ObjdumpIns>>,
+    /// Relocation type names as printed by objdump
+    reloc_names: HashMap<RelocationKind, String>,
+    /// Relocation types by the names printed by objdump
+    reloc_kinds: HashMap<String, RelocationKind>,
+}
+
+impl ObjArchObjdump {
+    pub fn new(obj_path: &Path, file: &File<'_>, config: &ObjdumpConfig) -> Result<Self> {
+        let output = Command::new(&config.executable)
+            .arg("-dr")
+            .args(&config.args)
+            .arg(obj_path)
+            .output()
+            .with_context(|| format!("Failed to execute {}", config.executable.display()))?;
+        if !output.status.success() {
+            bail!(
+                "{} exited with {}: {}",
+                config.executable.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let stdout = from_utf8(&output.stdout).context("Failed to process objdump output")?;
+        let family = parse_format(stdout).map_or(IsaFamily::Unknown, IsaFamily::from_format);
+        let sections = parse_objdump(stdout)?;
+
+        let mut section_names = HashMap::new();
+        let mut reloc_names = HashMap::new();
+        let mut reloc_kinds = HashMap::new();
+        for section in file.sections() {
+            let Ok(name) = section.name() else {
+                continue;
+            };
+            section_names.insert(section.index().0, name.to_string());
+            let Some(insts) = sections.get(name) else {
+                continue;
+            };
+            // Match up relocation types with the names objdump printed for them
+            for (address, reloc) in section.relocations() {
+                let Some(objdump_reloc) =
+                    insts.iter().flat_map(|ins| ins.relocs.iter()).find(|r| r.address == address)
+                else {
+                    continue;
+                };
+                reloc_names.entry(reloc.kind()).or_insert_with(|| objdump_reloc.name.clone());
+                reloc_kinds.entry(objdump_reloc.name.clone()).or_insert(reloc.kind());
+            }
+        }
+        Ok(Self {
+            endianness: file.endianness(),
+            family,
+            block_ends: config.block_ends.clone(),
+            section_names,
+            sections,
+            reloc_names,
+            reloc_kinds,
+        })
+    }
+}
+
+impl ObjArch for ObjArchObjdump {
+    fn process_code(
+        &self,
+        data: &[u8],
+        address: u64,
+        section_index: usize,
+        relocs: &[ObjReloc],
+        line_info: &Option<ObjLineInfo>,
+    ) -> Result<ProcessCodeResult> {
+        let end_address = address + data.len() as u64;
+        let section_name = self
+            .section_names
+            .get(&section_index)
+            .ok_or_else(|| anyhow!("Unknown section index {section_index}"))?;
+        let all_insts = self.sections.get(section_name).map(Vec::as_slice).unwrap_or_default();
+        let start = all_insts.partition_point(|ins| ins.address < address);
+        let end = all_insts.partition_point(|ins| ins.address < end_address);
+
+        let mut ops = Vec::<u8>::with_capacity(end - start);
+        let mut insts = Vec::<ObjIns>::with_capacity(end - start);
+        let mut in_delay_slot = false;
+        for ins in &all_insts[start..end] {
+            let ins_end = ins.address + ins.bytes.len() as u64;
+            let reloc = find_reloc(relocs, ins.address, ins_end)
+                .cloned()
+                .or_else(|| ins.relocs.first().map(|r| self.to_obj_reloc(r)));
+            let mut args = split_operands(&ins.operands)
+                .into_iter()
+                .map(|arg| ObjInsArg::Arg(operand_value(arg)))
+                .collect::<Vec<_>>();
+            // A relocated branch target is unrelated to the printed address
+            if reloc.is_some() {
+                insert_reloc_arg(&mut args);
+            }
+            for arg in &mut args {
+                resolve_branch_arg(arg, ins.address, address, end_address);
+            }
+            let branch_dest = args.iter().find_map(|a| match a {
+                ObjInsArg::BranchOffset(offset) => {
+                    Some((ins.address as i64 + *offset as i64) as u32)
+                }
+                _ => None,
+            });
+            // Assigned per diff from the mnemonic
+            ops.push(0);
+            let line = line_info.as_ref().and_then(|info| info.line(ins.address));
+            let mut orig = format!("{} {}", ins.mnemonic, ins.operands);
+            for reloc in &ins.relocs {
+                orig.push_str(&format!(" ; {} {}", reloc.name, reloc.target));
+            }
+            insts.push(ObjIns {
+                address: ins.address as u32,
+                code: ins.bytes.iter().take(4).fold(0, |acc, &b| (acc << 8) | b as u32),
+                op: 0,
+                mnemonic: ins.mnemonic.clone(),
+                args,
+                reloc,
+                branch_dest,
+                line,
+                orig: Some(orig.trim_end().to_string()),
+                delay_slot: in_delay_slot.then_some(ObjDelaySlot::Always),
+            });
+            in_delay_slot = self.family == IsaFamily::SuperH
+                && matches!(
+                    ins.mnemonic.as_str(),
+                    "bra"
+                        | "braf"
+                        | "bsr"
+                        | "bsrf"
+                        | "jmp"
+                        | "jsr"
+                        | "rts"
+                        | "rte"
+                        | "bt/s"
+                        | "bf/s"
+                        | "bt.s"
+                        | "bf.s"
+                );
+        }
+        Ok(ProcessCodeResult { ops, insts })
+    }
+
+    fn implicit_addend(
+        &self,
+        section: &ObjSection,
+        address: u64,
+        reloc: &Relocation,
+    ) -> Result<i64> {
+        // Without knowledge of the instruction encoding, only plain data
+        // relocations can be decoded.
+        let data = &section.data[address as usize..];
+        let little = self.endianness == Endianness::Little;
+        Ok(match (reloc.kind(), reloc.size()) {
+            (
+                RelocationKind::Absolute | RelocationKind::Relative | RelocationKind::PltRelative,
+                size,
+            ) if matches!(size, 16 | 32 | 64) => {
+                let len = size as usize / 8;
+                let bytes = data.get(..len).context("Relocation outside of section data")?;
+                let value = if little {
+                    bytes.iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64)
+                } else {
+                    bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64)
+                };
+                // Sign-extend from the field size
+                let shift = 64 - size as u32;
+                ((value << shift) as i64) >> shift
+            }
+            (kind, size) => {
+                log::warn!(
+                    "Unsupported implicit relocation {kind:?} ({size} bits) at {}+{address:#x}, \
+                     assuming an addend of 0",
+                    section.name
+                );
+                0
+            }
+        })
+    }
+
+    fn reloc_name(&self, kind: RelocationKind) -> Option<&str> {
+        if let Some(name) = self.reloc_names.get(&kind) {
+            return Some(name);
+        }
+        // Not seen in the disassembly (e.g. in a data section), use a generic name
+        Some(match kind {
+            RelocationKind::Absolute => "Absolute",
+            RelocationKind::Relative => "Relative",
+            RelocationKind::Got => "GOT",
+            RelocationKind::GotRelative => "GOT relative",
+            RelocationKind::PltRelative => "PLT relative",
+            _ => "Unknown",
+        })
+    }
+
+    fn display_reloc(&self, _kind: RelocationKind) -> Option<RelocFormat> {
+        Some(RelocFormat::default())
+    }
+
+    fn ops_from_mnemonics(&self) -> bool { true }
+
+    fn ends_block(&self, ins: &ObjIns) -> bool {
+        let mnemonic = ins.mnemonic.as_str();
+        if self.block_ends.iter().any(|m| m == mnemonic) {
+            return true;
+        }
+        match self.family {
+            IsaFamily::X86 => matches!(
+                mnemonic,
+                "jmp"
+                    | "jmpq"
+                    | "jmpl"
+                    | "ljmp"
+                    | "ret"
+                    | "retq"
+                    | "retl"
+                    | "lret"
+                    | "iret"
+                    | "iretq"
+                    | "ud2"
+            ),
+            IsaFamily::Arm => {
+                let writes_pc = |arg: Option<&ObjInsArg>| match arg {
+                    Some(ObjInsArg::Arg(ObjInsArgValue::Register(r))) => r == "pc",
+                    // Register list, e.g. `{r4, pc}`
+                    Some(ObjInsArg::Arg(ObjInsArgValue::Opaque(s))) => {
+                        s.strip_prefix('{').is_some_and(|list| {
+                            list.trim_end_matches('}').split(',').any(|r| r.trim() == "pc")
+                        })
+                    }
+                    _ => false,
+                };
+                // Thumb-2 width suffixes don't change the operation
+                match mnemonic.trim_end_matches(".n").trim_end_matches(".w") {
+                    "b" | "bx" => true,
+                    "pop" => writes_pc(ins.args.first()),
+                    "ldm" | "ldmia" | "ldmfd" => writes_pc(ins.args.last()),
+                    "mov" | "ldr" => writes_pc(ins.args.first()),
+                    _ => false,
+                }
+            }
+            IsaFamily::Aarch64 => matches!(mnemonic, "b" | "br" | "ret"),
+            IsaFamily::SuperH => matches!(mnemonic, "bra" | "braf" | "jmp" | "rts" | "rte"),
+            IsaFamily::M68k => matches!(
+                mnemonic,
+                "bra" | "bras" | "braw" | "bral" | "jmp" | "rts" | "rte" | "rtd" | "rtr"
+            ),
+            IsaFamily::Unknown => false,
+        }
+    }
+
+    // Assemblers pad x86 code with multi-byte nops, and linkers with int3
+    fn is_padding(&self, ins: &ObjIns) -> bool {
+        let Some(orig) = &ins.orig else {
+            return false;
+        };
+        // Long nops are printed with their prefixes, e.g. `data16 cs nopw`
+        let mut words =
+            orig.split_whitespace().skip_while(|w| matches!(*w, "data16" | "cs" | "ds"));
+        match words.next() {
+            Some("nop" | "nopw" | "nopl" | "int3") => true,
+            Some("xchg") => matches!(words.next(), Some("%ax,%ax" | "ax,ax")),
+            _ => false,
+        }
+    }
+}
+
+impl ObjArchObjdump {
+    fn to_obj_reloc(&self, reloc: &ObjdumpReloc) -> ObjReloc {
+        let kind = self
+            .reloc_kinds
+            .get(&reloc.name)
+            .copied()
+            .unwrap_or_else(|| reloc_kind_from_name(&reloc.name));
+        ObjReloc {
+            kind,
+            address: reloc.address,
+            target: ObjSymbol {
+                name: reloc.target.clone(),
+                demangled_name: None,
+                address: 0,
+                section_address: 0,
+                size: 0,
+                size_known: false,
+                flags: Default::default(),
+                addend: reloc.addend,
+                diff_symbol: None,
+                instructions: vec![],
+                match_percent: None,
+                diff_score: None,
+                truncated: false,
+                blocks: vec![],
+            },
+            target_section: None,
+            unpaired: false,
+            sda_base: None,
+        }
+    }
+}
+
+/// Guesses the kind of a relocation type not seen in the object file from its
+/// name, e.g. `R_X86_64_PLT32` or `R_ARM_REL32`.
+fn reloc_kind_from_name(name: &str) -> RelocationKind {
+    // Skip the `R_` and architecture prefix
+    let mut parts = name.split('_').skip(2);
+    if parts.clone().any(|p| p.starts_with("PLT")) {
+        RelocationKind::PltRelative
+    } else if parts.clone().any(|p| p.starts_with("GOTPC") || p.starts_with("GOTREL")) {
+        RelocationKind::GotRelative
+    } else if parts.clone().any(|p| p.starts_with("GOT")) {
+        RelocationKind::Got
+    } else if parts.any(|p| {
+        p.starts_with("PC") || p.starts_with("PREL") || (p.starts_with("REL") && p != "RELATIVE")
+    }) {
+        RelocationKind::Relative
+    } else {
+        RelocationKind::Absolute
+    }
+}
+
+/// Splits an operand list on top-level commas.
+fn split_operands(operands: &str) -> Vec<&str> {
+    let mut result = vec![];
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in operands.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = operands[start..].trim();
+    if !last.is_empty() {
+        result.push(last);
+    }
+    result
+}
+
+/// Classifies an operand as a register, e.g. `%eax`, `r0` or `sp`. Immediates are
+/// prefixed (`$0x10`, `#4`) or numeric, and memory operands contain punctuation.
+fn operand_value(s: &str) -> ObjInsArgValue {
+    let name = s.strip_prefix('%').unwrap_or(s);
+    if name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+    {
+        ObjInsArgValue::Register(s.to_string())
+    } else {
+        ObjInsArgValue::Opaque(s.to_string())
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u64> { u64::from_str_radix(s.trim_start_matches("0x"), 16).ok() }
+
+/// Whether an immediate is zero, which is what objdump shows for an unrelocated field.
+fn is_zero_imm(s: &str) -> bool {
+    let s = s.trim_start_matches(['$', '#']);
+    matches!(parse_hex(s), Some(0))
+}
+
+fn resolve_branch_arg(arg: &mut ObjInsArg, ins_address: u64, start_address: u64, end_address: u64) {
+    // Branch targets are printed as `addr <symbol+offset>`
+    let ObjInsArg::Arg(ObjInsArgValue::Opaque(s)) = arg else {
+        return;
+    };
+    let Some(dest) = s.split_once(" <").and_then(|(addr, _)| parse_hex(addr)) else {
+        return;
+    };
+    if dest >= start_address && dest < end_address {
+        *arg = ObjInsArg::BranchOffset((dest as i64 - ins_address as i64) as i32);
+    }
+}
+
+/// Replaces the operand that a relocation most likely applies to.
+fn insert_reloc_arg(args: &mut Vec<ObjInsArg>) {
+    let opaque = |a: &ObjInsArg| match a {
+        ObjInsArg::Arg(ObjInsArgValue::Opaque(s)) => Some(s.clone()),
+        _ => None,
+    };
+    // Call or branch to an address outside of the function
+    if let Some(arg) =
+        args.iter_mut().find(|a| opaque(a).is_some_and(|s| s.contains(" <") && s.ends_with('>')))
+    {
+        *arg = ObjInsArg::Reloc;
+        return;
+    }
+    // Offset from a base register, e.g. `0x0(%rip)`
+    if let Some(idx) = args.iter().position(|a| {
+        opaque(a).is_some_and(|s| s.split_once('(').is_some_and(|(imm, _)| is_zero_imm(imm)))
+    }) {
+        let s = opaque(&args[idx]).unwrap();
+        let (_, base) = s.split_once('(').unwrap();
+        args[idx] = ObjInsArg::RelocWithBase;
+        args.insert(idx + 1, ObjInsArg::Arg(operand_value(base.trim_end_matches(')'))));
+        return;
+    }
+    // Plain immediate
+    if let Some(arg) = args.iter_mut().find(|a| opaque(a).is_some_and(|s| is_zero_imm(&s))) {
+        *arg = ObjInsArg::Reloc;
+        return;
+    }
+    args.push(ObjInsArg::Reloc);
+}
+
+fn parse_reloc(address: &str, rest: &str) -> Option<ObjdumpReloc> {
+    let address = parse_hex(address)?;
+    let mut parts = rest.split_whitespace();
+    let name = parts.next()?;
+    if !name.starts_with("R_") {
+        return None;
+    }
+    let expr = parts.next().unwrap_or_default();
+    // Target is printed as `symbol`, `symbol+0x10` or `symbol-0x4`
+    let (target, addend) = match expr.rfind(['+', '-']) {
+        Some(idx) if idx > 0 => match parse_hex(&expr[idx + 1..]) {
+            Some(value) if expr.as_bytes()[idx] == b'-' => (&expr[..idx], -(value as i64)),
+            Some(value) => (&expr[..idx], value as i64),
+            None => (expr, 0),
+        },
+        _ => (expr, 0),
+    };
+    Some(ObjdumpReloc { address, name: name.to_string(), target: target.to_string(), addend })
+}
+
+/// Parses instruction bytes, printed either per byte (`e8 00 00`) or per word (`e92d4800`).
+fn parse_bytes(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    for group in s.split_whitespace() {
+        if group.len() % 2 != 0 {
+            return None;
+        }
+        for i in (0..group.len()).step_by(2) {
+            bytes.push(u8::from_str_radix(group.get(i..i + 2)?, 16).ok()?);
+        }
+    }
+    Some(bytes)
+}
+
+/// Strips a trailing comment, e.g. `! 0x0` (SH), `# b <main+0xb>` (x86) or `@ (8 <f+0x8>)` (ARM).
+/// Comment markers are preceded by whitespace and followed by a space, which distinguishes
+/// them from immediates such as `#4`.
+fn strip_comment(text: &str) -> &str {
+    let bytes = text.as_bytes();
+    for (i, &c) in bytes.iter().enumerate().skip(1) {
+        if matches!(c, b'!' | b'#' | b';' | b'@')
+            && bytes[i - 1].is_ascii_whitespace()
+            && bytes.get(i + 1).map_or(true, |c| *c == b' ')
+        {
+            return text[..i].trim_end();
+        }
+    }
+    text
+}
+
+/// Finds the file format in the header of objdump's output, e.g. `elf32-sh`.
+fn parse_format(text: &str) -> Option<&str> {
+    text.lines().find_map(|line| line.split_once("file format ")).map(|(_, format)| format.trim())
+}
+
+/// Parses the output of `objdump -dr` into instructions, grouped by section name.
+pub fn parse_objdump(text: &str) -> Result<HashMap<String, Vec<ObjdumpIns>>> {
+    let mut sections = HashMap::<String, Vec<ObjdumpIns>>::new();
+    let mut current: Option<&mut Vec<ObjdumpIns>> = None;
+    for line in text.lines() {
+        if let Some(name) =
+            line.strip_prefix("Disassembly of section ").and_then(|s| s.strip_suffix(':'))
+        {
+            current = Some(sections.entry(name.to_string()).or_default());
+            continue;
+        }
+        let Some(insts) = current.as_mut() else {
+            continue;
+        };
+        // Instruction lines: `   4:\te8 00 00 00 00       \tcall   9 <main+0x9>`
+        // Relocation lines:  `\t\t\t5: R_X86_64_PLT32\tfoo-0x4`
+        let Some((address, rest)) = line.trim_start().split_once(':') else {
+            continue;
+        };
+        if address.is_empty() || !address.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        if let Some(reloc) = parse_reloc(address, rest) {
+            if let Some(ins) = insts.last_mut() {
+                ins.relocs.push(reloc);
+            }
+            continue;
+        }
+        let mut fields = rest.trim_start_matches(' ').splitn(3, '\t');
+        let _ = fields.next();
+        let Some(bytes) = fields.next().and_then(parse_bytes) else {
+            continue;
+        };
+        let address = parse_hex(address).context("Invalid instruction address")?;
+        let text = fields.next().map(str::trim).unwrap_or_default();
+        if text.is_empty() {
+            // Continuation of a long instruction's bytes
+            if let Some(ins) = insts.last_mut() {
+                ins.bytes.extend(bytes);
+            }
+            continue;
+        }
+        let text = strip_comment(text);
+        let (mnemonic, operands) = text.split_once([' ', '\t']).unwrap_or((text, ""));
+        insts.push(ObjdumpIns {
+            address,
+            bytes,
+            mnemonic: mnemonic.to_string(),
+            operands: operands.trim().to_string(),
+            relocs: vec![],
+        });
+    }
+    for insts in sections.values_mut() {
+        insts.sort_by_key(|ins| ins.address);
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `gcc -O1 -fno-pic -falign-functions=16 -c objdump-x86_64.c && objdump -dr objdump-x86_64.o`
+    const X86_64: &str = include_str!("../../tests/data/objdump-x86_64.txt");
+
+    fn opaque(s: &str) -> ObjInsArg { ObjInsArg::Arg(ObjInsArgValue::Opaque(s.to_string())) }
+
+    fn register(s: &str) -> ObjInsArg { ObjInsArg::Arg(ObjInsArgValue::Register(s.to_string())) }
+
+    fn arch(family: IsaFamily) -> ObjArchObjdump {
+        ObjArchObjdump {
+            endianness: Endianness::Little,
+            family,
+            block_ends: vec![],
+            section_names: HashMap::new(),
+            sections: HashMap::new(),
+            reloc_names: HashMap::new(),
+            reloc_kinds: HashMap::new(),
+        }
+    }
+
+    /// Instruction as printed by objdump, without relocations.
+    fn ins(text: &str) -> ObjIns {
+        let (mnemonic, operands) = text.split_once(' ').unwrap_or((text, ""));
+        ObjIns {
+            address: 0,
+            code: 0,
+            op: 0,
+            mnemonic: mnemonic.to_string(),
+            args: split_operands(operands)
+                .into_iter()
+                .map(|arg| ObjInsArg::Arg(operand_value(arg)))
+                .collect(),
+            reloc: None,
+            branch_dest: None,
+            line: None,
+            orig: Some(text.to_string()),
+            delay_slot: None,
+        }
+    }
+
+    #[test]
+    fn parse_objdump_x86_64() {
+        let sections = parse_objdump(X86_64).unwrap();
+        assert_eq!(sections.keys().collect::<Vec<_>>(), [".text"]);
+        let insts = &sections[".text"];
+        assert_eq!(insts.len(), 25);
+        assert_eq!(insts[0].address, 0);
+        assert_eq!(insts[0].bytes, [0x53]);
+        assert_eq!(insts[0].mnemonic, "push");
+        assert_eq!(insts[0].operands, "%rbx");
+        assert_eq!(insts.last().unwrap().address, 0x5a);
+
+        // Bytes continued on the next line
+        let lea = insts.iter().find(|ins| ins.address == 0xd).unwrap();
+        assert_eq!(lea.bytes, [0x48, 0x8d, 0x14, 0xbd, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(lea.operands, "0x0(,%rdi,4),%rdx");
+        assert_eq!(lea.relocs, [ObjdumpReloc {
+            address: 0x11,
+            name: "R_X86_64_32S".to_string(),
+            target: "table".to_string(),
+            addend: 0,
+        }]);
+        let movabs = insts.iter().find(|ins| ins.address == 0x40).unwrap();
+        assert_eq!(movabs.bytes.len(), 10);
+
+        let call = insts.iter().find(|ins| ins.address == 0x27).unwrap();
+        assert_eq!(call.mnemonic, "call");
+        assert_eq!(call.operands, "2c <sum+0x2c>");
+        assert_eq!(call.relocs, [ObjdumpReloc {
+            address: 0x28,
+            name: "R_X86_64_PLT32".to_string(),
+            target: "callee".to_string(),
+            addend: -4,
+        }]);
+
+        // Trailing comment stripped, relocation after continued bytes
+        let store = insts.iter().find(|ins| ins.address == 0x50).unwrap();
+        assert_eq!(store.mnemonic, "movl");
+        assert_eq!(store.operands, "$0x12345678,0x0(%rip)");
+        assert_eq!(store.bytes.len(), 10);
+        assert_eq!(store.relocs.len(), 1);
+        assert_eq!(store.relocs[0].address, 0x52);
+
+        let ret = insts.iter().find(|ins| ins.address == 0x35).unwrap();
+        assert_eq!((ret.mnemonic.as_str(), ret.operands.as_str()), ("ret", ""));
+    }
+
+    #[test]
+    fn parse_reloc_addends() {
+        let reloc = parse_reloc("30", " R_X86_64_PC32\tcounter-0x4").unwrap();
+        assert_eq!((reloc.address, reloc.name.as_str()), (0x30, "R_X86_64_PC32"));
+        assert_eq!((reloc.target.as_str(), reloc.addend), ("counter", -4));
+        let reloc = parse_reloc("8", " R_ARM_ABS32\tdata+0x10").unwrap();
+        assert_eq!((reloc.target.as_str(), reloc.addend), ("data", 0x10));
+        let reloc = parse_reloc("c", " R_SH_DIR32\t_foo").unwrap();
+        assert_eq!((reloc.target.as_str(), reloc.addend), ("_foo", 0));
+        // Leading `-` or `+` is part of the name
+        let reloc = parse_reloc("c", " R_SH_DIR32\t-x").unwrap();
+        assert_eq!((reloc.target.as_str(), reloc.addend), ("-x", 0));
+        // Instruction lines are not relocations
+        assert_eq!(parse_reloc("4", "\te8 00 00 00 00\tcall 9 <main+0x9>"), None);
+    }
+
+    #[test]
+    fn strip_comments() {
+        assert_eq!(
+            strip_comment("mov    0x0(%rip),%eax        # 34 <sum+0x34>"),
+            "mov    0x0(%rip),%eax"
+        );
+        assert_eq!(strip_comment("mov.l\t8 <f+0x8>,r1\t! 0x0"), "mov.l\t8 <f+0x8>,r1");
+        assert_eq!(strip_comment("ldr\tr0, [pc, #4]\t@ (8 <f+0x8>)"), "ldr\tr0, [pc, #4]");
+        // Immediates aren't comments
+        assert_eq!(strip_comment("add\tr0, r0, #4"), "add\tr0, r0, #4");
+        assert_eq!(strip_comment("mov\t#-1,r0"), "mov\t#-1,r0");
+    }
+
+    #[test]
+    fn split_operands_nested() {
+        assert_eq!(split_operands("0x0(,%rdi,4),%rdx"), ["0x0(,%rdi,4)", "%rdx"]);
+        assert_eq!(split_operands("r0, [r1, #4]"), ["r0", "[r1, #4]"]);
+        assert_eq!(split_operands("{r4, lr}"), ["{r4, lr}"]);
+        assert_eq!(split_operands("2c <sum+0x2c>"), ["2c <sum+0x2c>"]);
+        assert!(split_operands("").is_empty());
+    }
+
+    #[test]
+    fn insert_reloc_args() {
+        // Call to an address outside of the function
+        let mut args = vec![opaque("2c <sum+0x2c>")];
+        insert_reloc_arg(&mut args);
+        assert_eq!(args, [ObjInsArg::Reloc]);
+
+        // Offset from a base register
+        let mut args = vec![opaque("0x0(%rip)"), register("%eax")];
+        insert_reloc_arg(&mut args);
+        assert_eq!(args, [ObjInsArg::RelocWithBase, register("%rip"), register("%eax")]);
+
+        // Plain immediate
+        let mut args = vec![opaque("$0x0"), register("%eax")];
+        insert_reloc_arg(&mut args);
+        assert_eq!(args, [ObjInsArg::Reloc, register("%eax")]);
+
+        // No candidate operand
+        let mut args = vec![register("%eax")];
+        insert_reloc_arg(&mut args);
+        assert_eq!(args, [register("%eax"), ObjInsArg::Reloc]);
+    }
+
+    #[test]
+    fn reloc_kinds_from_names() {
+        assert_eq!(reloc_kind_from_name("R_X86_64_PLT32"), RelocationKind::PltRelative);
+        assert_eq!(reloc_kind_from_name("R_X86_64_PC32"), RelocationKind::Relative);
+        assert_eq!(reloc_kind_from_name("R_X86_64_GOTPCREL"), RelocationKind::GotRelative);
+        assert_eq!(reloc_kind_from_name("R_386_GOT32"), RelocationKind::Got);
+        assert_eq!(reloc_kind_from_name("R_ARM_REL32"), RelocationKind::Relative);
+        assert_eq!(reloc_kind_from_name("R_X86_64_32S"), RelocationKind::Absolute);
+        assert_eq!(reloc_kind_from_name("R_SH_DIR32"), RelocationKind::Absolute);
+        // The architecture prefix isn't a relocation kind
+        assert_eq!(reloc_kind_from_name("R_PPC_ADDR32"), RelocationKind::Absolute);
+    }
+
+    #[test]
+    fn operand_values() {
+        for reg in ["%eax", "%r10d", "r0", "sp", "fr4", "d0.l"] {
+            assert_eq!(operand_value(reg), ObjInsArgValue::Register(reg.to_string()), "{reg}");
+        }
+        for imm in ["$0x10", "#4", "#-1", "0x18", "-8", "2c <sum+0x2c>", "0x0(%rip)", "[r1, #4]"] {
+            assert_eq!(operand_value(imm), ObjInsArgValue::Opaque(imm.to_string()), "{imm}");
+        }
+    }
+
+    #[test]
+    fn padding_instructions() {
+        let arch = arch(IsaFamily::X86);
+        for orig in [
+            "nop",
+            "xchg %ax,%ax",
+            "nopl (%rax)",
+            "nopl 0x0(%rax,%rax,1)",
+            "nopw 0x0(%rax,%rax,1)",
+            "data16 cs nopw 0x0(%rax,%rax,1)",
+            "cs nopw 0x0(%rax,%rax,1)",
+            "int3",
+        ] {
+            assert!(arch.is_padding(&ins(orig)), "{orig}");
+        }
+        for orig in ["xchg %eax,%ebx", "ret", "data16 movw $0x1,(%rax)"] {
+            assert!(!arch.is_padding(&ins(orig)), "{orig}");
+        }
+    }
+
+    #[test]
+    fn file_formats() {
+        assert_eq!(parse_format(X86_64), Some("elf64-x86-64"));
+        for (format, family) in [
+            ("elf64-x86-64", IsaFamily::X86),
+            ("elf32-i386", IsaFamily::X86),
+            ("elf32-littlearm", IsaFamily::Arm),
+            ("elf32-bigarm", IsaFamily::Arm),
+            ("elf64-littleaarch64", IsaFamily::Aarch64),
+            ("elf32-sh", IsaFamily::SuperH),
+            ("elf32-shl", IsaFamily::SuperH),
+            ("elf32-sh-linux", IsaFamily::SuperH),
+            ("elf32-m68k", IsaFamily::M68k),
+            ("elf32-avr", IsaFamily::Unknown),
+            ("binary", IsaFamily::Unknown),
+        ] {
+            assert_eq!(IsaFamily::from_format(format), family, "{format}");
+        }
+    }
+
+    #[test]
+    fn block_ends() {
+        for (family, ends, falls_through) in [
+            (
+                IsaFamily::X86,
+                &["jmp 10 <f+0x10>", "jmp *%rax", "ret", "retq", "ud2"][..],
+                &["jne 10 <f+0x10>", "call 10 <f+0x10>"][..],
+            ),
+            (
+                IsaFamily::Arm,
+                &[
+                    "b 10 <f+0x10>",
+                    "b.n 10 <f+0x10>",
+                    "bx lr",
+                    "pop {r4, pc}",
+                    "ldmia sp!, {r4, pc}",
+                    "mov pc, lr",
+                    "ldr pc, [sp], #4",
+                ],
+                &[
+                    "beq 10 <f+0x10>",
+                    "bl 10 <f+0x10>",
+                    "bxeq lr",
+                    "pop {r4, lr}",
+                    "mov r0, lr",
+                    "ldr r0, [pc, #4]",
+                ],
+            ),
+            (IsaFamily::Aarch64, &["b 10 <f+0x10>", "br x16", "ret"], &[
+                "b.eq 10 <f+0x10>",
+                "bl 10 <f+0x10>",
+                "cbz x0, 10 <f+0x10>",
+            ]),
+            (IsaFamily::SuperH, &["bra 10 <f+0x10>", "braf r1", "jmp @r1", "rts", "rte"], &[
+                "bt 10 <f+0x10>",
+                "bf/s 10 <f+0x10>",
+                "bsr 10 <f+0x10>",
+                "jsr @r1",
+            ]),
+            (
+                IsaFamily::M68k,
+                &["bras 10 <f+0x10>", "braw 10 <f+0x10>", "jmp %a0@", "rts", "rte"],
+                &["beqs 10 <f+0x10>", "bsrs 10 <f+0x10>", "jsr %a0@"],
+            ),
+            (IsaFamily::Unknown, &[], &["rts", "ret", "b 10 <f+0x10>"]),
+        ] {
+            let arch = arch(family);
+            for text in ends {
+                assert!(arch.ends_block(&ins(text)), "{family:?} {text}");
+            }
+            for text in falls_through {
+                assert!(!arch.ends_block(&ins(text)), "{family:?} {text}");
+            }
+        }
+
+        // Configured mnemonics extend the built-in ones
+        let mut arch = arch(IsaFamily::Unknown);
+        arch.block_ends = vec!["jr".to_string()];
+        assert!(arch.ends_block(&ins("jr $ra")));
+        assert!(!arch.ends_block(&ins("jal 10 <f+0x10>")));
+    }
+
+    #[test]
+    fn superh_delay_slots() {
+        let text = "\
+f.o:     file format elf32-sh
+
+
+Disassembly of section .text:
+
+00000000 <_f>:
+   0:\t01 89       \tbt\t6 <_f+0x6>
+   2:\t0b 00       \trts\t
+   4:\t09 00       \tnop
+   6:\tfc af       \tbra\t2 <_f+0x2>
+   8:\t09 00       \tnop
+";
+        let mut arch = arch(IsaFamily::from_format(parse_format(text).unwrap()));
+        arch.sections = parse_objdump(text).unwrap();
+        arch.section_names.insert(1, ".text".to_string());
+        let result = arch.process_code(&[0; 10], 0, 1, &[], &None).unwrap();
+        let slots = result.insts.iter().map(|ins| ins.delay_slot).collect::<Vec<_>>();
+        assert_eq!(slots, [
+            None,
+            None,
+            Some(ObjDelaySlot::Always),
+            None,
+            Some(ObjDelaySlot::Always)
+        ]);
+
+        // Delay slots stay in the block of their branch
+        let blocks = crate::diff::cfg::basic_blocks(&arch, &result.insts);
+        let blocks =
+            blocks.iter().map(|b| (b.insts.clone(), b.successors.clone())).collect::<Vec<_>>();
+        assert_eq!(blocks, [(0..1, vec![2, 1]), (1..3, vec![]), (3..5, vec![1])]);
+    }
+}
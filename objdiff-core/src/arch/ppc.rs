@@ -0,0 +1,200 @@
+use anyhow::{bail, Result};
+use object::{elf, File, Relocation, RelocationKind};
+use ppc750cl::{disasm_iter, Argument, SimplifiedIns};
+
+use crate::{
+    arch::{find_reloc, ObjArch, RelocFormat},
+    diff::ProcessCodeResult,
+    obj::{
+        demangle::Demangler, ObjIns, ObjInsArg, ObjInsArgValue, ObjLineInfo, ObjReloc, ObjSdaBase,
+        ObjSection,
+    },
+};
+
+// Relative relocation, can be Simm or BranchOffset
+fn is_relative_arg(arg: &ObjInsArg) -> bool {
+    matches!(arg, ObjInsArg::Arg(ObjInsArgValue::Signed(_)) | ObjInsArg::BranchOffset(_))
+}
+
+// Relative or absolute relocation, can be Uimm, Simm or Offset
+fn is_rel_abs_arg(arg: &ObjInsArg) -> bool {
+    matches!(
+        arg,
+        ObjInsArg::Arg(ObjInsArgValue::Signed(_) | ObjInsArgValue::Unsigned(_))
+            | ObjInsArg::ArgWithBase(ObjInsArgValue::Signed(_))
+    )
+}
+
+fn is_offset_arg(arg: &ObjInsArg) -> bool {
+    matches!(arg, ObjInsArg::ArgWithBase(ObjInsArgValue::Signed(_)))
+}
+
+pub struct ObjArchPpc {}
+
+impl ObjArchPpc {
+    pub fn new(_file: &File<'_>) -> Result<Self> { Ok(Self {}) }
+}
+
+impl ObjArch for ObjArchPpc {
+    fn process_code(
+        &self,
+        data: &[u8],
+        address: u64,
+        _section_index: usize,
+        relocs: &[ObjReloc],
+        line_info: &Option<ObjLineInfo>,
+    ) -> Result<ProcessCodeResult> {
+        let ins_count = data.len() / 4;
+        let mut ops = Vec::<u8>::with_capacity(ins_count);
+        let mut insts = Vec::<ObjIns>::with_capacity(ins_count);
+        for mut ins in disasm_iter(data, address as u32) {
+            let reloc = find_reloc(relocs, ins.addr as u64, ins.addr as u64 + 4);
+            if let Some(reloc) = reloc {
+                // Zero out relocations
+                ins.code = match reloc.kind {
+                    RelocationKind::Elf(elf::R_PPC_EMB_SDA21) => ins.code & !0x1FFFFF,
+                    RelocationKind::Elf(elf::R_PPC_REL24) => ins.code & !0x3FFFFFC,
+                    RelocationKind::Elf(elf::R_PPC_REL14) => ins.code & !0xFFFC,
+                    RelocationKind::Elf(elf::R_PPC_ADDR16_HI)
+                    | RelocationKind::Elf(elf::R_PPC_ADDR16_HA)
+                    | RelocationKind::Elf(elf::R_PPC_ADDR16_LO) => ins.code & !0xFFFF,
+                    _ => ins.code,
+                };
+            }
+            let simplified = ins.clone().simplified();
+            let mut args: Vec<ObjInsArg> = simplified
+                .args
+                .iter()
+                .map(|a| match a {
+                    Argument::Simm(simm) => ObjInsArg::Arg(ObjInsArgValue::Signed(simm.0)),
+                    Argument::Uimm(uimm) => ObjInsArg::Arg(ObjInsArgValue::Unsigned(uimm.0)),
+                    Argument::Offset(offset) => {
+                        ObjInsArg::ArgWithBase(ObjInsArgValue::Signed(offset.0))
+                    }
+                    Argument::BranchDest(dest) => ObjInsArg::BranchOffset(dest.0),
+                    Argument::OpaqueU(_) => ObjInsArg::Arg(ObjInsArgValue::Opaque(a.to_string())),
+                    _ => ObjInsArg::Arg(ObjInsArgValue::Register(a.to_string())),
+                })
+                .collect();
+            if let Some(reloc) = reloc {
+                match reloc.kind {
+                    RelocationKind::Elf(elf::R_PPC_EMB_SDA21) => {
+                        args = vec![args[0].clone(), ObjInsArg::Reloc];
+                    }
+                    RelocationKind::Elf(elf::R_PPC_REL24)
+                    | RelocationKind::Elf(elf::R_PPC_REL14) => {
+                        let arg =
+                            args.iter_mut().rfind(|a| is_relative_arg(a)).ok_or_else(|| {
+                                anyhow::Error::msg("Failed to locate rel arg for reloc")
+                            })?;
+                        *arg = ObjInsArg::Reloc;
+                    }
+                    RelocationKind::Elf(elf::R_PPC_ADDR16_HI)
+                    | RelocationKind::Elf(elf::R_PPC_ADDR16_HA)
+                    | RelocationKind::Elf(elf::R_PPC_ADDR16_LO) => {
+                        let arg =
+                            args.iter_mut().rfind(|a| is_rel_abs_arg(a)).ok_or_else(|| {
+                                anyhow::Error::msg("Failed to locate rel/abs arg for reloc")
+                            })?;
+                        *arg = if is_offset_arg(arg) {
+                            ObjInsArg::RelocWithBase
+                        } else {
+                            ObjInsArg::Reloc
+                        };
+                    }
+                    _ => {}
+                }
+            }
+            ops.push(simplified.ins.op as u8);
+            let line = line_info.as_ref().and_then(|info| info.line(simplified.ins.addr as u64));
+            insts.push(ObjIns {
+                address: simplified.ins.addr,
+                code: simplified.ins.code,
+                mnemonic: format!("{}{}", simplified.mnemonic, simplified.suffix),
+                args,
+                reloc: reloc.cloned(),
+                op: ins.op as u8,
+                branch_dest: None,
+                line,
+                orig: Some(format!("{}", SimplifiedIns::basic_form(ins))),
+                delay_slot: None,
+            });
+        }
+        Ok(ProcessCodeResult { ops, insts })
+    }
+
+    fn implicit_addend(
+        &self,
+        section: &ObjSection,
+        address: u64,
+        reloc: &Relocation,
+    ) -> Result<i64> {
+        match reloc.kind() {
+            RelocationKind::Absolute => {
+                let data = section.data[address as usize..address as usize + 4].try_into()?;
+                Ok(u32::from_be_bytes(data) as i64)
+            }
+            kind => bail!("Unsupported PPC implicit relocation {kind:?}"),
+        }
+    }
+
+    fn reloc_name(&self, kind: RelocationKind) -> Option<&str> {
+        match kind {
+            RelocationKind::Elf(kind) => match kind {
+                elf::R_PPC_ADDR16_LO => Some("R_PPC_ADDR16_LO"),
+                elf::R_PPC_ADDR16_HI => Some("R_PPC_ADDR16_HI"),
+                elf::R_PPC_ADDR16_HA => Some("R_PPC_ADDR16_HA"),
+                elf::R_PPC_REL24 => Some("R_PPC_REL24"),
+                elf::R_PPC_REL14 => Some("R_PPC_REL14"),
+                elf::R_PPC_EMB_SDA21 => Some("R_PPC_EMB_SDA21"),
+                _ => None,
+            },
+            RelocationKind::Absolute => Some("R_PPC_ADDR32"),
+            _ => None,
+        }
+    }
+
+    fn display_reloc(&self, kind: RelocationKind) -> Option<RelocFormat> {
+        let suffix = match kind {
+            RelocationKind::Elf(elf::R_PPC_ADDR16_LO) => "@l",
+            RelocationKind::Elf(elf::R_PPC_ADDR16_HI) => "@h",
+            RelocationKind::Elf(elf::R_PPC_ADDR16_HA) => "@ha",
+            RelocationKind::Elf(elf::R_PPC_EMB_SDA21) => "@sda21",
+            RelocationKind::Elf(elf::R_PPC_REL24 | elf::R_PPC_REL14) => "",
+            _ => return None,
+        };
+        Some(RelocFormat { prefix: "", suffix })
+    }
+
+    fn sda_base(&self, kind: RelocationKind, section: Option<&str>) -> Option<ObjSdaBase> {
+        if kind != RelocationKind::Elf(elf::R_PPC_EMB_SDA21) {
+            return None;
+        }
+        // The base of an extern target is only known once linked
+        match section? {
+            ".sdata" | ".sbss" => Some(ObjSdaBase::Sda),
+            ".sdata2" | ".sbss2" => Some(ObjSdaBase::Sda2),
+            ".PPC.EMB.sdata0" | ".PPC.EMB.sbss0" => Some(ObjSdaBase::Sda0),
+            _ => None,
+        }
+    }
+
+    fn is_indirect_branch(&self, ins: &ObjIns) -> bool { ins.mnemonic == "bctr" }
+
+    fn ends_block(&self, ins: &ObjIns) -> bool {
+        matches!(ins.mnemonic.as_str(), "b" | "blr" | "bctr")
+    }
+
+    // Linkers pad with zero words as well as nops
+    fn is_padding(&self, ins: &ObjIns) -> bool { ins.mnemonic == "nop" || ins.code == 0 }
+
+    fn demangler(&self) -> Demangler { Demangler::CodeWarrior }
+
+    fn dwarf_register_name(&self, register: u16) -> Option<String> {
+        match register {
+            0..=31 => Some(format!("r{register}")),
+            32..=63 => Some(format!("f{}", register - 32)),
+            _ => None,
+        }
+    }
+}
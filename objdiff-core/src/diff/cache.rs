@@ -0,0 +1,387 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use twox_hash::XxHash64;
+
+use crate::{
+    arch::find_reloc,
+    diff::{
+        code::CodeContext, equiv::EquivalenceRule, DiffAlg, DiffObjConfig, RelocDiffOptions,
+        ScoreWeights,
+    },
+    obj::{ObjBlockDiff, ObjInsDiff, ObjSymbol},
+};
+
+/// Code diff results from a previous run, keyed by the fingerprints of both symbols.
+/// Passed to [`diff_objs_cached`](crate::diff::diff_objs_cached) to skip re-diffing
+/// functions that did not change between builds.
+#[derive(Default)]
+pub struct DiffCache {
+    settings: Option<CodeSettings>,
+    entries: HashMap<DiffKey, CachedDiff>,
+}
+
+pub(crate) type DiffKey = (u64, u64);
+
+/// The fields of [`DiffObjConfig`] affecting code diffs.
+#[derive(PartialEq)]
+struct CodeSettings {
+    code_alg: DiffAlg,
+    relax_reloc_diffs: bool,
+    reloc_diffs: RelocDiffOptions,
+    block_diff: bool,
+    group_delay_slots: bool,
+    score_weights: ScoreWeights,
+    equivalences: Vec<EquivalenceRule>,
+}
+
+impl CodeSettings {
+    fn new(config: &DiffObjConfig) -> Self {
+        Self {
+            code_alg: config.code_alg,
+            relax_reloc_diffs: config.relax_reloc_diffs,
+            reloc_diffs: config.reloc_diffs,
+            block_diff: config.block_diff,
+            group_delay_slots: config.group_delay_slots,
+            score_weights: config.score_weights,
+            equivalences: config.equivalences.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CachedDiff {
+    left: CachedSymbol,
+    right: CachedSymbol,
+}
+
+#[derive(Clone)]
+struct CachedSymbol {
+    address: u64,
+    instructions: Vec<ObjInsDiff>,
+    match_percent: Option<f32>,
+    diff_score: Option<u64>,
+    blocks: Vec<ObjBlockDiff>,
+}
+
+impl CachedSymbol {
+    fn new(symbol: &ObjSymbol) -> Self {
+        Self {
+            address: symbol.address,
+            instructions: symbol.instructions.clone(),
+            match_percent: symbol.match_percent,
+            diff_score: symbol.diff_score,
+            blocks: symbol.blocks.clone(),
+        }
+    }
+
+    /// Restores the cached result, adjusting addresses for a symbol that moved
+    /// within its section.
+    fn apply(&self, ctx: CodeContext, symbol: &mut ObjSymbol) {
+        let delta = symbol.address.wrapping_sub(self.address);
+        let rebase = |addr: u32| (addr as u64).wrapping_add(delta) as u32;
+        symbol.instructions = self.instructions.clone();
+        for table in symbol.instructions.iter_mut().filter_map(|i| i.jump_table.as_mut()) {
+            table.targets.iter_mut().for_each(|target| *target = rebase(*target));
+        }
+        for ins in symbol.instructions.iter_mut().filter_map(|i| i.ins.as_mut()) {
+            ins.address = rebase(ins.address);
+            ins.branch_dest = ins.branch_dest.map(rebase);
+            // Relocation targets and line numbers may differ without affecting the diff
+            if let Some(reloc) = &mut ins.reloc {
+                let address = reloc.address.wrapping_add(delta);
+                if let Some(new_reloc) = find_reloc(ctx.relocs, address, address + 1) {
+                    *reloc = new_reloc.clone();
+                }
+            }
+            ins.line = ctx.line_info.as_ref().and_then(|info| info.line(ins.address as u64));
+        }
+        symbol.match_percent = self.match_percent;
+        symbol.diff_score = self.diff_score;
+        symbol.blocks = self.blocks.clone();
+        symbol.truncated = false;
+    }
+}
+
+impl DiffCache {
+    /// Drops all entries if the settings affecting code diffs changed.
+    pub(crate) fn validate(&mut self, config: &DiffObjConfig) {
+        let settings = CodeSettings::new(config);
+        if self.settings.as_ref() != Some(&settings) {
+            self.settings = Some(settings);
+            self.entries.clear();
+        }
+    }
+
+    /// Restores the previous result for a symbol pair, if any.
+    pub(crate) fn apply(
+        &self,
+        key: DiffKey,
+        left_ctx: CodeContext,
+        right_ctx: CodeContext,
+        left_symbol: &mut ObjSymbol,
+        right_symbol: &mut ObjSymbol,
+    ) -> bool {
+        let Some(entry) = self.entries.get(&key) else {
+            return false;
+        };
+        entry.left.apply(left_ctx, left_symbol);
+        entry.right.apply(right_ctx, right_symbol);
+        true
+    }
+
+    /// Replaces the cache contents with the results of the latest run.
+    pub(crate) fn replace(&mut self, entries: impl IntoIterator<Item = (DiffKey, CachedDiff)>) {
+        self.entries = entries.into_iter().collect();
+    }
+}
+
+impl CachedDiff {
+    /// Captures a completed diff, or `None` if it was truncated.
+    pub(crate) fn new(left_symbol: &ObjSymbol, right_symbol: &ObjSymbol) -> Option<Self> {
+        if left_symbol.truncated || right_symbol.truncated {
+            return None;
+        }
+        Some(Self { left: CachedSymbol::new(left_symbol), right: CachedSymbol::new(right_symbol) })
+    }
+}
+
+pub(crate) fn diff_key(
+    left_ctx: CodeContext,
+    right_ctx: CodeContext,
+    left_symbol: &ObjSymbol,
+    right_symbol: &ObjSymbol,
+) -> DiffKey {
+    (fingerprint(left_ctx, left_symbol), fingerprint(right_ctx, right_symbol))
+}
+
+/// Hashes everything the code diff depends on: the symbol's bytes, the
+/// alignment of its end, its relocations and its jump tables, relative to the
+/// symbol's address.
+/// Relocation targets are hashed by name and addend, so that moving the
+/// target elsewhere doesn't invalidate the diff, except for targets within the
+/// symbol itself, where the offset matters for branch resolution.
+fn fingerprint(ctx: CodeContext, symbol: &ObjSymbol) -> u64 {
+    let mut hasher = XxHash64::default();
+    let start = symbol.section_address as usize;
+    ctx.data[start..start + symbol.size as usize].hash(&mut hasher);
+    let end_address = symbol.address + symbol.size;
+    // Trailing padding depends on the alignment of the function's end
+    end_address.trailing_zeros().min(63).hash(&mut hasher);
+    ctx.section_align.hash(&mut hasher);
+    let first = ctx.relocs.partition_point(|r| r.address < symbol.address);
+    for reloc in ctx.relocs[first..].iter().take_while(|r| r.address < end_address) {
+        (reloc.address - symbol.address).hash(&mut hasher);
+        reloc.kind.hash(&mut hasher);
+        reloc.target.name.hash(&mut hasher);
+        reloc.target.addend.hash(&mut hasher);
+        if reloc.target_section.as_deref() == Some(ctx.section_name)
+            && (symbol.address..end_address).contains(&reloc.target.address)
+        {
+            (reloc.target.address - symbol.address).hash(&mut hasher);
+        }
+        reloc.target.flags.0.bits().hash(&mut hasher);
+        reloc.target_section.hash(&mut hasher);
+        reloc.unpaired.hash(&mut hasher);
+    }
+    let first = ctx.jump_tables.partition_point(|t| t.load_address < symbol.address);
+    for table in ctx.jump_tables[first..].iter().take_while(|t| t.load_address < end_address) {
+        (table.load_address - symbol.address).hash(&mut hasher);
+        table.name.hash(&mut hasher);
+        for target in &table.targets {
+            (target - symbol.address).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use object::RelocationKind;
+
+    use super::*;
+    use crate::{
+        diff::{
+            diff_objs_cached,
+            tests::{code_section, symbol, test_obj},
+        },
+        obj::{ObjInfo, ObjJumpTable, ObjReloc},
+    };
+
+    /// Loads a relocated value, dispatches through a jump table to one of
+    /// words 3 and 4, and branches over word 4.
+    const WORDS: [u32; 6] = [0x01010000, 0x02020002, 0x20030000, 0x10000008, 0x03030003, 0];
+
+    /// Object with function `f` made of `words` after a function of `pad`
+    /// words, relocating against `target` in `.data`.
+    fn build(pad: usize, words: &[u32], target: ObjSymbol) -> ObjInfo {
+        let mut all = vec![0x01000000; pad];
+        all.extend_from_slice(words);
+        let mut section = code_section(&all, &[("pad", 0), ("f", pad)]);
+        let base = pad as u64 * 4;
+        section.relocations.push(ObjReloc {
+            kind: RelocationKind::Absolute,
+            address: base,
+            target,
+            target_section: Some(".data".to_string()),
+            unpaired: false,
+            sda_base: None,
+        });
+        section.jump_tables.push(ObjJumpTable {
+            load_address: base + 4,
+            name: "jtbl".to_string(),
+            targets: vec![base + 12, base + 16],
+        });
+        test_obj(vec![section])
+    }
+
+    fn data(address: u64) -> ObjSymbol { symbol("data", address, 4) }
+
+    /// Replaces the cached match percentages with a sentinel, telling results
+    /// restored from the cache apart from fresh diffs.
+    fn mark(cache: &mut DiffCache) {
+        for entry in cache.entries.values_mut() {
+            entry.left.match_percent = Some(-1.0);
+            entry.right.match_percent = Some(-1.0);
+        }
+    }
+
+    fn get<'a>(obj: &'a ObjInfo, name: &str) -> &'a ObjSymbol {
+        obj.sections[0].symbols.iter().find(|s| s.name == name).unwrap()
+    }
+
+    fn cached(obj: &ObjInfo, name: &str) -> bool { get(obj, name).match_percent == Some(-1.0) }
+
+    /// Diffs a first build, then `left` and `right` against it, returning them.
+    fn rebuild(
+        cache: &mut DiffCache,
+        config: &DiffObjConfig,
+        mut left: ObjInfo,
+        mut right: ObjInfo,
+    ) -> (ObjInfo, ObjInfo) {
+        let mut first_left = build(1, &WORDS, data(0x100));
+        let mut first_right = build(1, &WORDS, data(0x100));
+        diff_objs_cached(config, Some(&mut first_left), Some(&mut first_right), cache).unwrap();
+        mark(cache);
+        diff_objs_cached(config, Some(&mut left), Some(&mut right), cache).unwrap();
+        (left, right)
+    }
+
+    #[test]
+    fn hit_after_rebuild() {
+        let config = DiffObjConfig::default();
+        let mut cache = DiffCache::default();
+        let (left, right) = rebuild(
+            &mut cache,
+            &config,
+            build(1, &WORDS, data(0x100)),
+            build(1, &WORDS, data(0x100)),
+        );
+        for obj in [&left, &right] {
+            assert!(cached(obj, "pad") && cached(obj, "f"));
+        }
+        assert_eq!(cache.entries.len(), 2);
+    }
+
+    #[test]
+    fn miss_on_changed_bytes() {
+        let config = DiffObjConfig::default();
+        let mut cache = DiffCache::default();
+        let mut words = WORDS;
+        words[1] = 0x02020003;
+        let (left, right) = rebuild(
+            &mut cache,
+            &config,
+            build(1, &WORDS, data(0x100)),
+            build(1, &words, data(0x100)),
+        );
+        assert!(cached(&left, "pad") && cached(&right, "pad"));
+        assert!(!cached(&left, "f") && !cached(&right, "f"));
+        assert_eq!(get(&left, "f").match_percent, Some(5.0 / 6.0 * 100.0));
+    }
+
+    #[test]
+    fn miss_on_changed_relocs() {
+        let config = DiffObjConfig::default();
+        let renamed = build(1, &WORDS, symbol("other", 0x100, 4));
+        let offset = build(1, &WORDS, ObjSymbol { addend: 4, ..data(0x100) });
+        let mut unpaired = build(1, &WORDS, data(0x100));
+        unpaired.sections[0].relocations[0].unpaired = true;
+        for right in [renamed, offset, unpaired] {
+            let mut cache = DiffCache::default();
+            let (left, right) = rebuild(&mut cache, &config, build(1, &WORDS, data(0x100)), right);
+            assert!(cached(&left, "pad") && cached(&right, "pad"));
+            assert!(!cached(&left, "f") && !cached(&right, "f"));
+        }
+    }
+
+    #[test]
+    fn miss_on_changed_settings() {
+        let mut cache = DiffCache::default();
+        let mut first_left = build(1, &WORDS, data(0x100));
+        let mut first_right = build(1, &WORDS, data(0x100));
+        let config = DiffObjConfig::default();
+        diff_objs_cached(&config, Some(&mut first_left), Some(&mut first_right), &mut cache)
+            .unwrap();
+        mark(&mut cache);
+        let (mut left, mut right) = (build(1, &WORDS, data(0x100)), build(1, &WORDS, data(0x100)));
+        let config = DiffObjConfig { block_diff: true, ..Default::default() };
+        diff_objs_cached(&config, Some(&mut left), Some(&mut right), &mut cache).unwrap();
+        for obj in [&left, &right] {
+            assert!(!cached(obj, "pad") && !cached(obj, "f"));
+        }
+    }
+
+    /// Object with function `f` of a word and a `nop` after `pad` words, in a
+    /// section aligned to `align`.
+    fn padded(pad: usize, align: u64) -> ObjInfo {
+        let mut words = vec![0x01000000; pad];
+        words.extend([0x01010001, 0]);
+        let mut section = code_section(&words, &[("pad", 0), ("f", pad)]);
+        section.align = align;
+        test_obj(vec![section])
+    }
+
+    #[test]
+    fn miss_on_changed_alignment() {
+        let config = DiffObjConfig::default();
+        let padding = |obj: &ObjInfo| get(obj, "f").instructions[1].padding;
+        // Ending `f` at a 16-byte boundary makes the `nop` padding, but not
+        // ending it at an 8-byte one or in a 4-byte aligned section
+        for (pad, align) in [(1, 16), (2, 4)] {
+            let mut cache = DiffCache::default();
+            let (mut left, mut right) = (padded(2, 16), padded(2, 16));
+            diff_objs_cached(&config, Some(&mut left), Some(&mut right), &mut cache).unwrap();
+            assert!(padding(&left) && padding(&right));
+            mark(&mut cache);
+            let (mut left, mut right) = (padded(pad, align), padded(pad, align));
+            diff_objs_cached(&config, Some(&mut left), Some(&mut right), &mut cache).unwrap();
+            assert!(!cached(&left, "f") && !cached(&right, "f"));
+            assert!(!padding(&left) && !padding(&right));
+        }
+    }
+
+    #[test]
+    fn apply_rebases_addresses() {
+        let config = DiffObjConfig::default();
+        let mut cache = DiffCache::default();
+        // `f` moves by 4 words, and the relocation target elsewhere
+        let (_, right) = rebuild(
+            &mut cache,
+            &config,
+            build(5, &WORDS, data(0x200)),
+            build(5, &WORDS, data(0x200)),
+        );
+        let f = get(&right, "f");
+        assert!(cached(&right, "f"));
+        let ins = f.instructions.iter().map(|i| i.ins.as_ref().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ins.iter().map(|i| i.address).collect::<Vec<_>>(), [20, 24, 28, 32, 36, 40]);
+        let reloc = ins[0].reloc.as_ref().unwrap();
+        assert_eq!((reloc.address, reloc.target.address), (20, 0x200));
+        assert_eq!(ins[3].branch_dest, Some(40));
+        let table = f.instructions[2].jump_table.as_ref().unwrap();
+        assert_eq!(table.targets, [32, 36]);
+    }
+}
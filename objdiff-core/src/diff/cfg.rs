@@ -0,0 +1,246 @@
+use std::{borrow::Borrow, collections::BTreeMap, ops::Range};
+
+use similar::{Algorithm, DiffTag};
+
+use crate::{
+    arch::ObjArch,
+    diff::limit::{capture_diff_slices_limited, DiffLimit},
+    obj::{ObjIns, ObjInsArg, ObjInsDiff},
+};
+
+/// Straight-line run of instructions with a single entry point.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    /// Instruction indices covered by the block
+    pub insts: Range<usize>,
+    /// Indices of blocks control can pass to
+    pub successors: Vec<usize>,
+}
+
+/// Resolves the in-function branch target of an instruction.
+fn branch_target(ins: &ObjIns) -> Option<u32> {
+    ins.args
+        .iter()
+        .find_map(|arg| match arg {
+            ObjInsArg::BranchOffset(offset) => Some((ins.address as i32 + offset) as u32),
+            _ => None,
+        })
+        .or(ins.branch_dest)
+        .filter(|&dest| dest != ins.address || ins.reloc.is_none())
+}
+
+/// Splits a function into basic blocks. Blocks start at the function entry, at
+/// branch targets within the function and after branches and their delay slots.
+pub fn basic_blocks<I: Borrow<ObjIns>>(arch: &dyn ObjArch, insts: &[I]) -> Vec<BasicBlock> {
+    if insts.is_empty() {
+        return vec![];
+    }
+    let index_of = insts
+        .iter()
+        .enumerate()
+        .map(|(i, ins)| (ins.borrow().address, i))
+        .collect::<BTreeMap<_, _>>();
+    let targets = insts
+        .iter()
+        .map(|ins| branch_target(ins.borrow()).and_then(|dest| index_of.get(&dest).copied()))
+        .collect::<Vec<_>>();
+    let mut leaders = vec![false; insts.len()];
+    leaders[0] = true;
+    for (i, target) in targets.iter().enumerate() {
+        if let Some(target) = *target {
+            leaders[target] = true;
+        }
+        if target.is_some() || arch.ends_block(insts[i].borrow()) {
+            // A delay slot belongs to its branch
+            let next = if insts.get(i + 1).is_some_and(|ins| ins.borrow().delay_slot.is_some()) {
+                i + 2
+            } else {
+                i + 1
+            };
+            if next < insts.len() {
+                leaders[next] = true;
+            }
+        }
+    }
+    let mut blocks = Vec::<BasicBlock>::new();
+    let mut block_of = vec![0usize; insts.len()];
+    for i in 0..insts.len() {
+        if leaders[i] {
+            blocks.push(BasicBlock { insts: i..i + 1, successors: vec![] });
+        } else {
+            blocks.last_mut().unwrap().insts.end = i + 1;
+        }
+        block_of[i] = blocks.len() - 1;
+    }
+    let block_count = blocks.len();
+    for (idx, block) in blocks.iter_mut().enumerate() {
+        let mut last = block.insts.end - 1;
+        if last > block.insts.start && insts[last].borrow().delay_slot.is_some() {
+            last -= 1;
+        }
+        if let Some(target) = targets[last] {
+            block.successors.push(block_of[target]);
+        }
+        if idx + 1 < block_count
+            && !arch.ends_block(insts[last].borrow())
+            && !block.successors.contains(&(idx + 1))
+        {
+            block.successors.push(idx + 1);
+        }
+    }
+    blocks
+}
+
+/// Basic block of a diffed function, for display.
+#[derive(Debug, Clone)]
+pub struct CfgBlock {
+    /// Rows of [`crate::obj::ObjSymbol::instructions`] covered by the block
+    pub rows: Range<usize>,
+    /// Indices of blocks control can pass to
+    pub successors: Vec<usize>,
+    /// Percentage of identical rows within the block
+    pub match_percent: f32,
+}
+
+/// Builds the control flow graph of a diffed function. Rows without an
+/// instruction belong to the block before them.
+pub fn row_cfg(arch: &dyn ObjArch, rows: &[ObjInsDiff]) -> Vec<CfgBlock> {
+    let (row_idx, insts): (Vec<usize>, Vec<&ObjIns>) =
+        rows.iter().enumerate().filter_map(|(i, row)| row.ins.as_ref().map(|ins| (i, ins))).unzip();
+    let blocks = basic_blocks(arch, &insts);
+    let block_count = blocks.len();
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, block)| {
+            let start = if idx == 0 { 0 } else { row_idx[block.insts.start] };
+            let end = if idx + 1 < block_count {
+                row_idx[blocks[idx + 1].insts.start]
+            } else {
+                rows.len()
+            };
+            let matching = rows[start..end].iter().filter(|row| row.kind.is_match()).count();
+            CfgBlock {
+                rows: start..end,
+                successors: block.successors.clone(),
+                match_percent: matching as f32 / (end - start) as f32 * 100.0,
+            }
+        })
+        .collect()
+}
+
+/// Pair of blocks to diff against each other. A missing side means the block
+/// only exists on the other side.
+pub type BlockPair = (Option<usize>, Option<usize>);
+
+/// Matches basic blocks between two functions by the opcodes they contain.
+/// Identical blocks are aligned first; the remaining blocks between them are
+/// paired in order. Returns the pairs in order, and whether the limit was reached.
+pub fn match_blocks(
+    left_blocks: &[BasicBlock],
+    left_ops: &[u8],
+    right_blocks: &[BasicBlock],
+    right_ops: &[u8],
+    limit: DiffLimit,
+) -> (Vec<BlockPair>, bool) {
+    let signature = |block: &BasicBlock, ops: &[u8]| {
+        twox_hash::xxh3::hash64(&ops[block.insts.clone()]) ^ (block.successors.len() as u64)
+    };
+    let left_sigs = left_blocks.iter().map(|b| signature(b, left_ops)).collect::<Vec<_>>();
+    let right_sigs = right_blocks.iter().map(|b| signature(b, right_ops)).collect::<Vec<_>>();
+    let (ops, truncated) =
+        capture_diff_slices_limited(Algorithm::Patience, &left_sigs, &right_sigs, limit);
+
+    let mut pairs = Vec::new();
+    for op in ops {
+        let (tag, left_range, right_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal | DiffTag::Replace => {
+                let paired = left_range.len().min(right_range.len());
+                pairs.extend(
+                    left_range.clone().zip(right_range.clone()).map(|(l, r)| (Some(l), Some(r))),
+                );
+                pairs.extend(left_range.skip(paired).map(|l| (Some(l), None)));
+                pairs.extend(right_range.skip(paired).map(|r| (None, Some(r))));
+            }
+            DiffTag::Delete => pairs.extend(left_range.map(|l| (Some(l), None))),
+            DiffTag::Insert => pairs.extend(right_range.map(|r| (None, Some(r)))),
+        }
+    }
+    (pairs, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use object::{Relocation, RelocationKind};
+
+    use super::*;
+    use crate::{
+        arch::RelocFormat,
+        diff::ProcessCodeResult,
+        obj::{ObjLineInfo, ObjReloc, ObjSection},
+    };
+
+    /// Architecture where `b` and `ret` end a block.
+    struct TestArch;
+
+    impl ObjArch for TestArch {
+        fn process_code(
+            &self,
+            _data: &[u8],
+            _address: u64,
+            _section_index: usize,
+            _relocs: &[ObjReloc],
+            _line_info: &Option<ObjLineInfo>,
+        ) -> Result<ProcessCodeResult> {
+            unimplemented!()
+        }
+
+        fn implicit_addend(
+            &self,
+            _section: &ObjSection,
+            _address: u64,
+            _reloc: &Relocation,
+        ) -> Result<i64> {
+            unimplemented!()
+        }
+
+        fn reloc_name(&self, _kind: RelocationKind) -> Option<&str> { None }
+
+        fn display_reloc(&self, _kind: RelocationKind) -> Option<RelocFormat> { None }
+
+        fn ends_block(&self, ins: &ObjIns) -> bool { matches!(ins.mnemonic.as_str(), "b" | "ret") }
+    }
+
+    fn ins(address: u32, mnemonic: &str, branch_dest: Option<u32>) -> ObjIns {
+        ObjIns {
+            address,
+            code: 0,
+            op: 0,
+            mnemonic: mnemonic.to_string(),
+            args: vec![],
+            reloc: None,
+            branch_dest,
+            line: None,
+            orig: None,
+            delay_slot: None,
+        }
+    }
+
+    #[test]
+    fn no_fallthrough_after_unconditional_branch() {
+        let insts = [
+            ins(0, "bne", Some(12)),
+            ins(4, "b", Some(16)),
+            ins(8, "add", None),
+            ins(12, "ret", None),
+            ins(16, "add", None),
+        ];
+        let blocks = basic_blocks(&TestArch, &insts);
+        let ranges = blocks.iter().map(|b| b.insts.clone()).collect::<Vec<_>>();
+        assert_eq!(ranges, [0..1, 1..2, 2..3, 3..4, 4..5]);
+        let successors = blocks.iter().map(|b| b.successors.clone()).collect::<Vec<_>>();
+        assert_eq!(successors, [vec![3, 1], vec![4], vec![3], vec![], vec![]]);
+    }
+}
@@ -1,146 +1,194 @@
 use std::{
     cmp::max,
-    collections::BTreeMap,
-    time::{Duration, Instant},
+    collections::{BTreeMap, HashMap},
 };
 
 use anyhow::Result;
-use similar::{capture_diff_slices_deadline, Algorithm};
+use similar::Algorithm;
 
 use crate::{
+    arch::ObjArch,
     diff::{
-        editops::{editops_find, LevEditType},
-        DiffAlg, DiffObjConfig, ProcessCodeResult,
+        cfg::{basic_blocks, match_blocks, BasicBlock},
+        editops::{editops_find_until, LevEditType},
+        equiv::InsPattern,
+        limit::{capture_diff_slices_limited, DiffLimit},
+        DiffAlg, DiffObjConfig, ProcessCodeResult, ScoreWeights,
     },
-    obj,
     obj::{
-        ObjArchitecture, ObjInfo, ObjInsArg, ObjInsArgDiff, ObjInsBranchFrom, ObjInsBranchTo,
-        ObjInsDiff, ObjInsDiffKind, ObjReloc, ObjSymbol, ObjSymbolFlags,
+        ObjBlockDiff, ObjDelaySlot, ObjInfo, ObjIns, ObjInsArg, ObjInsArgDiff, ObjInsArgValue,
+        ObjInsBranchFrom, ObjInsBranchTo, ObjInsDiff, ObjInsDiffKind, ObjInsJumpTable, ObjInsMove,
+        ObjJumpTable, ObjLineInfo, ObjReloc, ObjSymbol, ObjSymbolFlags,
     },
 };
 
-pub fn no_diff_code(
-    arch: ObjArchitecture,
-    data: &[u8],
-    symbol: &mut ObjSymbol,
-    relocs: &[ObjReloc],
-    line_info: &Option<BTreeMap<u64, u64>>,
-) -> Result<()> {
-    let code =
-        &data[symbol.section_address as usize..(symbol.section_address + symbol.size) as usize];
-    let out: ProcessCodeResult = match arch {
-        #[cfg(feature = "ppc")]
-        ObjArchitecture::PowerPc => {
-            obj::ppc::process_code(code, symbol.address, relocs, line_info)?
-        }
-        #[cfg(feature = "mips")]
-        ObjArchitecture::Mips => obj::mips::process_code(
+/// Section state required to disassemble a function.
+#[derive(Copy, Clone)]
+pub struct CodeContext<'a> {
+    pub arch: &'a dyn ObjArch,
+    pub data: &'a [u8],
+    pub section_name: &'a str,
+    pub section_index: usize,
+    pub section_align: u64,
+    pub relocs: &'a [ObjReloc],
+    pub jump_tables: &'a [ObjJumpTable],
+    pub line_info: &'a Option<ObjLineInfo>,
+}
+
+impl CodeContext<'_> {
+    fn process_code(&self, symbol: &ObjSymbol) -> Result<ProcessCodeResult> {
+        let code = &self.data
+            [symbol.section_address as usize..(symbol.section_address + symbol.size) as usize];
+        self.arch.process_code(
             code,
             symbol.address,
-            symbol.address + symbol.size,
-            relocs,
-            line_info,
-        )?,
+            self.section_index,
+            self.relocs,
+            self.line_info,
+        )
+    }
+
+    fn split_padding(&self, symbol: &ObjSymbol, code: &mut ProcessCodeResult) -> Vec<ObjIns> {
+        let end = symbol.address + symbol.size;
+        split_padding(code, end, self.section_align, |ins| self.arch.is_padding(ins))
+    }
+}
+
+/// Splits trailing alignment padding off the instructions of a function ending
+/// at `end`. Padding must end at an alignment boundary, up to `align`, and be
+/// shorter than that alignment; otherwise the instructions are part of the
+/// function. A function's first instruction and delay slots are never padding.
+fn split_padding(
+    code: &mut ProcessCodeResult,
+    end: u64,
+    align: u64,
+    is_padding: impl Fn(&ObjIns) -> bool,
+) -> Vec<ObjIns> {
+    let len = code.insts.len();
+    let padding = code
+        .insts
+        .iter()
+        .skip(1)
+        .rev()
+        .take_while(|ins| ins.delay_slot.is_none() && is_padding(ins))
+        .count();
+    let Some(start) = code.insts.get(len - padding).map(|ins| ins.address as u64) else {
+        return vec![];
     };
+    // Largest alignment the end of the function satisfies
+    let boundary = align.min(1 << end.trailing_zeros().min(63));
+    if end - start >= boundary {
+        return vec![];
+    }
+    code.ops.truncate(len - padding);
+    code.insts.split_off(len - padding)
+}
+
+/// Appends padding rows after the diffed instructions, pairing up padding
+/// present on both sides.
+fn append_padding(
+    left_diff: &mut Vec<ObjInsDiff>,
+    right_diff: &mut Vec<ObjInsDiff>,
+    left_padding: Vec<ObjIns>,
+    right_padding: Vec<ObjIns>,
+) {
+    let rows = left_padding.len().max(right_padding.len());
+    let mut left_padding = left_padding.into_iter();
+    let mut right_padding = right_padding.into_iter();
+    for _ in 0..rows {
+        left_diff.push(ObjInsDiff {
+            ins: left_padding.next(),
+            padding: true,
+            ..Default::default()
+        });
+        right_diff.push(ObjInsDiff {
+            ins: right_padding.next(),
+            padding: true,
+            ..Default::default()
+        });
+    }
+}
+
+/// Assigns opcodes by mnemonic, numbered in order of appearance across all
+/// of `codes`. Beyond 256 distinct mnemonics, the remaining ones share an opcode.
+fn intern_mnemonics(codes: &mut [&mut ProcessCodeResult]) {
+    let mut table = HashMap::<String, u8>::new();
+    for code in codes {
+        for (op, ins) in code.ops.iter_mut().zip(&mut code.insts) {
+            let next = u8::try_from(table.len()).unwrap_or(u8::MAX);
+            *op = *table.entry(ins.mnemonic.clone()).or_insert(next);
+            ins.op = *op;
+        }
+    }
+}
+
+pub fn no_diff_code(ctx: CodeContext, symbol: &mut ObjSymbol) -> Result<()> {
+    let mut out = ctx.process_code(symbol)?;
+    if ctx.arch.ops_from_mnemonics() {
+        intern_mnemonics(&mut [&mut out]);
+    }
+    let padding = ctx.split_padding(symbol, &mut out);
 
     let mut diff = Vec::<ObjInsDiff>::new();
     for i in out.insts {
         diff.push(ObjInsDiff { ins: Some(i), kind: ObjInsDiffKind::None, ..Default::default() });
     }
+    resolve_jump_tables(ctx, symbol, &mut diff);
     resolve_branches(&mut diff);
+    diff.extend(padding.into_iter().map(|i| ObjInsDiff {
+        ins: Some(i),
+        padding: true,
+        ..Default::default()
+    }));
     symbol.instructions = diff;
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn diff_code(
     config: &DiffObjConfig,
-    arch: ObjArchitecture,
-    left_data: &[u8],
-    right_data: &[u8],
+    left_ctx: CodeContext,
+    right_ctx: CodeContext,
     left_symbol: &mut ObjSymbol,
     right_symbol: &mut ObjSymbol,
-    left_relocs: &[ObjReloc],
-    right_relocs: &[ObjReloc],
-    left_line_info: &Option<BTreeMap<u64, u64>>,
-    right_line_info: &Option<BTreeMap<u64, u64>>,
 ) -> Result<()> {
-    let left_code = &left_data[left_symbol.section_address as usize
-        ..(left_symbol.section_address + left_symbol.size) as usize];
-    let right_code = &right_data[right_symbol.section_address as usize
-        ..(right_symbol.section_address + right_symbol.size) as usize];
-    let (left_out, right_out) = match arch {
-        #[cfg(feature = "ppc")]
-        ObjArchitecture::PowerPc => (
-            obj::ppc::process_code(left_code, left_symbol.address, left_relocs, left_line_info)?,
-            obj::ppc::process_code(
-                right_code,
-                right_symbol.address,
-                right_relocs,
-                right_line_info,
-            )?,
-        ),
-        #[cfg(feature = "mips")]
-        ObjArchitecture::Mips => (
-            obj::mips::process_code(
-                left_code,
-                left_symbol.address,
-                left_symbol.address + left_symbol.size,
-                left_relocs,
-                left_line_info,
-            )?,
-            obj::mips::process_code(
-                right_code,
-                right_symbol.address,
-                left_symbol.address + left_symbol.size,
-                right_relocs,
-                right_line_info,
-            )?,
-        ),
-    };
+    let mut left_out = left_ctx.process_code(left_symbol)?;
+    let mut right_out = right_ctx.process_code(right_symbol)?;
+    if left_ctx.arch.ops_from_mnemonics() || right_ctx.arch.ops_from_mnemonics() {
+        intern_mnemonics(&mut [&mut left_out, &mut right_out]);
+    }
+    let left_padding = left_ctx.split_padding(left_symbol, &mut left_out);
+    let right_padding = right_ctx.split_padding(right_symbol, &mut right_out);
 
+    let limit = DiffLimit::start(config);
     let mut left_diff = Vec::<ObjInsDiff>::new();
     let mut right_diff = Vec::<ObjInsDiff>::new();
-    match config.code_alg {
-        DiffAlg::Levenshtein => {
-            diff_instructions_lev(
-                &mut left_diff,
-                &mut right_diff,
-                left_symbol,
-                right_symbol,
-                &left_out,
-                &right_out,
-            )?;
-        }
-        DiffAlg::Lcs => {
-            diff_instructions_similar(
-                Algorithm::Lcs,
-                &mut left_diff,
-                &mut right_diff,
-                &left_out,
-                &right_out,
-            )?;
-        }
-        DiffAlg::Myers => {
-            diff_instructions_similar(
-                Algorithm::Myers,
-                &mut left_diff,
-                &mut right_diff,
-                &left_out,
-                &right_out,
-            )?;
-        }
-        DiffAlg::Patience => {
-            diff_instructions_similar(
-                Algorithm::Patience,
-                &mut left_diff,
-                &mut right_diff,
-                &left_out,
-                &right_out,
-            )?;
-        }
-    }
+    let (truncated, mut blocks) = if config.block_diff {
+        diff_blocks(
+            config.code_alg,
+            config.group_delay_slots,
+            limit,
+            left_ctx.arch,
+            right_ctx.arch,
+            &mut left_diff,
+            &mut right_diff,
+            &left_out,
+            &right_out,
+        )?
+    } else {
+        let truncated = diff_instructions(
+            config.code_alg,
+            config.group_delay_slots,
+            limit,
+            &mut left_diff,
+            &mut right_diff,
+            &left_out,
+            &right_out,
+        )?;
+        (truncated, vec![])
+    };
 
+    resolve_jump_tables(left_ctx, left_symbol, &mut left_diff);
+    resolve_jump_tables(right_ctx, right_symbol, &mut right_diff);
     resolve_branches(&mut left_diff);
     resolve_branches(&mut right_diff);
 
@@ -152,15 +200,38 @@ pub fn diff_code(
         left.arg_diff = result.left_args_diff;
         right.arg_diff = result.right_args_diff;
     }
+    resolve_moves(config, &mut left_diff, &mut right_diff, &mut diff_state);
+    append_padding(&mut left_diff, &mut right_diff, left_padding, right_padding);
 
-    let total = left_out.insts.len();
-    let percent = if diff_state.diff_count >= total {
-        0.0
+    // An incomplete diff would report a misleadingly low match
+    let (percent, score) = if truncated {
+        (None, None)
     } else {
-        ((total - diff_state.diff_count) as f32 / total as f32) * 100.0
+        let total = left_out.insts.len();
+        let percent = if diff_state.diff_count >= total {
+            0.0
+        } else {
+            ((total - diff_state.diff_count) as f32 / total as f32) * 100.0
+        };
+        (Some(percent), Some(diff_score(&config.score_weights, &left_diff, &right_diff)))
     };
-    left_symbol.match_percent = Some(percent);
-    right_symbol.match_percent = Some(percent);
+    left_symbol.match_percent = percent;
+    right_symbol.match_percent = percent;
+    left_symbol.diff_score = score;
+    right_symbol.diff_score = score;
+    left_symbol.truncated = truncated;
+    right_symbol.truncated = truncated;
+
+    for block in &mut blocks {
+        let same = block
+            .rows
+            .clone()
+            .filter(|&row| left_diff[row].kind.is_match() && right_diff[row].kind.is_match())
+            .count();
+        block.match_percent = (same as f32 / block.rows.len() as f32) * 100.0;
+    }
+    left_symbol.blocks = blocks.clone();
+    right_symbol.blocks = blocks;
 
     left_symbol.instructions = left_diff;
     right_symbol.instructions = right_diff;
@@ -168,15 +239,154 @@ pub fn diff_code(
     Ok(())
 }
 
+/// Aligns two instruction sequences with the given algorithm, optionally keeping
+/// delay slots with their branches. Returns whether the limit was reached, in
+/// which case the diff may be incomplete.
+fn diff_instructions(
+    alg: DiffAlg,
+    group_delay_slots: bool,
+    limit: DiffLimit,
+    left_diff: &mut Vec<ObjInsDiff>,
+    right_diff: &mut Vec<ObjInsDiff>,
+    left_code: &ProcessCodeResult,
+    right_code: &ProcessCodeResult,
+) -> Result<bool> {
+    let has_delay_slots =
+        |code: &ProcessCodeResult| code.insts.iter().any(|i| i.delay_slot.is_some());
+    if group_delay_slots && (has_delay_slots(left_code) || has_delay_slots(right_code)) {
+        return diff_delay_slot_units(alg, limit, left_diff, right_diff, left_code, right_code);
+    }
+    align_instructions(alg, limit, left_diff, right_diff, left_code, right_code)
+}
+
+/// Splits delay slots from the instructions, keyed by the address of their branch.
+fn split_delay_slots(code: &ProcessCodeResult) -> (ProcessCodeResult, HashMap<u32, ObjIns>) {
+    let mut units = ProcessCodeResult { ops: vec![], insts: vec![] };
+    let mut slots = HashMap::new();
+    for (op, ins) in code.ops.iter().zip(&code.insts) {
+        match units.insts.last() {
+            Some(branch) if ins.delay_slot.is_some() => {
+                slots.insert(branch.address, ins.clone());
+            }
+            _ => {
+                units.ops.push(*op);
+                units.insts.push(ins.clone());
+            }
+        }
+    }
+    (units, slots)
+}
+
+/// Aligns branches and their delay slots as a unit: slots are left out of the
+/// alignment and placed on the row after their branch, so a different choice of
+/// delay slot instruction doesn't misalign the rows around it.
+fn diff_delay_slot_units(
+    alg: DiffAlg,
+    limit: DiffLimit,
+    left_diff: &mut Vec<ObjInsDiff>,
+    right_diff: &mut Vec<ObjInsDiff>,
+    left_code: &ProcessCodeResult,
+    right_code: &ProcessCodeResult,
+) -> Result<bool> {
+    let (left_units, mut left_slots) = split_delay_slots(left_code);
+    let (right_units, mut right_slots) = split_delay_slots(right_code);
+    let mut left_rows = Vec::new();
+    let mut right_rows = Vec::new();
+    let truncated =
+        align_instructions(alg, limit, &mut left_rows, &mut right_rows, &left_units, &right_units)?;
+    for (left, right) in left_rows.into_iter().zip(right_rows) {
+        let left_slot = left.ins.as_ref().and_then(|ins| left_slots.remove(&ins.address));
+        let right_slot = right.ins.as_ref().and_then(|ins| right_slots.remove(&ins.address));
+        left_diff.push(left);
+        right_diff.push(right);
+        if left_slot.is_some() || right_slot.is_some() {
+            left_diff.push(ObjInsDiff { ins: left_slot, ..Default::default() });
+            right_diff.push(ObjInsDiff { ins: right_slot, ..Default::default() });
+        }
+    }
+    Ok(truncated)
+}
+
+/// Aligns two instruction sequences with the given algorithm.
+/// Returns whether the limit was reached, in which case the diff may be incomplete.
+fn align_instructions(
+    alg: DiffAlg,
+    limit: DiffLimit,
+    left_diff: &mut Vec<ObjInsDiff>,
+    right_diff: &mut Vec<ObjInsDiff>,
+    left_code: &ProcessCodeResult,
+    right_code: &ProcessCodeResult,
+) -> Result<bool> {
+    let alg = match alg {
+        DiffAlg::Levenshtein => {
+            return diff_instructions_lev(limit, left_diff, right_diff, left_code, right_code);
+        }
+        DiffAlg::Lcs => Algorithm::Lcs,
+        DiffAlg::Myers => Algorithm::Myers,
+        DiffAlg::Patience => Algorithm::Patience,
+    };
+    diff_instructions_similar(alg, limit, left_diff, right_diff, left_code, right_code)
+}
+
+/// Matches basic blocks first and aligns instructions within each pair of blocks,
+/// so that an extra block only affects its own rows. Returns whether the limit
+/// was reached, and the rows of each block.
+#[allow(clippy::too_many_arguments)]
+fn diff_blocks(
+    alg: DiffAlg,
+    group_delay_slots: bool,
+    limit: DiffLimit,
+    left_arch: &dyn ObjArch,
+    right_arch: &dyn ObjArch,
+    left_diff: &mut Vec<ObjInsDiff>,
+    right_diff: &mut Vec<ObjInsDiff>,
+    left_code: &ProcessCodeResult,
+    right_code: &ProcessCodeResult,
+) -> Result<(bool, Vec<ObjBlockDiff>)> {
+    let left_blocks = basic_blocks(left_arch, &left_code.insts);
+    let right_blocks = basic_blocks(right_arch, &right_code.insts);
+    let (pairs, mut truncated) =
+        match_blocks(&left_blocks, &left_code.ops, &right_blocks, &right_code.ops, limit);
+    let slice = |code: &ProcessCodeResult, block: Option<&BasicBlock>| match block {
+        Some(block) => ProcessCodeResult {
+            ops: code.ops[block.insts.clone()].to_vec(),
+            insts: code.insts[block.insts.clone()].to_vec(),
+        },
+        None => ProcessCodeResult { ops: vec![], insts: vec![] },
+    };
+    let mut blocks = Vec::with_capacity(pairs.len());
+    for (left_idx, right_idx) in pairs {
+        let left_block = slice(left_code, left_idx.map(|i| &left_blocks[i]));
+        let right_block = slice(right_code, right_idx.map(|i| &right_blocks[i]));
+        let start = left_diff.len();
+        truncated |= diff_instructions(
+            alg,
+            group_delay_slots,
+            limit,
+            left_diff,
+            right_diff,
+            &left_block,
+            &right_block,
+        )?;
+        blocks.push(ObjBlockDiff {
+            rows: start..left_diff.len(),
+            matched: left_idx.is_some() && right_idx.is_some(),
+            match_percent: 0.0,
+        });
+    }
+    Ok((truncated, blocks))
+}
+
+/// Returns whether the limit was reached, in which case the diff may be incomplete.
 fn diff_instructions_similar(
     alg: Algorithm,
+    limit: DiffLimit,
     left_diff: &mut Vec<ObjInsDiff>,
     right_diff: &mut Vec<ObjInsDiff>,
     left_code: &ProcessCodeResult,
     right_code: &ProcessCodeResult,
-) -> Result<()> {
-    let deadline = Instant::now() + Duration::from_secs(5);
-    let ops = capture_diff_slices_deadline(alg, &left_code.ops, &right_code.ops, Some(deadline));
+) -> Result<bool> {
+    let (ops, truncated) = capture_diff_slices_limited(alg, &left_code.ops, &right_code.ops, limit);
     if ops.is_empty() {
         left_diff.extend(
             left_code
@@ -190,7 +400,7 @@ fn diff_instructions_similar(
                 .iter()
                 .map(|i| ObjInsDiff { ins: Some(i.clone()), ..Default::default() }),
         );
-        return Ok(());
+        return Ok(truncated);
     }
 
     for op in ops {
@@ -214,79 +424,98 @@ fn diff_instructions_similar(
         }
     }
 
-    Ok(())
+    Ok(truncated)
 }
 
+/// Aligns instructions by their index, as edit operations refer to positions
+/// in the opcode sequences and instructions may vary in size. Returns whether
+/// the limit was reached, in which case the instructions are paired in order.
 fn diff_instructions_lev(
+    limit: DiffLimit,
     left_diff: &mut Vec<ObjInsDiff>,
     right_diff: &mut Vec<ObjInsDiff>,
-    left_symbol: &ObjSymbol,
-    right_symbol: &ObjSymbol,
     left_code: &ProcessCodeResult,
     right_code: &ProcessCodeResult,
-) -> Result<()> {
-    let edit_ops = editops_find(&left_code.ops, &right_code.ops);
-
-    let mut op_iter = edit_ops.iter();
-    let mut left_iter = left_code.insts.iter();
-    let mut right_iter = right_code.insts.iter();
-    let mut cur_op = op_iter.next();
-    let mut cur_left = left_iter.next();
-    let mut cur_right = right_iter.next();
-    while let Some(op) = cur_op {
-        let left_addr = op.first_start as u32 * 4;
-        let right_addr = op.second_start as u32 * 4;
-        while let (Some(left), Some(right)) = (cur_left, cur_right) {
-            if (left.address - left_symbol.address as u32) < left_addr {
-                left_diff.push(ObjInsDiff { ins: Some(left.clone()), ..ObjInsDiff::default() });
-                right_diff.push(ObjInsDiff { ins: Some(right.clone()), ..ObjInsDiff::default() });
-            } else {
-                break;
+) -> Result<bool> {
+    let edit_ops = editops_find_until(&left_code.ops, &right_code.ops, || limit.reached());
+    let truncated = edit_ops.is_none();
+    let left =
+        |idx: usize| ObjInsDiff { ins: left_code.insts.get(idx).cloned(), ..Default::default() };
+    let right =
+        |idx: usize| ObjInsDiff { ins: right_code.insts.get(idx).cloned(), ..Default::default() };
+
+    let mut left_idx = 0;
+    let mut right_idx = 0;
+    for op in edit_ops.unwrap_or_default() {
+        // Unchanged instructions up to the edit
+        while left_idx < op.first_start && right_idx < op.second_start {
+            left_diff.push(left(left_idx));
+            right_diff.push(right(right_idx));
+            left_idx += 1;
+            right_idx += 1;
+        }
+        match op.op_type {
+            LevEditType::Replace => {
+                left_diff.push(left(left_idx));
+                right_diff.push(right(right_idx));
+                left_idx += 1;
+                right_idx += 1;
             }
-            cur_left = left_iter.next();
-            cur_right = right_iter.next();
-        }
-        if let (Some(left), Some(right)) = (cur_left, cur_right) {
-            debug_assert_eq!(left.address - left_symbol.address as u32, left_addr);
-            debug_assert_eq!(right.address - right_symbol.address as u32, right_addr);
-            match op.op_type {
-                LevEditType::Replace => {
-                    left_diff.push(ObjInsDiff { ins: Some(left.clone()), ..ObjInsDiff::default() });
-                    right_diff
-                        .push(ObjInsDiff { ins: Some(right.clone()), ..ObjInsDiff::default() });
-                    cur_left = left_iter.next();
-                    cur_right = right_iter.next();
-                }
-                LevEditType::Insert => {
-                    left_diff.push(ObjInsDiff::default());
-                    right_diff
-                        .push(ObjInsDiff { ins: Some(right.clone()), ..ObjInsDiff::default() });
-                    cur_right = right_iter.next();
-                }
-                LevEditType::Delete => {
-                    left_diff.push(ObjInsDiff { ins: Some(left.clone()), ..ObjInsDiff::default() });
-                    right_diff.push(ObjInsDiff::default());
-                    cur_left = left_iter.next();
-                }
+            LevEditType::Insert => {
+                left_diff.push(ObjInsDiff::default());
+                right_diff.push(right(right_idx));
+                right_idx += 1;
+            }
+            LevEditType::Delete => {
+                left_diff.push(left(left_idx));
+                right_diff.push(ObjInsDiff::default());
+                left_idx += 1;
             }
-        } else {
-            break;
         }
-        cur_op = op_iter.next();
     }
 
     // Finalize
-    while cur_left.is_some() || cur_right.is_some() {
-        left_diff.push(ObjInsDiff { ins: cur_left.cloned(), ..ObjInsDiff::default() });
-        right_diff.push(ObjInsDiff { ins: cur_right.cloned(), ..ObjInsDiff::default() });
-        cur_left = left_iter.next();
-        cur_right = right_iter.next();
+    while left_idx < left_code.insts.len() || right_idx < right_code.insts.len() {
+        left_diff.push(left(left_idx));
+        right_diff.push(right(right_idx));
+        left_idx += 1;
+        right_idx += 1;
     }
 
-    Ok(())
+    Ok(truncated)
 }
 
-fn resolve_branches(vec: &mut [ObjInsDiff]) {
+/// Attaches each jump table of the function to the first indirect branch after
+/// the table is loaded.
+fn resolve_jump_tables(ctx: CodeContext, symbol: &ObjSymbol, vec: &mut [ObjInsDiff]) {
+    let first = ctx.jump_tables.partition_point(|t| t.load_address < symbol.address);
+    let end_address = symbol.address + symbol.size;
+    let tables = &ctx.jump_tables[first..];
+    let tables = &tables[..tables.partition_point(|t| t.load_address < end_address)];
+    let mut used = vec![false; tables.len()];
+    for ins_diff in vec.iter_mut() {
+        let Some(ins) = &ins_diff.ins else {
+            continue;
+        };
+        if !ctx.arch.is_indirect_branch(ins) {
+            continue;
+        }
+        // Closest table loaded before the branch
+        let idx = tables.partition_point(|t| t.load_address < ins.address as u64);
+        let Some(idx) = idx.checked_sub(1).filter(|&idx| !used[idx]) else {
+            continue;
+        };
+        used[idx] = true;
+        let table = &tables[idx];
+        ins_diff.jump_table = Some(ObjInsJumpTable {
+            name: table.name.clone(),
+            targets: table.targets.iter().map(|&target| target as u32).collect(),
+            branches: vec![],
+        });
+    }
+}
+
+pub(crate) fn resolve_branches(vec: &mut [ObjInsDiff]) {
     let mut branch_idx = 0usize;
     // Map addresses to indices
     let mut addr_map = BTreeMap::<u32, usize>::new();
@@ -308,17 +537,19 @@ fn resolve_branches(vec: &mut [ObjInsDiff]) {
                 .find_map(|a| if let ObjInsArg::BranchOffset(offs) = a { Some(offs) } else { None })
                 .and_then(|offs| addr_map.get(&((ins.address as i32 + offs) as u32)))
             {
-                if let Some(branch) = branches.get_mut(ins_idx) {
-                    ins_diff.branch_to =
-                        Some(ObjInsBranchTo { ins_idx: *ins_idx, branch_idx: branch.branch_idx });
-                    branch.ins_idx.push(i);
-                } else {
-                    ins_diff.branch_to = Some(ObjInsBranchTo { ins_idx: *ins_idx, branch_idx });
-                    branches.insert(*ins_idx, ObjInsBranchFrom { ins_idx: vec![i], branch_idx });
-                    branch_idx += 1;
-                }
+                ins_diff.branch_to = Some(add_branch(&mut branches, &mut branch_idx, i, *ins_idx));
             }
         }
+        if let Some(table) = &mut ins_diff.jump_table {
+            table.branches = table
+                .targets
+                .iter()
+                .map(|target| {
+                    let ins_idx = *addr_map.get(target)?;
+                    Some(add_branch(&mut branches, &mut branch_idx, i, ins_idx))
+                })
+                .collect();
+        }
     }
     // Store branch from
     for (i, branch) in branches {
@@ -326,6 +557,180 @@ fn resolve_branches(vec: &mut [ObjInsDiff]) {
     }
 }
 
+/// Records a branch from row `from` to row `to`, sharing the branch index of
+/// other branches to the same row.
+fn add_branch(
+    branches: &mut BTreeMap<usize, ObjInsBranchFrom>,
+    branch_idx: &mut usize,
+    from: usize,
+    to: usize,
+) -> ObjInsBranchTo {
+    if let Some(branch) = branches.get_mut(&to) {
+        if !branch.ins_idx.contains(&from) {
+            branch.ins_idx.push(from);
+        }
+        ObjInsBranchTo { ins_idx: to, branch_idx: branch.branch_idx }
+    } else {
+        branches.insert(to, ObjInsBranchFrom { ins_idx: vec![from], branch_idx: *branch_idx });
+        *branch_idx += 1;
+        ObjInsBranchTo { ins_idx: to, branch_idx: *branch_idx - 1 }
+    }
+}
+
+/// Maximum distance in rows between a deleted and an inserted instruction
+/// for them to be considered a single moved instruction.
+const MOVE_WINDOW: usize = 16;
+
+/// Whether the row holds a delay slot on one side and a `nop` on the other,
+/// which is where an instruction hoisted into the delay slot ends up. The slot
+/// of a branch-likely only executes when the branch is taken, so an instruction
+/// from before the branch can't be hoisted into it.
+fn is_hoisted_slot(slot: &ObjInsDiff, other: &ObjInsDiff) -> bool {
+    slot.ins.as_ref().is_some_and(|ins| ins.delay_slot == Some(ObjDelaySlot::Always))
+        && other.ins.as_ref().is_some_and(|ins| ins.mnemonic == "nop")
+}
+
+/// Pairs identical instructions at different positions, which are usually the
+/// result of instruction scheduling differences. This handles a deleted and an
+/// inserted instruction, as well as two mismatched rows with swapped instructions.
+/// With delay slots grouped, an instruction hoisted into a delay slot on one side
+/// is paired with its original position on the other side.
+fn resolve_moves(
+    config: &DiffObjConfig,
+    left_diff: &mut [ObjInsDiff],
+    right_diff: &mut [ObjInsDiff],
+    state: &mut InsDiffState,
+) {
+    let mut move_idx = 0usize;
+    let mismatched = |kind: ObjInsDiffKind| {
+        matches!(
+            kind,
+            ObjInsDiffKind::Replace | ObjInsDiffKind::OpMismatch | ObjInsDiffKind::ArgMismatch
+        )
+    };
+    let slots = config.group_delay_slots;
+    for i in 0..left_diff.len() {
+        let kind = left_diff[i].kind;
+        if kind != ObjInsDiffKind::Delete && !mismatched(kind) {
+            continue;
+        }
+        // Hoisted into the left delay slot, in its original position on the right
+        let left_hoisted = slots
+            && kind != ObjInsDiffKind::Delete
+            && is_hoisted_slot(&left_diff[i], &right_diff[i]);
+        let start = i.saturating_sub(MOVE_WINDOW);
+        let end = (i + MOVE_WINDOW + 1).min(right_diff.len());
+        // Prefer the closest candidate
+        let Some(j) = (start..end)
+            .filter(|&j| {
+                if kind == ObjInsDiffKind::Delete {
+                    right_diff[j].kind == ObjInsDiffKind::Insert
+                        || (slots
+                            && mismatched(right_diff[j].kind)
+                            && is_hoisted_slot(&right_diff[j], &left_diff[j]))
+                } else if left_hoisted {
+                    right_diff[j].kind == ObjInsDiffKind::Insert
+                } else {
+                    j != i
+                        && mismatched(right_diff[j].kind)
+                        && ins_eq(config, &left_diff[j], &right_diff[i])
+                }
+            })
+            .filter(|&j| ins_eq(config, &left_diff[i], &right_diff[j]))
+            .min_by_key(|&j| j.abs_diff(i))
+        else {
+            continue;
+        };
+        let swapped = kind != ObjInsDiffKind::Delete && !left_hoisted;
+        let mut moves = vec![(i, j)];
+        if swapped {
+            moves.push((j, i));
+        }
+        for (left_row, right_row) in moves {
+            for cell in [&mut left_diff[left_row], &mut right_diff[right_row]] {
+                cell.kind = ObjInsDiffKind::Moved;
+                cell.arg_diff.clear();
+            }
+            left_diff[left_row].moved = Some(ObjInsMove { ins_idx: right_row, move_idx });
+            right_diff[right_row].moved = Some(ObjInsMove { ins_idx: left_row, move_idx });
+            move_idx += 1;
+        }
+        for row in [i, j] {
+            for cell in [&mut left_diff[row], &mut right_diff[row]] {
+                if cell.kind == ObjInsDiffKind::Moved {
+                    continue;
+                }
+                // Placeholders opposite a deleted or inserted instruction no longer differ,
+                // while an instruction opposite a moved one, such as the slot an instruction
+                // was hoisted into, is unmatched
+                cell.kind =
+                    if cell.ins.is_some() { ObjInsDiffKind::Replace } else { ObjInsDiffKind::None };
+                cell.arg_diff.clear();
+            }
+        }
+        // Both rows were counted as differences, count the move as one unless
+        // an unmatched instruction remains
+        let unmatched = [i, j].iter().any(|&row| {
+            left_diff[row].kind == ObjInsDiffKind::Replace
+                || right_diff[row].kind == ObjInsDiffKind::Replace
+        });
+        if !unmatched {
+            state.diff_count -= 1;
+        }
+    }
+}
+
+/// Calculates a weighted score for a diff, similar to asm-differ. 0 is a full match.
+fn diff_score(weights: &ScoreWeights, left_diff: &[ObjInsDiff], right_diff: &[ObjInsDiff]) -> u64 {
+    let mut score = 0;
+    for (left, right) in left_diff.iter().zip(right_diff) {
+        score += match left.kind {
+            ObjInsDiffKind::None | ObjInsDiffKind::Equivalent => 0,
+            // Unmatched instruction opposite a moved one
+            ObjInsDiffKind::Replace if right.kind == ObjInsDiffKind::Moved => weights.deletion,
+            ObjInsDiffKind::OpMismatch | ObjInsDiffKind::Replace => {
+                weights.insertion + weights.deletion
+            }
+            ObjInsDiffKind::ArgMismatch => {
+                let (Some(left_ins), Some(right_ins)) = (&left.ins, &right.ins) else {
+                    continue;
+                };
+                let is_reg =
+                    |arg: &ObjInsArg| matches!(arg, ObjInsArg::Arg(ObjInsArgValue::Register(_)));
+                let regs_only = left_ins
+                    .args
+                    .iter()
+                    .zip(&right_ins.args)
+                    .zip(&left.arg_diff)
+                    .filter(|(_, diff)| diff.is_some())
+                    .all(|((a, b), _)| is_reg(a) && is_reg(b));
+                if regs_only {
+                    weights.regswap
+                } else {
+                    weights.immediate
+                }
+            }
+            ObjInsDiffKind::Delete => weights.deletion,
+            ObjInsDiffKind::Insert => weights.insertion,
+            ObjInsDiffKind::Moved if right.kind == ObjInsDiffKind::Replace => {
+                weights.reorder + weights.insertion
+            }
+            ObjInsDiffKind::Moved => weights.reorder,
+        };
+    }
+    score
+}
+
+fn ins_eq(config: &DiffObjConfig, left: &ObjInsDiff, right: &ObjInsDiff) -> bool {
+    let (Some(left_ins), Some(right_ins)) = (&left.ins, &right.ins) else {
+        return false;
+    };
+    left_ins.op == right_ins.op
+        && left_ins.mnemonic == right_ins.mnemonic
+        && left_ins.args.len() == right_ins.args.len()
+        && left_ins.args.iter().zip(&right_ins.args).all(|(a, b)| arg_eq(config, a, b, left, right))
+}
+
 fn address_eq(left: &ObjSymbol, right: &ObjSymbol) -> bool {
     left.address as i64 + left.addend == right.address as i64 + right.addend
 }
@@ -338,18 +743,38 @@ fn reloc_eq(
     let (Some(left), Some(right)) = (left_reloc, right_reloc) else {
         return false;
     };
-    if left.kind != right.kind {
+    if left.kind != right.kind || left.unpaired != right.unpaired {
+        return false;
+    }
+    // Small data references through a different base register or to a different
+    // small data section are never equivalent
+    if left.sda_base != right.sda_base
+        || (left.sda_base.is_some() && left.target_section != right.target_section)
+    {
         return false;
     }
     if config.relax_reloc_diffs {
         return true;
     }
+    let options = config.reloc_diffs;
+    // Addends and bindings of extern targets are left to the linker
+    let relax_extern = options.relax_extern_targets
+        && left.target_section.is_none()
+        && right.target_section.is_none();
 
-    let name_matches = left.target.name == right.target.name;
+    let is_local = |symbol: &ObjSymbol| symbol.flags.0.contains(ObjSymbolFlags::Local);
+    let name_matches = left.target.name == right.target.name
+        && (!options.check_addend || relax_extern || left.target.addend == right.target.addend);
+    if options.check_binding && !relax_extern && is_local(&left.target) != is_local(&right.target) {
+        return false;
+    }
     match (&left.target_section, &right.target_section) {
         (Some(sl), Some(sr)) => {
             // Match if section and name or address match
-            sl == sr && (name_matches || address_eq(&left.target, &right.target))
+            sl == sr
+                && (options.relax_target_name
+                    || name_matches
+                    || address_eq(&left.target, &right.target))
         }
         (Some(_), None) => false,
         (None, Some(_)) => {
@@ -396,6 +821,64 @@ fn arg_eq(
     };
 }
 
+/// Compares jump table entries by the rows they branch to.
+fn jump_table_eq(left: &ObjInsDiff, right: &ObjInsDiff) -> bool {
+    match (&left.jump_table, &right.jump_table) {
+        (Some(left), Some(right)) => {
+            left.branches.len() == right.branches.len()
+                && left
+                    .branches
+                    .iter()
+                    .zip(&right.branches)
+                    .all(|(l, r)| l.as_ref().map(|b| b.ins_idx) == r.as_ref().map(|b| b.ins_idx))
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Whether an equivalence rule matches the instructions, with captures shared
+/// by both patterns holding equal arguments.
+fn rule_matches(
+    config: &DiffObjConfig,
+    left: &InsPattern,
+    right: &InsPattern,
+    left_diff: &ObjInsDiff,
+    right_diff: &ObjInsDiff,
+) -> bool {
+    let (Some(left_ins), Some(right_ins)) = (&left_diff.ins, &right_diff.ins) else {
+        return false;
+    };
+    let (Some(left_captures), Some(right_captures)) =
+        (left.captures(left_ins), right.captures(right_ins))
+    else {
+        return false;
+    };
+    left_captures.iter().all(|(name, a)| {
+        right_captures.get(name).map_or(true, |b| arg_eq(config, a, b, left_diff, right_diff))
+    })
+}
+
+/// Replaces a differing result with [`ObjInsDiffKind::Equivalent`] if any
+/// equivalence rule matches the instructions.
+fn equivalent_or(
+    config: &DiffObjConfig,
+    left: &ObjInsDiff,
+    right: &ObjInsDiff,
+    result: InsDiffResult,
+    state: &mut InsDiffState,
+) -> InsDiffResult {
+    let equivalent = config.equivalences.iter().any(|rule| {
+        rule_matches(config, &rule.left, &rule.right, left, right)
+            || rule_matches(config, &rule.right, &rule.left, left, right)
+    });
+    if !equivalent {
+        return result;
+    }
+    state.diff_count -= 1;
+    InsDiffResult { kind: ObjInsDiffKind::Equivalent, ..Default::default() }
+}
+
 #[derive(Default)]
 struct InsDiffState {
     diff_count: usize,
@@ -424,7 +907,7 @@ fn compare_ins(
             // Totally different op
             result.kind = ObjInsDiffKind::Replace;
             state.diff_count += 1;
-            return Ok(result);
+            return Ok(equivalent_or(config, left, right, result, state));
         }
         if left_ins.mnemonic != right_ins.mnemonic {
             // Same op but different mnemonic, still cmp args
@@ -470,6 +953,13 @@ fn compare_ins(
                 result.right_args_diff.push(Some(b_diff));
             }
         }
+        if result.kind == ObjInsDiffKind::None && !jump_table_eq(left, right) {
+            result.kind = ObjInsDiffKind::ArgMismatch;
+            state.diff_count += 1;
+        }
+        if result.kind != ObjInsDiffKind::None {
+            result = equivalent_or(config, left, right, result, state);
+        }
     } else if left.ins.is_some() {
         result.kind = ObjInsDiffKind::Delete;
         state.diff_count += 1;
@@ -490,3 +980,408 @@ pub fn find_section_and_symbol(obj: &ObjInfo, name: &str) -> Option<(usize, usiz
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use object::{elf, RelocationKind};
+
+    use super::*;
+    use crate::{
+        diff::equiv::EquivalenceRule,
+        obj::{ObjSdaBase, ObjSymbolFlagSet},
+    };
+
+    fn ins(address: u32, mnemonic: &str) -> ObjIns {
+        ObjIns {
+            address,
+            code: 0,
+            op: 0,
+            mnemonic: mnemonic.to_string(),
+            args: vec![],
+            reloc: None,
+            branch_dest: None,
+            line: None,
+            orig: None,
+            delay_slot: None,
+        }
+    }
+
+    /// Instructions at the given addresses, with opcodes interned from the mnemonics.
+    fn code(insts: &[(u32, &str)]) -> ProcessCodeResult {
+        let insts = insts.iter().map(|&(address, mnemonic)| ins(address, mnemonic)).collect();
+        ProcessCodeResult { ops: vec![], insts }
+    }
+
+    fn interned(
+        mut left: ProcessCodeResult,
+        mut right: ProcessCodeResult,
+    ) -> [ProcessCodeResult; 2] {
+        left.ops = vec![0; left.insts.len()];
+        right.ops = vec![0; right.insts.len()];
+        intern_mnemonics(&mut [&mut left, &mut right]);
+        [left, right]
+    }
+
+    fn config() -> DiffObjConfig {
+        DiffObjConfig {
+            code_alg: DiffAlg::Patience,
+            data_alg: DiffAlg::Patience,
+            relax_reloc_diffs: false,
+            reloc_diffs: Default::default(),
+            block_diff: false,
+            group_delay_slots: false,
+            score_weights: Default::default(),
+            equivalences: vec![],
+            objdump: None,
+            demangler: Default::default(),
+            deadline: None,
+            cancel: Default::default(),
+        }
+    }
+
+    /// Aligns and compares instructions like [`diff_code`], without padding,
+    /// jump tables or scoring.
+    fn diff(
+        config: &DiffObjConfig,
+        left: &ProcessCodeResult,
+        right: &ProcessCodeResult,
+    ) -> (Vec<ObjInsDiff>, Vec<ObjInsDiff>) {
+        let mut left_diff = vec![];
+        let mut right_diff = vec![];
+        diff_instructions(
+            config.code_alg,
+            config.group_delay_slots,
+            DiffLimit::start(config),
+            &mut left_diff,
+            &mut right_diff,
+            left,
+            right,
+        )
+        .unwrap();
+        resolve_branches(&mut left_diff);
+        resolve_branches(&mut right_diff);
+        let mut state = InsDiffState::default();
+        for (left, right) in left_diff.iter_mut().zip(right_diff.iter_mut()) {
+            let result = compare_ins(config, left, right, &mut state).unwrap();
+            left.kind = result.kind;
+            right.kind = result.kind;
+            left.arg_diff = result.left_args_diff;
+            right.arg_diff = result.right_args_diff;
+        }
+        resolve_moves(config, &mut left_diff, &mut right_diff, &mut state);
+        (left_diff, right_diff)
+    }
+
+    fn kinds(diff: &[ObjInsDiff]) -> Vec<ObjInsDiffKind> {
+        diff.iter().map(|row| row.kind).collect()
+    }
+
+    fn rows(diff: &[ObjInsDiff]) -> Vec<Option<u32>> {
+        diff.iter().map(|row| row.ins.as_ref().map(|ins| ins.address)).collect()
+    }
+
+    #[test]
+    fn intern_mnemonics_shared() {
+        let [left, right] = interned(
+            code(&[(0, "push"), (1, "mov"), (4, "ret")]),
+            code(&[(0, "mov"), (3, "call"), (8, "ret")]),
+        );
+        assert_eq!(left.ops, [0, 1, 2]);
+        assert_eq!(right.ops, [1, 3, 2]);
+        assert_eq!(right.insts[1].op, 3);
+    }
+
+    #[test]
+    fn padding_to_alignment() {
+        let split = |insts: &[(u32, &str)], end: u64, align: u64| {
+            let mut code = code(insts);
+            code.ops = vec![0; code.insts.len()];
+            let padding = split_padding(&mut code, end, align, |ins| ins.mnemonic == "nop");
+            (code.insts.len(), padding.len())
+        };
+        let insts = [(0, "a"), (4, "b"), (8, "nop"), (12, "nop")];
+        assert_eq!(split(&insts, 16, 16), (2, 2));
+        // Not needed to reach the next boundary
+        assert_eq!(split(&insts, 16, 4), (4, 0));
+        assert_eq!(split(&[(0, "a"), (4, "b"), (8, "nop")], 12, 16), (3, 0));
+        // A whole alignment unit of nops is part of the function
+        let insts = [(0, "a"), (16, "nop"), (20, "nop"), (24, "nop"), (28, "nop")];
+        assert_eq!(split(&insts, 32, 16), (5, 0));
+        assert_eq!(split(&[(0, "a"), (20, "nop"), (24, "nop"), (28, "nop")], 32, 16), (1, 3));
+    }
+
+    #[test]
+    fn levenshtein_variable_length() {
+        let [left, right] = interned(
+            code(&[(0, "push"), (1, "mov"), (4, "call"), (9, "pop"), (10, "ret")]),
+            code(&[(0, "push"), (1, "mov"), (4, "add"), (8, "call"), (13, "pop"), (14, "ret")]),
+        );
+        let config = config();
+        let mut left_diff = vec![];
+        let mut right_diff = vec![];
+        let truncated = diff_instructions_lev(
+            DiffLimit::start(&config),
+            &mut left_diff,
+            &mut right_diff,
+            &left,
+            &right,
+        )
+        .unwrap();
+        assert!(!truncated);
+        assert_eq!(rows(&left_diff), [Some(0), Some(1), None, Some(4), Some(9), Some(10)]);
+        assert_eq!(rows(&right_diff), [Some(0), Some(1), Some(4), Some(8), Some(13), Some(14)]);
+
+        // Once cancelled, instructions are paired in order and the diff is truncated
+        let [left, right] = interned(
+            code(&[(0, "a"), (4, "b"), (8, "c")]),
+            code(&[(0, "d"), (4, "b"), (8, "e"), (12, "f")]),
+        );
+        config.cancel.cancel();
+        let mut left_diff = vec![];
+        let mut right_diff = vec![];
+        let truncated = diff_instructions_lev(
+            DiffLimit::start(&config),
+            &mut left_diff,
+            &mut right_diff,
+            &left,
+            &right,
+        )
+        .unwrap();
+        assert!(truncated);
+        assert_eq!(rows(&left_diff), [Some(0), Some(4), Some(8), None]);
+    }
+
+    #[test]
+    fn moved_instruction_placeholders() {
+        let [left, right] = interned(
+            code(&[(0, "a"), (4, "b"), (8, "c"), (12, "x")]),
+            code(&[(0, "a"), (4, "x"), (8, "b"), (12, "c")]),
+        );
+        let (left_diff, right_diff) = diff(&config(), &left, &right);
+        assert_eq!(rows(&left_diff), [Some(0), None, Some(4), Some(8), Some(12)]);
+        assert_eq!(rows(&right_diff), [Some(0), Some(4), Some(8), Some(12), None]);
+        let (none, moved) = (ObjInsDiffKind::None, ObjInsDiffKind::Moved);
+        assert_eq!(kinds(&left_diff), [none, none, none, none, moved]);
+        assert_eq!(kinds(&right_diff), [none, moved, none, none, none]);
+        assert_eq!(left_diff[4].moved.as_ref().map(|m| m.ins_idx), Some(1));
+        assert_eq!(right_diff[1].moved.as_ref().map(|m| m.ins_idx), Some(4));
+    }
+
+    #[test]
+    fn score_registers_and_immediates() {
+        let with_args = |mut code: ProcessCodeResult, args: &[ObjInsArgValue]| {
+            code.insts[0].args = args.iter().cloned().map(ObjInsArg::Arg).collect();
+            code
+        };
+        let reg = |s: &str| ObjInsArgValue::Register(s.to_string());
+        let imm = |s: &str| ObjInsArgValue::Opaque(s.to_string());
+        let config = config();
+        let score = |left_args: &[ObjInsArgValue], right_args: &[ObjInsArgValue]| {
+            let [left, right] = interned(code(&[(0, "addiu")]), code(&[(0, "addiu")]));
+            let (left_diff, right_diff) =
+                diff(&config, &with_args(left, left_args), &with_args(right, right_args));
+            diff_score(&config.score_weights, &left_diff, &right_diff)
+        };
+        let weights = config.score_weights;
+        assert_eq!(score(&[reg("$a0"), imm("0x10")], &[reg("$a1"), imm("0x10")]), weights.regswap);
+        assert_eq!(
+            score(&[reg("$a0"), imm("0x10")], &[reg("$a0"), imm("0x18")]),
+            weights.immediate
+        );
+        assert_eq!(
+            score(&[reg("$a0"), imm("0x10")], &[reg("$a1"), imm("0x18")]),
+            weights.immediate
+        );
+    }
+
+    /// Relocation to the global symbol `var` at address 0.
+    fn reloc(kind: u32, section: Option<&str>) -> ObjReloc {
+        ObjReloc {
+            kind: RelocationKind::Elf(kind),
+            address: 0,
+            target: ObjSymbol {
+                name: "var".to_string(),
+                demangled_name: None,
+                address: 0,
+                section_address: 0,
+                size: 4,
+                size_known: true,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                addend: 0,
+                diff_symbol: None,
+                instructions: vec![],
+                match_percent: None,
+                diff_score: None,
+                truncated: false,
+                blocks: vec![],
+            },
+            target_section: section.map(str::to_string),
+            unpaired: false,
+            sda_base: None,
+        }
+    }
+
+    #[test]
+    fn reloc_diff_options() {
+        let mut config = config();
+        let left = reloc(elf::R_MIPS_26, Some(".text"));
+        let mut addend = reloc(elf::R_MIPS_26, Some(".text"));
+        addend.target.addend = 4;
+        let mut local = reloc(elf::R_MIPS_26, Some(".text"));
+        local.target.flags = ObjSymbolFlagSet(ObjSymbolFlags::Local.into());
+        let mut renamed = reloc(elf::R_MIPS_26, Some(".text"));
+        renamed.target.name = "other".to_string();
+        renamed.target.address = 8;
+        let ext = reloc(elf::R_MIPS_26, None);
+        let mut ext_renamed = reloc(elf::R_MIPS_26, None);
+        ext_renamed.target.name = "other".to_string();
+        let mut ext_addend = reloc(elf::R_MIPS_26, None);
+        ext_addend.target.addend = 4;
+        let mut ext_local = reloc(elf::R_MIPS_26, None);
+        ext_local.target.flags = ObjSymbolFlagSet(ObjSymbolFlags::Local.into());
+
+        // Addend and binding differences are ignored by default
+        assert!(reloc_eq(&config, Some(&left), Some(&addend)));
+        assert!(reloc_eq(&config, Some(&left), Some(&local)));
+        assert!(!reloc_eq(&config, Some(&left), Some(&renamed)));
+        assert!(!reloc_eq(&config, Some(&ext), Some(&ext_renamed)));
+
+        config.reloc_diffs.check_addend = true;
+        assert!(!reloc_eq(&config, Some(&left), Some(&addend)));
+        assert!(!reloc_eq(&config, Some(&ext), Some(&ext_addend)));
+        config.reloc_diffs.check_binding = true;
+        assert!(!reloc_eq(&config, Some(&left), Some(&local)));
+        assert!(!reloc_eq(&config, Some(&ext), Some(&ext_local)));
+
+        // Only extern targets of the same name are relaxed
+        config.reloc_diffs.relax_extern_targets = true;
+        assert!(reloc_eq(&config, Some(&ext), Some(&ext_addend)));
+        assert!(reloc_eq(&config, Some(&ext), Some(&ext_local)));
+        assert!(!reloc_eq(&config, Some(&ext), Some(&ext_renamed)));
+        assert!(!reloc_eq(&config, Some(&left), Some(&addend)));
+        assert!(!reloc_eq(&config, Some(&left), Some(&local)));
+        config.reloc_diffs.relax_target_name = true;
+        assert!(reloc_eq(&config, Some(&left), Some(&renamed)));
+
+        // Overrides all of the above
+        config.relax_reloc_diffs = true;
+        assert!(reloc_eq(&config, Some(&ext), Some(&ext_renamed)));
+    }
+
+    #[test]
+    fn small_data_section_mismatch() {
+        let reloc = |section: Option<&str>| ObjReloc {
+            sda_base: Some(ObjSdaBase::Gp),
+            ..reloc(elf::R_MIPS_GPREL16, section)
+        };
+        let mut config = config();
+        let sdata = reloc(Some(".sdata"));
+        assert!(reloc_eq(&config, Some(&sdata), Some(&reloc(Some(".sdata")))));
+        assert!(!reloc_eq(&config, Some(&sdata), Some(&reloc(Some(".sbss")))));
+        assert!(!reloc_eq(&config, Some(&sdata), Some(&reloc(Some(".scommon")))));
+        assert!(!reloc_eq(&config, Some(&sdata), Some(&reloc(None))));
+        assert!(reloc_eq(&config, Some(&reloc(None)), Some(&reloc(None))));
+        // Still checked when relocation differences are relaxed
+        config.relax_reloc_diffs = true;
+        assert!(!reloc_eq(&config, Some(&sdata), Some(&reloc(Some(".sbss")))));
+    }
+
+    #[test]
+    fn hoisted_delay_slot() {
+        let mut config = config();
+        config.group_delay_slots = true;
+        // `x` is hoisted into the delay slot on the left only
+        let diff_hoisted = |branch: &str, slot: ObjDelaySlot| {
+            let mut left = code(&[(0, branch), (4, "x")]);
+            left.insts[1].delay_slot = Some(slot);
+            let mut right = code(&[(0, "x"), (4, branch), (8, "nop")]);
+            right.insts[2].delay_slot = Some(slot);
+            let [left, right] = interned(left, right);
+            let (left_diff, right_diff) = diff(&config, &left, &right);
+            assert_eq!(rows(&left_diff), [None, Some(0), Some(4)]);
+            assert_eq!(rows(&right_diff), [Some(0), Some(4), Some(8)]);
+            (left_diff, right_diff)
+        };
+        let (none, moved) = (ObjInsDiffKind::None, ObjInsDiffKind::Moved);
+        let (left_diff, right_diff) = diff_hoisted("beq", ObjDelaySlot::Always);
+        assert_eq!(kinds(&left_diff), [none, none, moved]);
+        // The slot's `nop` didn't move
+        assert_eq!(kinds(&right_diff), [moved, none, ObjInsDiffKind::Replace]);
+        assert_eq!(left_diff[2].moved.as_ref().map(|m| m.ins_idx), Some(0));
+        assert_eq!(right_diff[0].moved.as_ref().map(|m| m.ins_idx), Some(2));
+        assert!(right_diff[2].moved.is_none());
+        let weights = config.score_weights;
+        assert_eq!(
+            diff_score(&weights, &left_diff, &right_diff),
+            weights.reorder + weights.insertion
+        );
+
+        // The slot of a branch-likely isn't executed when falling through
+        let (left_diff, right_diff) = diff_hoisted("beql", ObjDelaySlot::Likely);
+        assert!(!kinds(&left_diff).contains(&moved));
+        assert!(!kinds(&right_diff).contains(&moved));
+    }
+
+    /// Compares two instructions with the given arguments under equivalence
+    /// rules, returning the result and the resulting difference count.
+    fn compare_equivalent(
+        rules: &[(&str, &str)],
+        left: (&str, &[ObjInsArgValue]),
+        right: (&str, &[ObjInsArgValue]),
+    ) -> (ObjInsDiffKind, usize) {
+        let mut config = config();
+        config.equivalences = rules
+            .iter()
+            .map(|&(l, r)| EquivalenceRule { left: l.parse().unwrap(), right: r.parse().unwrap() })
+            .collect();
+        let [left_code, right_code] = interned(code(&[(0, left.0)]), code(&[(0, right.0)]));
+        let row = |mut code: ProcessCodeResult, args: &[ObjInsArgValue]| {
+            let mut ins = code.insts.remove(0);
+            ins.args = args.iter().cloned().map(ObjInsArg::Arg).collect();
+            ObjInsDiff { ins: Some(ins), ..Default::default() }
+        };
+        let mut state = InsDiffState::default();
+        let result =
+            compare_ins(&config, &row(left_code, left.1), &row(right_code, right.1), &mut state)
+                .unwrap();
+        (result.kind, state.diff_count)
+    }
+
+    #[test]
+    fn equivalent_instructions() {
+        let reg = |s: &str| ObjInsArgValue::Register(s.to_string());
+        let imm = ObjInsArgValue::Signed;
+        let rules = [
+            ("li {d}, {imm}", "addi {d}, r0, {imm}"),
+            ("move {d}, {s}", "or {d}, {s}, $zero"),
+            ("nop", "ori r0, r0, 0"),
+            ("or {d}, {s}, $zero", "or {d}, $zero, {s}"),
+        ];
+        let equivalent = (ObjInsDiffKind::Equivalent, 0);
+        let replace = (ObjInsDiffKind::Replace, 1);
+
+        let li = [reg("r3"), imm(16)];
+        let addi = [reg("r3"), reg("r0"), imm(16)];
+        assert_eq!(compare_equivalent(&rules, ("li", &li), ("addi", &addi)), equivalent);
+        // Rules apply in both directions
+        assert_eq!(compare_equivalent(&rules, ("addi", &addi), ("li", &li)), equivalent);
+        let mv = [reg("$a0"), reg("$a1")];
+        let or = [reg("$a0"), reg("$a1"), reg("$zero")];
+        assert_eq!(compare_equivalent(&rules, ("move", &mv), ("or", &or)), equivalent);
+        let ori = [reg("r0"), reg("r0"), imm(0)];
+        assert_eq!(compare_equivalent(&rules, ("ori", &ori), ("nop", &[])), equivalent);
+        // Same mnemonic with differing arguments
+        let or_swapped = [reg("$a0"), reg("$zero"), reg("$a1")];
+        assert_eq!(compare_equivalent(&rules, ("or", &or), ("or", &or_swapped)), equivalent);
+
+        // Captures sharing a name must hold matching arguments
+        let addi_other = [reg("r3"), reg("r0"), imm(32)];
+        assert_eq!(compare_equivalent(&rules, ("li", &li), ("addi", &addi_other)), replace);
+        let or_other = [reg("$a0"), reg("$a2"), reg("$zero")];
+        assert_eq!(compare_equivalent(&rules, ("move", &mv), ("or", &or_other)), replace);
+        // As do literals
+        let ori_other = [reg("r0"), reg("r0"), imm(1)];
+        assert_eq!(compare_equivalent(&rules, ("nop", &[]), ("ori", &ori_other)), replace);
+        assert_eq!(compare_equivalent(&[], ("li", &li), ("addi", &addi)), replace);
+    }
+}
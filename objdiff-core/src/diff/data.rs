@@ -1,27 +1,39 @@
 use std::{
     cmp::{max, min, Ordering},
     mem::take,
-    time::{Duration, Instant},
 };
 
 use anyhow::{ensure, Result};
-use similar::{capture_diff_slices_deadline, Algorithm};
+use similar::Algorithm;
 
 use crate::{
     diff::{
-        editops::{editops_find, LevEditType},
-        DiffAlg,
+        editops::{editops_find, editops_find_until, LevEditType},
+        limit::{capture_diff_slices_limited, DiffLimit},
+        DiffAlg, DiffObjConfig,
     },
     obj::{ObjDataDiff, ObjDataDiffKind, ObjSection, ObjSymbol},
 };
 
-pub fn diff_data(alg: DiffAlg, left: &mut ObjSection, right: &mut ObjSection) -> Result<()> {
-    match alg {
-        DiffAlg::Levenshtein => diff_data_lev(left, right),
-        DiffAlg::Lcs => diff_data_similar(Algorithm::Lcs, left, right),
-        DiffAlg::Myers => diff_data_similar(Algorithm::Myers, left, right),
-        DiffAlg::Patience => diff_data_similar(Algorithm::Patience, left, right),
+pub fn diff_data(
+    config: &DiffObjConfig,
+    left: &mut ObjSection,
+    right: &mut ObjSection,
+) -> Result<()> {
+    let limit = DiffLimit::start(config);
+    match config.data_alg {
+        DiffAlg::Levenshtein => diff_data_lev(limit, left, right)?,
+        DiffAlg::Lcs => diff_data_similar(Algorithm::Lcs, limit, left, right)?,
+        DiffAlg::Myers => diff_data_similar(Algorithm::Myers, limit, left, right)?,
+        DiffAlg::Patience => diff_data_similar(Algorithm::Patience, limit, left, right)?,
+    }
+    // Symbols are listed individually, mark them like truncated code symbols
+    for section in [left, right] {
+        for symbol in &mut section.symbols {
+            symbol.truncated = section.truncated;
+        }
     }
+    Ok(())
 }
 
 pub fn diff_bss_symbols(
@@ -96,11 +108,13 @@ pub fn diff_data_symbols(left: &mut ObjSection, right: &mut ObjSection) -> Resul
 
 pub fn diff_data_similar(
     alg: Algorithm,
+    limit: DiffLimit,
     left: &mut ObjSection,
     right: &mut ObjSection,
 ) -> Result<()> {
-    let deadline = Instant::now() + Duration::from_secs(5);
-    let ops = capture_diff_slices_deadline(alg, &left.data, &right.data, Some(deadline));
+    let (ops, truncated) = capture_diff_slices_limited(alg, &left.data, &right.data, limit);
+    left.truncated = truncated;
+    right.truncated = truncated;
 
     let mut left_diff = Vec::<ObjDataDiff>::new();
     let mut right_diff = Vec::<ObjDataDiff>::new();
@@ -175,18 +189,44 @@ pub fn diff_data_similar(
     Ok(())
 }
 
-pub fn diff_data_lev(left: &mut ObjSection, right: &mut ObjSection) -> Result<()> {
-    let matrix_size = (left.data.len() as u64).saturating_mul(right.data.len() as u64);
+pub fn diff_data_lev(
+    limit: DiffLimit,
+    left: &mut ObjSection,
+    right: &mut ObjSection,
+) -> Result<()> {
+    // Running time grows with the product of the section sizes, only diff huge
+    // sections when a deadline bounds it
+    let comparisons = (left.data.len() as u64).saturating_mul(right.data.len() as u64);
     ensure!(
-        matrix_size < 1_000_000_000,
-        "Data section {} too large for Levenshtein diff ({} * {} = {})",
+        limit.has_deadline() || comparisons < 1_000_000_000,
+        "Data section {} too large for Levenshtein diff without a deadline ({} * {} = {} \
+         comparisons)",
         left.name,
         left.data.len(),
         right.data.len(),
-        matrix_size
+        comparisons
     );
 
-    let edit_ops = editops_find(&left.data, &right.data);
+    let Some(edit_ops) = editops_find_until(&left.data, &right.data, || limit.reached()) else {
+        // Stopped early, show the sections as replaced entirely
+        left.truncated = true;
+        right.truncated = true;
+        left.data_diff = vec![ObjDataDiff {
+            data: left.data.clone(),
+            kind: ObjDataDiffKind::Replace,
+            len: left.data.len(),
+            symbol: String::new(),
+        }];
+        right.data_diff = vec![ObjDataDiff {
+            data: right.data.clone(),
+            kind: ObjDataDiffKind::Replace,
+            len: right.data.len(),
+            symbol: String::new(),
+        }];
+        return Ok(());
+    };
+    left.truncated = false;
+    right.truncated = false;
     if edit_ops.is_empty() && !left.data.is_empty() {
         left.data_diff = vec![ObjDataDiff {
             data: left.data.clone(),
@@ -403,3 +443,54 @@ pub fn no_diff_data(section: &mut ObjSection) {
         symbol: String::new(),
     }];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::ObjSectionKind;
+
+    fn section(data: &[u8], symbols: &[(&str, u64, u64)]) -> ObjSection {
+        ObjSection {
+            name: ".data".to_string(),
+            kind: ObjSectionKind::Data,
+            address: 0,
+            size: data.len() as u64,
+            align: 4,
+            data: data.to_vec(),
+            index: 0,
+            symbols: symbols
+                .iter()
+                .map(|&(name, address, size)| ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section_address: address,
+                    size,
+                    size_known: true,
+                    ..Default::default()
+                })
+                .collect(),
+            relocations: vec![],
+            jump_tables: vec![],
+            data_diff: vec![],
+            match_percent: 0.0,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn truncated_symbols() {
+        for data_alg in [DiffAlg::Levenshtein, DiffAlg::Patience] {
+            let config = DiffObjConfig { data_alg, ..Default::default() };
+            let mut left = section(&[1, 2, 3, 4, 5, 6, 7, 8], &[("a", 0, 4), ("b", 4, 4)]);
+            let mut right = section(&[1, 2, 3, 4, 5, 6, 0, 8], &[("a", 0, 4), ("b", 4, 4)]);
+            diff_data(&config, &mut left, &mut right).unwrap();
+            assert!(!left.truncated);
+            assert!(left.symbols.iter().chain(&right.symbols).all(|s| !s.truncated));
+
+            config.cancel.cancel();
+            diff_data(&config, &mut left, &mut right).unwrap();
+            assert!(left.truncated && right.truncated, "{data_alg:?}");
+            assert!(left.symbols.iter().chain(&right.symbols).all(|s| s.truncated), "{data_alg:?}");
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use crate::obj::{
+    ObjDebugInfo, ObjFunctionInfo, ObjGlobalInfo, ObjMember, ObjTypeInfo, ObjTypeKind,
+};
+
+/// Functions, globals and types of the target's and base's debug information,
+/// paired by name.
+#[derive(Debug, Clone, Default)]
+pub struct DebugInfoDiff {
+    pub functions: Vec<DebugEntryDiff>,
+    pub globals: Vec<DebugEntryDiff>,
+    pub types: Vec<DebugEntryDiff>,
+}
+
+/// Entry of the target's debug information, the base's, or both. Target
+/// entries come first, in their original order.
+#[derive(Debug, Clone)]
+pub struct DebugEntryDiff {
+    pub name: String,
+    /// Index into the target's functions, globals or types
+    pub left: Option<usize>,
+    /// Index into the base's functions, globals or types
+    pub right: Option<usize>,
+    /// Empty unless the entry is present on both sides
+    pub differences: Vec<DebugDifference>,
+}
+
+impl DebugEntryDiff {
+    pub fn is_match(&self) -> bool {
+        self.left.is_some() && self.right.is_some() && self.differences.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DebugDifference {
+    /// Function prototype, global declaration, or type kind, name and bases
+    Signature {
+        left: String,
+        right: String,
+    },
+    /// Local variables, not counting those of inlined functions
+    LocalCount {
+        left: usize,
+        right: usize,
+    },
+    /// Local variable with a different type, or only present on one side
+    Local {
+        name: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+    Size {
+        left: Option<u64>,
+        right: Option<u64>,
+    },
+    /// Data member with a different offset or type, or only present on one side
+    Member {
+        name: String,
+        left: Option<ObjMember>,
+        right: Option<ObjMember>,
+    },
+    /// Enum constant with a different value, or only present on one side
+    Enumerator {
+        name: String,
+        left: Option<i64>,
+        right: Option<i64>,
+    },
+}
+
+impl DebugDifference {
+    /// Describes what differs, e.g. `member next`.
+    pub fn label(&self) -> String {
+        match self {
+            DebugDifference::Signature { .. } => "signature".to_string(),
+            DebugDifference::LocalCount { .. } => "local count".to_string(),
+            DebugDifference::Local { name, .. } => format!("local {name}"),
+            DebugDifference::Size { .. } => "size".to_string(),
+            DebugDifference::Member { name, .. } => format!("member {name}"),
+            DebugDifference::Enumerator { name, .. } => format!("enumerator {name}"),
+        }
+    }
+
+    /// Returns the target's and base's values, empty where missing.
+    pub fn values(&self) -> (String, String) {
+        fn opt<T>(value: &Option<T>, f: impl Fn(&T) -> String) -> String {
+            value.as_ref().map(f).unwrap_or_default()
+        }
+        match self {
+            DebugDifference::Signature { left, right } => (left.clone(), right.clone()),
+            DebugDifference::LocalCount { left, right } => (left.to_string(), right.to_string()),
+            DebugDifference::Local { left, right, .. } => {
+                (opt(left, String::clone), opt(right, String::clone))
+            }
+            DebugDifference::Size { left, right } => {
+                (opt(left, |size| format!("{size:#x}")), opt(right, |size| format!("{size:#x}")))
+            }
+            DebugDifference::Member { left, right, .. } => {
+                (opt(left, member_text), opt(right, member_text))
+            }
+            DebugDifference::Enumerator { left, right, .. } => {
+                (opt(left, i64::to_string), opt(right, i64::to_string))
+            }
+        }
+    }
+}
+
+/// Writes a member with its offset, e.g. `0x8: u32 flags : 3`.
+fn member_text(member: &ObjMember) -> String {
+    let mut out = match member.offset {
+        Some(offset) => format!("{offset:#x}: {}", member.declaration),
+        None => member.declaration.clone(),
+    };
+    if let Some(bit_size) = member.bit_size {
+        out.push_str(&format!(" : {bit_size}"));
+    }
+    if let Some(bit_offset) = member.bit_offset {
+        out.push_str(&format!(" (bit {bit_offset})"));
+    }
+    out
+}
+
+/// Pairs the functions, globals and types of the target's debug information
+/// with the base's.
+pub fn diff_debug_info(left: &ObjDebugInfo, right: &ObjDebugInfo) -> DebugInfoDiff {
+    DebugInfoDiff {
+        functions: diff_entries(
+            &left.functions,
+            &right.functions,
+            |f| f.linkage_name.clone().unwrap_or_else(|| f.name.clone()),
+            |f| &f.name,
+            diff_function,
+        ),
+        globals: diff_entries(
+            &left.globals,
+            &right.globals,
+            |g| g.linkage_name.clone().unwrap_or_else(|| g.name.clone()),
+            |g| &g.name,
+            diff_global,
+        ),
+        // A typedef can share its name with the struct it aliases
+        types: diff_entries(
+            &left.types,
+            &right.types,
+            |t| (t.kind == ObjTypeKind::Typedef, t.name.clone()),
+            |t| &t.name,
+            diff_type,
+        ),
+    }
+}
+
+fn diff_entries<T, K: Eq + Hash>(
+    left: &[T],
+    right: &[T],
+    key: impl Fn(&T) -> K,
+    name: impl Fn(&T) -> &String,
+    diff: impl Fn(&T, &T) -> Vec<DebugDifference>,
+) -> Vec<DebugEntryDiff> {
+    pair(left, right, key)
+        .into_iter()
+        .map(|(l, r)| {
+            let entry = l.map(|i| &left[i]).or_else(|| r.map(|i| &right[i])).unwrap();
+            let differences = match (l, r) {
+                (Some(l), Some(r)) => diff(&left[l], &right[r]),
+                _ => vec![],
+            };
+            DebugEntryDiff { name: name(entry).clone(), left: l, right: r, differences }
+        })
+        .collect()
+}
+
+/// Pairs items with equal keys, in order when a key repeats. Unpaired right
+/// items follow the left items.
+fn pair<T, K: Eq + Hash>(
+    left: &[T],
+    right: &[T],
+    key: impl Fn(&T) -> K,
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut right_by_key = HashMap::<K, VecDeque<usize>>::new();
+    for (i, item) in right.iter().enumerate() {
+        right_by_key.entry(key(item)).or_default().push_back(i);
+    }
+    let mut paired = vec![false; right.len()];
+    let mut out = Vec::with_capacity(left.len().max(right.len()));
+    for (i, item) in left.iter().enumerate() {
+        let j = right_by_key.get_mut(&key(item)).and_then(VecDeque::pop_front);
+        if let Some(j) = j {
+            paired[j] = true;
+        }
+        out.push((Some(i), j));
+    }
+    out.extend(paired.iter().enumerate().filter(|(_, &p)| !p).map(|(j, _)| (None, Some(j))));
+    out
+}
+
+fn diff_function(left: &ObjFunctionInfo, right: &ObjFunctionInfo) -> Vec<DebugDifference> {
+    let mut out = vec![];
+    if left.prototype != right.prototype {
+        out.push(DebugDifference::Signature {
+            left: left.prototype.clone(),
+            right: right.prototype.clone(),
+        });
+    }
+    let left_locals =
+        left.locals.iter().filter(|var| var.inlined_from.is_none()).collect::<Vec<_>>();
+    let right_locals =
+        right.locals.iter().filter(|var| var.inlined_from.is_none()).collect::<Vec<_>>();
+    if left_locals.len() != right_locals.len() {
+        out.push(DebugDifference::LocalCount {
+            left: left_locals.len(),
+            right: right_locals.len(),
+        });
+    }
+    for (l, r) in pair(&left_locals, &right_locals, |var| var.name.clone()) {
+        let (l, r) = (l.map(|i| left_locals[i]), r.map(|i| right_locals[i]));
+        if l.zip(r).is_some_and(|(l, r)| l.declaration == r.declaration) {
+            continue;
+        }
+        out.push(DebugDifference::Local {
+            name: l.or(r).unwrap().name.clone(),
+            left: l.map(|var| var.declaration.clone()),
+            right: r.map(|var| var.declaration.clone()),
+        });
+    }
+    out
+}
+
+fn diff_global(left: &ObjGlobalInfo, right: &ObjGlobalInfo) -> Vec<DebugDifference> {
+    if left.declaration == right.declaration {
+        return vec![];
+    }
+    vec![DebugDifference::Signature {
+        left: left.declaration.clone(),
+        right: right.declaration.clone(),
+    }]
+}
+
+/// Writes the part of a type's definition preceding its body, e.g.
+/// `struct Derived : Base` or `typedef int (*Callback)(void)`.
+fn type_header(info: &ObjTypeInfo) -> String {
+    if let Some(typedef) = &info.typedef {
+        return format!("typedef {typedef}");
+    }
+    let mut out = if info.anonymous {
+        format!("typedef {} {}", info.kind.keyword(), info.name)
+    } else {
+        format!("{} {}", info.kind.keyword(), info.name)
+    };
+    if !info.bases.is_empty() {
+        out.push_str(" : ");
+        out.push_str(&info.bases.join(", "));
+    }
+    out
+}
+
+fn diff_type(left: &ObjTypeInfo, right: &ObjTypeInfo) -> Vec<DebugDifference> {
+    let mut out = vec![];
+    let (left_header, right_header) = (type_header(left), type_header(right));
+    if left_header != right_header {
+        out.push(DebugDifference::Signature { left: left_header, right: right_header });
+    }
+    if left.size != right.size {
+        out.push(DebugDifference::Size { left: left.size, right: right.size });
+    }
+    for (l, r) in pair(&left.members, &right.members, |member| member.name.clone()) {
+        let (l, r) = (l.map(|i| &left.members[i]), r.map(|i| &right.members[i]));
+        if l.zip(r).is_some_and(|(l, r)| l == r) {
+            continue;
+        }
+        out.push(DebugDifference::Member {
+            name: l.or(r).unwrap().name.clone(),
+            left: l.cloned(),
+            right: r.cloned(),
+        });
+    }
+    for (l, r) in pair(&left.enumerators, &right.enumerators, |(name, _)| name.clone()) {
+        let (l, r) = (l.map(|i| &left.enumerators[i]), r.map(|i| &right.enumerators[i]));
+        if l.zip(r).is_some_and(|(l, r)| l.1 == r.1) {
+            continue;
+        }
+        out.push(DebugDifference::Enumerator {
+            name: l.or(r).unwrap().0.clone(),
+            left: l.map(|(_, value)| *value),
+            right: r.map(|(_, value)| *value),
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::ObjVariable;
+
+    fn function(name: &str, prototype: &str, locals: &[(&str, &str)]) -> ObjFunctionInfo {
+        ObjFunctionInfo {
+            name: name.to_string(),
+            linkage_name: None,
+            address: 0,
+            size: 0,
+            external: true,
+            prototype: prototype.to_string(),
+            uses: vec![],
+            params: vec![],
+            locals: locals.iter().map(|&(name, declaration)| local(name, declaration)).collect(),
+            inlined: vec![],
+        }
+    }
+
+    fn local(name: &str, declaration: &str) -> ObjVariable {
+        ObjVariable {
+            name: name.to_string(),
+            declaration: declaration.to_string(),
+            inlined_from: None,
+            locations: vec![],
+        }
+    }
+
+    fn global(name: &str, declaration: &str) -> ObjGlobalInfo {
+        ObjGlobalInfo {
+            name: name.to_string(),
+            linkage_name: None,
+            declaration: declaration.to_string(),
+            uses: vec![],
+            address: None,
+            external: true,
+        }
+    }
+
+    fn ty(kind: ObjTypeKind, name: &str, size: u64, members: &[(&str, &str, u64)]) -> ObjTypeInfo {
+        ObjTypeInfo {
+            kind,
+            name: name.to_string(),
+            anonymous: false,
+            size: Some(size),
+            bases: vec![],
+            members: members
+                .iter()
+                .map(|&(name, declaration, offset)| member(name, declaration, offset))
+                .collect(),
+            enumerators: vec![],
+            typedef: None,
+            uses: vec![],
+        }
+    }
+
+    fn member(name: &str, declaration: &str, offset: u64) -> ObjMember {
+        ObjMember {
+            name: name.to_string(),
+            declaration: declaration.to_string(),
+            offset: Some(offset),
+            bit_size: None,
+            bit_offset: None,
+        }
+    }
+
+    fn pairs(entries: &[DebugEntryDiff]) -> Vec<(&str, Option<usize>, Option<usize>)> {
+        entries.iter().map(|e| (e.name.as_str(), e.left, e.right)).collect()
+    }
+
+    #[test]
+    fn pair_entries() {
+        let mut mangled = function("f", "void f(void)", &[]);
+        mangled.linkage_name = Some("_Z1fv".to_string());
+        let mut overload = function("f", "void f(int)", &[]);
+        overload.linkage_name = Some("_Z1fi".to_string());
+        let left = ObjDebugInfo {
+            functions: vec![
+                function("a", "void a(void)", &[]),
+                function("local", "void local(void)", &[]),
+                function("local", "void local(void)", &[]),
+                mangled,
+            ],
+            globals: vec![global("x", "int x")],
+            types: vec![ty(ObjTypeKind::Struct, "Vec", 8, &[]), ObjTypeInfo {
+                typedef: Some("struct Vec Vec".to_string()),
+                ..ty(ObjTypeKind::Typedef, "Vec", 8, &[])
+            }],
+        };
+        let right = ObjDebugInfo {
+            functions: vec![
+                overload,
+                function("local", "void local(void)", &[]),
+                function("b", "void b(void)", &[]),
+                function("local", "void local(void)", &[]),
+            ],
+            globals: vec![],
+            types: left.types.iter().rev().cloned().collect(),
+        };
+        let diff = diff_debug_info(&left, &right);
+        // Same-named entries pair in order, overloads by linkage name, and
+        // unpaired base entries follow in their order
+        assert_eq!(pairs(&diff.functions), [
+            ("a", Some(0), None),
+            ("local", Some(1), Some(1)),
+            ("local", Some(2), Some(3)),
+            ("f", Some(3), None),
+            ("f", None, Some(0)),
+            ("b", None, Some(2)),
+        ]);
+        assert!(diff.functions[1].is_match() && !diff.functions[0].is_match());
+        assert_eq!(pairs(&diff.globals), [("x", Some(0), None)]);
+        // A struct and the typedef of the same name pair by kind
+        assert_eq!(pairs(&diff.types), [("Vec", Some(0), Some(1)), ("Vec", Some(1), Some(0))]);
+        assert!(diff.types.iter().all(DebugEntryDiff::is_match));
+    }
+
+    #[test]
+    fn function_differences() {
+        let left = function("f", "int f(int a)", &[("i", "int i"), ("s", "char* s")]);
+        let mut right =
+            function("f", "int f(short a)", &[("i", "int i"), ("s", "short s"), ("t", "float t")]);
+        // Locals of inlined functions aren't counted or compared
+        right
+            .locals
+            .push(ObjVariable { inlined_from: Some("g".to_string()), ..local("u", "int u") });
+        assert_eq!(diff_function(&left, &right), [
+            DebugDifference::Signature {
+                left: "int f(int a)".to_string(),
+                right: "int f(short a)".to_string(),
+            },
+            DebugDifference::LocalCount { left: 2, right: 3 },
+            DebugDifference::Local {
+                name: "s".to_string(),
+                left: Some("char* s".to_string()),
+                right: Some("short s".to_string()),
+            },
+            DebugDifference::Local {
+                name: "t".to_string(),
+                left: None,
+                right: Some("float t".to_string())
+            },
+        ]);
+        assert!(diff_function(&left, &left).is_empty());
+    }
+
+    #[test]
+    fn global_differences() {
+        let left = global("counts", "int counts[4]");
+        assert!(diff_global(&left, &left).is_empty());
+        assert_eq!(diff_global(&left, &global("counts", "short counts[4]")), [
+            DebugDifference::Signature {
+                left: "int counts[4]".to_string(),
+                right: "short counts[4]".to_string(),
+            }
+        ]);
+    }
+
+    #[test]
+    fn type_differences() {
+        let left = ty(ObjTypeKind::Struct, "Node", 8, &[
+            ("value", "int value", 0),
+            ("next", "struct Node* next", 4),
+        ]);
+        let right = ObjTypeInfo {
+            bases: vec!["Base".to_string()],
+            ..ty(ObjTypeKind::Struct, "Node", 16, &[
+                ("value", "int value", 0),
+                ("next", "struct Node* next", 8),
+                ("prev", "struct Node* prev", 12),
+            ])
+        };
+        let differences = diff_type(&left, &right);
+        assert_eq!(differences, [
+            DebugDifference::Signature {
+                left: "struct Node".to_string(),
+                right: "struct Node : Base".to_string(),
+            },
+            DebugDifference::Size { left: Some(8), right: Some(16) },
+            DebugDifference::Member {
+                name: "next".to_string(),
+                left: Some(member("next", "struct Node* next", 4)),
+                right: Some(member("next", "struct Node* next", 8)),
+            },
+            DebugDifference::Member {
+                name: "prev".to_string(),
+                left: None,
+                right: Some(member("prev", "struct Node* prev", 12)),
+            },
+        ]);
+        assert_eq!(differences[2].label(), "member next");
+        assert_eq!(
+            differences[2].values(),
+            ("0x4: struct Node* next".to_string(), "0x8: struct Node* next".to_string())
+        );
+
+        let enumeration = |enumerators: &[(&str, i64)]| ObjTypeInfo {
+            enumerators: enumerators
+                .iter()
+                .map(|&(name, value)| (name.to_string(), value))
+                .collect(),
+            ..ty(ObjTypeKind::Enum, "Color", 4, &[])
+        };
+        assert_eq!(
+            diff_type(
+                &enumeration(&[("RED", 0), ("GREEN", 1)]),
+                &enumeration(&[("RED", 0), ("GREEN", 2)])
+            ),
+            [DebugDifference::Enumerator {
+                name: "GREEN".to_string(),
+                left: Some(1),
+                right: Some(2)
+            }]
+        );
+    }
+}
@@ -39,8 +39,148 @@ pub struct LevEditOp {
     pub second_start: usize,  /* destination position */
 }
 
+/// Cost matrices larger than this many cells are split with Hirschberg's algorithm,
+/// keeping memory usage linear for very large inputs.
+const MAX_MATRIX_SIZE: usize = 1 << 22;
+
 pub fn editops_find<T>(query: &[T], choice: &[T]) -> Vec<LevEditOp>
 where T: PartialEq {
+    editops_find_until(query, choice, || false).unwrap_or_default()
+}
+
+/// Finds edit operations like [`editops_find`], giving up once `stop` returns
+/// true. `stop` is checked once per row of the cost matrix.
+pub fn editops_find_until<T>(
+    query: &[T],
+    choice: &[T],
+    stop: impl Fn() -> bool,
+) -> Option<Vec<LevEditOp>>
+where
+    T: PartialEq,
+{
+    editops_split_until(query, choice, MAX_MATRIX_SIZE, &stop)
+}
+
+fn editops_split_until<T>(
+    query: &[T],
+    choice: &[T],
+    max_matrix_size: usize,
+    stop: &dyn Fn() -> bool,
+) -> Option<Vec<LevEditOp>>
+where
+    T: PartialEq,
+{
+    let mut ops = Vec::new();
+    editops_split(query, choice, 0, 0, max_matrix_size, stop, &mut ops)?;
+    Some(ops)
+}
+
+/// Finds edit operations using a full cost matrix, regardless of input size.
+/// This uses O(n*m) memory; prefer [`editops_find`].
+pub fn editops_find_matrix<T>(query: &[T], choice: &[T]) -> Vec<LevEditOp>
+where T: PartialEq {
+    let mut ops = Vec::new();
+    editops_matrix(query, choice, 0, 0, &|| false, &mut ops);
+    ops
+}
+
+fn editops_split<T>(
+    query: &[T],
+    choice: &[T],
+    first_offset: usize,
+    second_offset: usize,
+    max_matrix_size: usize,
+    stop: &dyn Fn() -> bool,
+    ops: &mut Vec<LevEditOp>,
+) -> Option<()>
+where
+    T: PartialEq,
+{
+    let matrix_size = (query.len() + 1).saturating_mul(choice.len() + 1);
+    if matrix_size <= max_matrix_size || query.len() < 2 {
+        return editops_matrix(query, choice, first_offset, second_offset, stop, ops);
+    }
+
+    let Affix { prefix_len, suffix_len } = Affix::find(query, choice);
+    if prefix_len > 0 || suffix_len > 0 {
+        return editops_split(
+            &query[prefix_len..query.len() - suffix_len],
+            &choice[prefix_len..choice.len() - suffix_len],
+            first_offset + prefix_len,
+            second_offset + prefix_len,
+            max_matrix_size,
+            stop,
+            ops,
+        );
+    }
+
+    // Find where an optimal path crosses the middle row, then solve each half
+    let mid = query.len() / 2;
+    let upper = last_row_costs(&query[..mid], choice, false, stop)?;
+    let lower = last_row_costs(&query[mid..], choice, true, stop)?;
+    let split = (0..=choice.len()).min_by_key(|&j| upper[j] + lower[choice.len() - j]).unwrap();
+    editops_split(
+        &query[..mid],
+        &choice[..split],
+        first_offset,
+        second_offset,
+        max_matrix_size,
+        stop,
+        ops,
+    )?;
+    editops_split(
+        &query[mid..],
+        &choice[split..],
+        first_offset + mid,
+        second_offset + split,
+        max_matrix_size,
+        stop,
+        ops,
+    )
+}
+
+/// Computes the edit distance between `query` and every prefix of `choice`
+/// (or suffix, when `reverse` is set) using a single row of the cost matrix.
+/// Returns `None` if stopped.
+fn last_row_costs<T>(
+    query: &[T],
+    choice: &[T],
+    reverse: bool,
+    stop: &dyn Fn() -> bool,
+) -> Option<Vec<usize>>
+where
+    T: PartialEq,
+{
+    let query_at = |i: usize| if reverse { &query[query.len() - 1 - i] } else { &query[i] };
+    let choice_at = |j: usize| if reverse { &choice[choice.len() - 1 - j] } else { &choice[j] };
+    let mut row: Vec<usize> = (0..=choice.len()).collect();
+    for i in 0..query.len() {
+        if stop() {
+            return None;
+        }
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for j in 0..choice.len() {
+            let cost =
+                (diag + (query_at(i) != choice_at(j)) as usize).min(row[j + 1] + 1).min(row[j] + 1);
+            diag = row[j + 1];
+            row[j + 1] = cost;
+        }
+    }
+    Some(row)
+}
+
+fn editops_matrix<T>(
+    query: &[T],
+    choice: &[T],
+    first_offset: usize,
+    second_offset: usize,
+    stop: &dyn Fn() -> bool,
+    ops: &mut Vec<LevEditOp>,
+) -> Option<()>
+where
+    T: PartialEq,
+{
     let Affix { prefix_len, suffix_len } = Affix::find(query, choice);
 
     let first_string = &query[prefix_len..query.len() - suffix_len];
@@ -59,6 +199,9 @@ where T: PartialEq {
     }
 
     for (i, char1) in first_string.iter().enumerate() {
+        if stop() {
+            return None;
+        }
         let mut prev = i * matrix_rows;
         let current = prev + matrix_rows;
         let mut x = i + 1;
@@ -76,16 +219,27 @@ where T: PartialEq {
             cache_matrix[current + 1 + p] = x;
         }
     }
-    editops_from_cost_matrix(matrix_columns, matrix_rows, prefix_len, cache_matrix)
+    editops_from_cost_matrix(
+        matrix_columns,
+        matrix_rows,
+        first_offset + prefix_len,
+        second_offset + prefix_len,
+        cache_matrix,
+        ops,
+    );
+    Some(())
 }
 
 fn editops_from_cost_matrix(
     len1: usize,
     len2: usize,
-    prefix_len: usize,
+    first_offset: usize,
+    second_offset: usize,
     cache_matrix: Vec<usize>,
-) -> Vec<LevEditOp> {
-    let mut ops = Vec::with_capacity(cache_matrix[len1 * len2 - 1]);
+    ops: &mut Vec<LevEditOp>,
+) {
+    let start = ops.len();
+    ops.reserve(cache_matrix[len1 * len2 - 1]);
     let mut dir = 0;
     let mut i = len1 - 1;
     let mut j = len2 - 1;
@@ -130,15 +284,16 @@ fn editops_from_cost_matrix(
         dir = new_dir;
 
         if let Some(op_type) = op_type {
-            ops.insert(0, LevEditOp {
+            ops.push(LevEditOp {
                 op_type,
-                first_start: i + prefix_len,
-                second_start: j + prefix_len,
+                first_start: i + first_offset,
+                second_start: j + second_offset,
             });
         }
     }
 
-    ops
+    // Operations were found in reverse order
+    ops[start..].reverse();
 }
 
 pub struct Affix {
@@ -160,3 +315,74 @@ impl Affix {
         Affix { prefix_len, suffix_len }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that `ops` turn `query` into `choice`, and that every replaced
+    /// element differs.
+    fn assert_valid<T: PartialEq + std::fmt::Debug>(query: &[T], choice: &[T], ops: &[LevEditOp]) {
+        let (mut i, mut j) = (0, 0);
+        for op in ops {
+            assert!(op.first_start >= i && op.second_start >= j, "out of order: {op:?}");
+            // Unchanged elements between edits
+            assert_eq!(op.first_start - i, op.second_start - j, "misaligned: {op:?}");
+            assert_eq!(query[i..op.first_start], choice[j..op.second_start]);
+            (i, j) = (op.first_start, op.second_start);
+            match op.op_type {
+                LevEditType::Replace => {
+                    assert_ne!(query[i], choice[j]);
+                    i += 1;
+                    j += 1;
+                }
+                LevEditType::Insert => j += 1,
+                LevEditType::Delete => i += 1,
+            }
+        }
+        assert_eq!(query[i..], choice[j..]);
+    }
+
+    #[test]
+    fn split_matches_matrix() {
+        let mut state = 0x2545f491u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        for _ in 0..500 {
+            let query_len = next() as usize % 60;
+            let query = (0..query_len).map(|_| next() as u8 % 4).collect::<Vec<_>>();
+            // Edited copies share affixes and runs, random ones mostly don't
+            let choice = if next() % 2 == 0 {
+                let mut choice = Vec::new();
+                for &op in &query {
+                    match next() % 6 {
+                        0 => {}
+                        1 => choice.extend([op, next() as u8 % 4]),
+                        2 => choice.push(next() as u8 % 4),
+                        _ => choice.push(op),
+                    }
+                }
+                choice
+            } else {
+                (0..next() as usize % 60).map(|_| next() as u8 % 4).collect()
+            };
+            let expected = editops_find_matrix(&query, &choice);
+            assert_valid(&query, &choice, &expected);
+            // Split down to the smallest matrices
+            let ops = editops_split_until(&query, &choice, 4, &|| false).unwrap();
+            assert_valid(&query, &choice, &ops);
+            assert_eq!(ops.len(), expected.len(), "{query:?} {choice:?}");
+        }
+    }
+
+    #[test]
+    fn split_stops() {
+        let query = vec![0u8; 100];
+        let choice = vec![1u8; 100];
+        assert!(editops_split_until(&query, &choice, 4, &|| true).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, ensure, Error, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::obj::{parse_int, ObjIns, ObjInsArg, ObjInsArgValue};
+
+/// Declares two instruction patterns equivalent, e.g. `li {d}, {imm}` and
+/// `addi {d}, r0, {imm}`. Rules apply in both directions.
+///
+/// Operands are literals, compared numerically when they parse as integers, or
+/// `{name}` captures. Captures sharing a name across both patterns must hold
+/// matching arguments; the rest match anything. `off(base)` operands match
+/// arguments displayed with a base register.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct EquivalenceRule {
+    pub left: InsPattern,
+    pub right: InsPattern,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct InsPattern {
+    source: String,
+    mnemonic: String,
+    operands: Vec<PatternOperand>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct PatternOperand {
+    value: PatternValue,
+    /// Offset of an `off(base)` operand
+    with_base: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum PatternValue {
+    Capture(String),
+    Literal(String),
+}
+
+/// Arguments captured by a pattern, by capture name.
+pub(crate) type Captures<'a> = HashMap<&'a str, &'a ObjInsArg>;
+
+impl InsPattern {
+    /// Matches an instruction against the pattern, returning its captures.
+    pub(crate) fn captures<'a>(&'a self, ins: &'a ObjIns) -> Option<Captures<'a>> {
+        if ins.mnemonic != self.mnemonic || ins.args.len() != self.operands.len() {
+            return None;
+        }
+        let mut captures = Captures::new();
+        for (operand, arg) in self.operands.iter().zip(&ins.args) {
+            let arg_with_base = matches!(arg, ObjInsArg::ArgWithBase(_) | ObjInsArg::RelocWithBase);
+            if operand.with_base != arg_with_base {
+                return None;
+            }
+            match &operand.value {
+                PatternValue::Capture(name) => {
+                    if captures.insert(name.as_str(), arg).is_some_and(|prev| prev != arg) {
+                        return None;
+                    }
+                }
+                PatternValue::Literal(literal) => match arg {
+                    ObjInsArg::Arg(value) | ObjInsArg::ArgWithBase(value) => {
+                        if !literal_eq(literal, value) {
+                            return None;
+                        }
+                    }
+                    _ => return None,
+                },
+            }
+        }
+        Some(captures)
+    }
+}
+
+fn literal_eq(literal: &str, value: &ObjInsArgValue) -> bool {
+    let Some(literal_int) = parse_int(literal) else {
+        return matches!(value, ObjInsArgValue::Opaque(s) | ObjInsArgValue::Register(s) if s == literal);
+    };
+    match value {
+        ObjInsArgValue::Signed(v) => *v as i64 == literal_int,
+        ObjInsArgValue::Unsigned(v) => *v as i64 == literal_int,
+        ObjInsArgValue::Opaque(s) => parse_int(s) == Some(literal_int) || s == literal,
+        ObjInsArgValue::Register(_) => false,
+    }
+}
+
+fn parse_operand(s: &str) -> Result<Vec<PatternOperand>> {
+    let value = |s: &str| -> Result<PatternValue> {
+        let s = s.trim();
+        ensure!(!s.is_empty(), "Empty operand");
+        if let Some(name) = s.strip_prefix('{') {
+            let name =
+                name.strip_suffix('}').ok_or_else(|| anyhow!("Unterminated capture '{s}'"))?;
+            ensure!(
+                !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'),
+                "Invalid capture name '{s}'"
+            );
+            Ok(PatternValue::Capture(name.to_string()))
+        } else {
+            Ok(PatternValue::Literal(s.to_string()))
+        }
+    };
+    match s.trim().strip_suffix(')').and_then(|s| s.split_once('(')) {
+        Some((offset, base)) => {
+            let offset = PatternOperand { value: value(offset)?, with_base: true };
+            let base = PatternOperand { value: value(base)?, with_base: false };
+            Ok(vec![offset, base])
+        }
+        None => Ok(vec![PatternOperand { value: value(s)?, with_base: false }]),
+    }
+}
+
+impl FromStr for InsPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (mnemonic, operands) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        if mnemonic.is_empty() {
+            bail!("Missing mnemonic in pattern '{s}'");
+        }
+        let mut parsed = vec![];
+        if !operands.trim().is_empty() {
+            for operand in operands.split(',') {
+                parsed.extend(parse_operand(operand).map_err(|e| anyhow!("{e} in pattern '{s}'"))?);
+            }
+        }
+        Ok(Self { source: s.to_string(), mnemonic: mnemonic.to_string(), operands: parsed })
+    }
+}
+
+impl TryFrom<String> for InsPattern {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> { value.parse() }
+}
+
+impl From<InsPattern> for String {
+    fn from(value: InsPattern) -> Self { value.source }
+}
+
+impl fmt::Display for InsPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.source) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(name: &str, with_base: bool) -> PatternOperand {
+        PatternOperand { value: PatternValue::Capture(name.to_string()), with_base }
+    }
+
+    fn literal(value: &str, with_base: bool) -> PatternOperand {
+        PatternOperand { value: PatternValue::Literal(value.to_string()), with_base }
+    }
+
+    fn error(s: &str) -> String { s.parse::<InsPattern>().unwrap_err().to_string() }
+
+    #[test]
+    fn parse_patterns() {
+        let pattern = "addi {d}, r0, {imm}".parse::<InsPattern>().unwrap();
+        assert_eq!(pattern.mnemonic, "addi");
+        assert_eq!(pattern.operands, [
+            capture("d", false),
+            literal("r0", false),
+            capture("imm", false)
+        ]);
+        assert_eq!(pattern.to_string(), "addi {d}, r0, {imm}");
+
+        let pattern = "  lw {d}, 0x10({base}) ".parse::<InsPattern>().unwrap();
+        assert_eq!(pattern.operands, [
+            capture("d", false),
+            literal("0x10", true),
+            capture("base", false)
+        ]);
+        assert_eq!(pattern.to_string(), "lw {d}, 0x10({base})");
+
+        let pattern = "nop".parse::<InsPattern>().unwrap();
+        assert_eq!((pattern.mnemonic.as_str(), pattern.operands.len()), ("nop", 0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(error("li {d, 1"), "Unterminated capture '{d' in pattern 'li {d, 1'");
+        assert_eq!(error("li {}, 1"), "Invalid capture name '{}' in pattern 'li {}, 1'");
+        assert_eq!(error("li {a-b}, 1"), "Invalid capture name '{a-b}' in pattern 'li {a-b}, 1'");
+        assert_eq!(error("lw {d}, ({base})"), "Empty operand in pattern 'lw {d}, ({base})'");
+        assert_eq!(error("li {d},, 1"), "Empty operand in pattern 'li {d},, 1'");
+        assert_eq!(error(""), "Missing mnemonic in pattern ''");
+        assert_eq!(error("   "), "Missing mnemonic in pattern ''");
+    }
+}
@@ -0,0 +1,191 @@
+use std::{convert::Infallible, hash::Hash, time::Instant};
+
+use similar::{
+    algorithms::{diff_slices_deadline, Capture, DiffHook, Replace},
+    Algorithm, DiffOp,
+};
+
+use crate::diff::{CancellationToken, DiffObjConfig};
+
+/// Time limit and cancellation of a single diff, checked by the alignment
+/// algorithms while they run.
+#[derive(Copy, Clone)]
+pub struct DiffLimit<'a> {
+    deadline: Option<Instant>,
+    cancel: &'a CancellationToken,
+}
+
+impl<'a> DiffLimit<'a> {
+    /// Starts the time limit given by [`DiffObjConfig::deadline`].
+    pub fn start(config: &'a DiffObjConfig) -> Self {
+        Self { deadline: config.deadline.map(|d| Instant::now() + d), cancel: &config.cancel }
+    }
+
+    /// Returns whether the diff gives up after a time limit.
+    pub fn has_deadline(&self) -> bool { self.deadline.is_some() }
+
+    /// Returns whether the deadline has passed or the diff was cancelled.
+    pub fn reached(&self) -> bool {
+        self.cancel.is_cancelled() || self.deadline.is_some_and(|d| Instant::now() > d)
+    }
+}
+
+/// Diffs two slices like [`similar::capture_diff_slices_deadline`], and returns
+/// whether the limit cut the diff short. The elements left when it did are
+/// reported as a single replacement.
+pub fn capture_diff_slices_limited<T>(
+    alg: Algorithm,
+    old: &[T],
+    new: &[T],
+    limit: DiffLimit,
+) -> (Vec<DiffOp>, bool)
+where
+    T: Eq + Hash + Ord,
+{
+    let mut hook = LimitHook { inner: Replace::new(Capture::new()), limit, old_pos: 0, new_pos: 0 };
+    // similar gives up on its own once the deadline passes, replacing the rest of
+    // the range it was working on. The hook sees that replacement after the
+    // deadline, so either way the diff stops with an error here.
+    let truncated = diff_slices_deadline(alg, &mut hook, old, new, limit.deadline).is_err();
+    let LimitHook { mut inner, old_pos, new_pos, .. } = hook;
+    if truncated {
+        let result = (|| {
+            if old_pos < old.len() {
+                inner.delete(old_pos, old.len() - old_pos, new_pos)?;
+            }
+            if new_pos < new.len() {
+                inner.insert(old_pos, new_pos, new.len() - new_pos)?;
+            }
+            inner.finish()
+        })();
+        result.unwrap_or_else(|e: Infallible| match e {});
+    }
+    (inner.into_inner().into_ops(), truncated)
+}
+
+/// Error returned by [`LimitHook`] to stop the algorithm.
+struct LimitReached;
+
+/// Forwards the edit script to `inner` while the limit isn't reached, tracking
+/// how far into each sequence it got.
+struct LimitHook<'a> {
+    inner: Replace<Capture>,
+    limit: DiffLimit<'a>,
+    old_pos: usize,
+    new_pos: usize,
+}
+
+impl LimitHook<'_> {
+    fn check(&self) -> Result<(), LimitReached> {
+        if self.limit.reached() {
+            Err(LimitReached)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl DiffHook for LimitHook<'_> {
+    type Error = LimitReached;
+
+    fn equal(
+        &mut self,
+        old_index: usize,
+        new_index: usize,
+        len: usize,
+    ) -> Result<(), LimitReached> {
+        self.check()?;
+        self.inner.equal(old_index, new_index, len).unwrap_or_else(|e| match e {});
+        self.old_pos = old_index + len;
+        self.new_pos = new_index + len;
+        Ok(())
+    }
+
+    fn delete(
+        &mut self,
+        old_index: usize,
+        old_len: usize,
+        new_index: usize,
+    ) -> Result<(), LimitReached> {
+        self.check()?;
+        self.inner.delete(old_index, old_len, new_index).unwrap_or_else(|e| match e {});
+        self.old_pos = old_index + old_len;
+        self.new_pos = new_index;
+        Ok(())
+    }
+
+    fn insert(
+        &mut self,
+        old_index: usize,
+        new_index: usize,
+        new_len: usize,
+    ) -> Result<(), LimitReached> {
+        self.check()?;
+        self.inner.insert(old_index, new_index, new_len).unwrap_or_else(|e| match e {});
+        self.old_pos = old_index;
+        self.new_pos = new_index + new_len;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LimitReached> {
+        self.inner.finish().unwrap_or_else(|e| match e {});
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::diff::DiffAlg;
+
+    fn config(deadline: Option<Duration>) -> DiffObjConfig {
+        DiffObjConfig {
+            code_alg: DiffAlg::Patience,
+            data_alg: DiffAlg::Patience,
+            relax_reloc_diffs: false,
+            reloc_diffs: Default::default(),
+            block_diff: false,
+            group_delay_slots: false,
+            score_weights: Default::default(),
+            equivalences: vec![],
+            objdump: None,
+            demangler: Default::default(),
+            deadline,
+            cancel: Default::default(),
+        }
+    }
+
+    #[test]
+    fn limited_diff() {
+        let old = [1, 2, 3, 4, 5];
+        let new = [1, 2, 9, 4, 5, 6];
+        let unlimited = config(None);
+        let (ops, truncated) =
+            capture_diff_slices_limited(Algorithm::Myers, &old, &new, DiffLimit::start(&unlimited));
+        assert!(!truncated);
+        assert_eq!(ops, similar::capture_diff_slices(Algorithm::Myers, &old, &new));
+
+        // Nothing is matched once cancelled, the replacement covers both sequences
+        let cancelled = config(None);
+        cancelled.cancel.cancel();
+        for alg in [Algorithm::Myers, Algorithm::Patience, Algorithm::Lcs] {
+            let (ops, truncated) =
+                capture_diff_slices_limited(alg, &old, &new, DiffLimit::start(&cancelled));
+            assert!(truncated);
+            assert_eq!(ops, [DiffOp::Replace {
+                old_index: 0,
+                old_len: 5,
+                new_index: 0,
+                new_len: 6
+            }]);
+        }
+
+        let expired = config(Some(Duration::ZERO));
+        let limit = DiffLimit::start(&expired);
+        std::thread::sleep(Duration::from_millis(1));
+        let (_, truncated) = capture_diff_slices_limited(Algorithm::Patience, &old, &new, limit);
+        assert!(truncated);
+    }
+}
@@ -1,16 +1,34 @@
+pub mod cache;
+pub mod cfg;
 pub mod code;
 pub mod data;
+pub mod debug_info;
 pub mod editops;
+pub mod equiv;
+pub mod limit;
+pub mod three_way;
 
-use anyhow::Result;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{ensure, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    arch::objdump::ObjdumpConfig,
     diff::{
-        code::{diff_code, find_section_and_symbol, no_diff_code},
+        cache::{diff_key, CachedDiff, DiffCache, DiffKey},
+        code::{diff_code, no_diff_code, CodeContext},
         data::{diff_bss_symbols, diff_data, no_diff_data},
+        equiv::EquivalenceRule,
     },
-    obj::{ObjInfo, ObjIns, ObjSectionKind},
+    obj::{demangle::Demangler, ObjInfo, ObjIns, ObjSection, ObjSectionKind, ObjSymbol},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -22,10 +40,82 @@ pub enum DiffAlg {
     Lcs,
 }
 
+/// Penalties used to calculate [`ObjSymbol::diff_score`](crate::obj::ObjSymbol::diff_score).
+/// The defaults are modelled on asm-differ and decomp-permuter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreWeights {
+    /// Same instruction with different registers
+    pub regswap: u64,
+    /// Same instruction with a different immediate, relocation or branch target
+    pub immediate: u64,
+    /// Identical instruction at a different position
+    pub reorder: u64,
+    /// Instruction only present in the base
+    pub insertion: u64,
+    /// Instruction only present in the target
+    pub deletion: u64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self { regswap: 5, immediate: 10, reorder: 60, insertion: 100, deletion: 100 }
+    }
+}
+
+/// Relocation target differences to check or ignore when comparing instructions.
+/// By default, relocations to the same symbol match regardless of addend and
+/// binding. Unlike [`DiffObjConfig::relax_reloc_diffs`], each option only
+/// affects the check it names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelocDiffOptions {
+    /// Report the same target symbol with a different addend
+    pub check_addend: bool,
+    /// Report the same target symbol, local on one side and global on the other
+    pub check_binding: bool,
+    /// Ignore different target symbols in the same section
+    pub relax_target_name: bool,
+    /// Ignore addend and binding differences between extern targets of the same
+    /// name, even if checked above
+    pub relax_extern_targets: bool,
+}
+
+/// Shared flag to abort a running diff. Clones refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) { self.0.store(true, Ordering::Relaxed); }
+
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::Relaxed) }
+}
+
+#[derive(Clone, Default)]
 pub struct DiffObjConfig {
     pub code_alg: DiffAlg,
     pub data_alg: DiffAlg,
+    /// Treat relocations of the same kind as equal, regardless of their targets.
+    /// Takes precedence over [`reloc_diffs`](Self::reloc_diffs), which has no
+    /// effect while set.
     pub relax_reloc_diffs: bool,
+    pub reloc_diffs: RelocDiffOptions,
+    /// Match basic blocks before diffing instructions within them
+    pub block_diff: bool,
+    /// Align branches and their delay slots as a unit
+    pub group_delay_slots: bool,
+    pub score_weights: ScoreWeights,
+    /// Differing instructions to report as [`ObjInsDiffKind::Equivalent`](crate::obj::ObjInsDiffKind::Equivalent)
+    pub equivalences: Vec<EquivalenceRule>,
+    /// Fallback disassembler for architectures without native support
+    pub objdump: Option<ObjdumpConfig>,
+    /// Mangling scheme of symbol names
+    pub demangler: Demangler,
+    /// Time limit for diffing a single symbol or section. When exceeded, the
+    /// result is marked as truncated.
+    pub deadline: Option<Duration>,
+    /// Aborts [`diff_objs`] with an error when cancelled
+    pub cancel: CancellationToken,
 }
 
 pub struct ProcessCodeResult {
@@ -33,52 +123,164 @@ pub struct ProcessCodeResult {
     pub insts: Vec<ObjIns>,
 }
 
+enum CodeJob<'a> {
+    Diff(CodeContext<'a>, &'a mut ObjSymbol, CodeContext<'a>, &'a mut ObjSymbol),
+    Single(CodeContext<'a>, &'a mut ObjSymbol),
+}
+
+impl CodeJob<'_> {
+    /// Returns the result to cache for the next run, if a cache is in use.
+    fn run(
+        self,
+        config: &DiffObjConfig,
+        cache: Option<&DiffCache>,
+    ) -> Result<Option<(DiffKey, CachedDiff)>> {
+        ensure!(!config.cancel.is_cancelled(), "Cancelled");
+        match self {
+            CodeJob::Diff(left_ctx, left_symbol, right_ctx, right_symbol) => {
+                let Some(cache) = cache else {
+                    diff_code(config, left_ctx, right_ctx, left_symbol, right_symbol)?;
+                    return Ok(None);
+                };
+                let key = diff_key(left_ctx, right_ctx, left_symbol, right_symbol);
+                if !cache.apply(key, left_ctx, right_ctx, left_symbol, right_symbol) {
+                    diff_code(config, left_ctx, right_ctx, left_symbol, right_symbol)?;
+                }
+                Ok(CachedDiff::new(left_symbol, right_symbol).map(|entry| (key, entry)))
+            }
+            CodeJob::Single(ctx, symbol) => no_diff_code(ctx, symbol).map(|_| None),
+        }
+    }
+}
+
+/// Collects the code symbols of an object along with their section state.
+fn code_symbols(obj: &mut ObjInfo) -> Vec<(CodeContext<'_>, &mut ObjSymbol)> {
+    let ObjInfo { arch, sections, line_info, .. } = obj;
+    let (arch, line_info) = (&**arch, &*line_info);
+    let mut out = Vec::new();
+    for section in sections.iter_mut().filter(|s| s.kind == ObjSectionKind::Code) {
+        let ObjSection { name, align, data, index, relocations, jump_tables, symbols, .. } =
+            section;
+        let ctx = CodeContext {
+            arch,
+            data,
+            section_name: name,
+            section_index: *index,
+            section_align: *align,
+            relocs: relocations,
+            jump_tables,
+            line_info,
+        };
+        out.extend(symbols.iter_mut().map(|symbol| (ctx, symbol)));
+    }
+    out
+}
+
+/// Pairs code symbols by name. Symbols sharing a name (e.g. local statics)
+/// are paired in order of appearance.
+fn code_jobs<'a>(
+    left: Option<&'a mut ObjInfo>,
+    right: Option<&'a mut ObjInfo>,
+) -> Vec<CodeJob<'a>> {
+    let right_symbols = right.map(code_symbols).unwrap_or_default();
+    let mut right_index = HashMap::<String, VecDeque<usize>>::new();
+    for (i, (_, symbol)) in right_symbols.iter().enumerate() {
+        right_index.entry(symbol.name.clone()).or_default().push_back(i);
+    }
+    let mut right_symbols = right_symbols.into_iter().map(Some).collect::<Vec<_>>();
+
+    let mut jobs = Vec::new();
+    for (left_ctx, left_symbol) in left.map(code_symbols).unwrap_or_default() {
+        let right = right_index
+            .get_mut(&left_symbol.name)
+            .and_then(|indices| indices.pop_front())
+            .and_then(|i| right_symbols[i].take());
+        if let Some((right_ctx, right_symbol)) = right {
+            left_symbol.diff_symbol = Some(right_symbol.name.clone());
+            right_symbol.diff_symbol = Some(left_symbol.name.clone());
+            jobs.push(CodeJob::Diff(left_ctx, left_symbol, right_ctx, right_symbol));
+        } else {
+            jobs.push(CodeJob::Single(left_ctx, left_symbol));
+        }
+    }
+    jobs.extend(
+        right_symbols.into_iter().flatten().map(|(ctx, symbol)| CodeJob::Single(ctx, symbol)),
+    );
+    jobs
+}
+
 pub fn diff_objs(
+    config: &DiffObjConfig,
+    left: Option<&mut ObjInfo>,
+    right: Option<&mut ObjInfo>,
+) -> Result<()> {
+    diff_objs_impl(config, left, right, None)
+}
+
+/// Diffs only the code symbol named `symbol_name` against its counterpart,
+/// leaving the other symbols untouched.
+pub fn diff_symbol(
+    config: &DiffObjConfig,
+    left: &mut ObjInfo,
+    right: &mut ObjInfo,
+    symbol_name: &str,
+) -> Result<()> {
+    for job in code_jobs(Some(left), Some(right)) {
+        if matches!(&job, CodeJob::Diff(_, symbol, _, _) if symbol.name == symbol_name) {
+            job.run(config, None)?;
+        }
+    }
+    Ok(())
+}
+
+/// Like [`diff_objs`], but reuses code diff results from the previous call with
+/// the same cache for function pairs that did not change.
+pub fn diff_objs_cached(
+    config: &DiffObjConfig,
+    left: Option<&mut ObjInfo>,
+    right: Option<&mut ObjInfo>,
+    cache: &mut DiffCache,
+) -> Result<()> {
+    cache.validate(config);
+    diff_objs_impl(config, left, right, Some(cache))
+}
+
+fn diff_objs_impl(
     config: &DiffObjConfig,
     mut left: Option<&mut ObjInfo>,
     mut right: Option<&mut ObjInfo>,
+    cache: Option<&mut DiffCache>,
 ) -> Result<()> {
+    // Each job only modifies its own symbols, so the result is the same
+    // regardless of the order they are processed in.
+    let jobs = code_jobs(left.as_deref_mut(), right.as_deref_mut());
+    let prev = cache.as_deref();
+    #[cfg(feature = "parallel")]
+    let entries = {
+        use rayon::prelude::*;
+        jobs.into_par_iter().map(|job| job.run(config, prev)).collect::<Result<Vec<_>>>()?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let entries = jobs.into_iter().map(|job| job.run(config, prev)).collect::<Result<Vec<_>>>()?;
+    if let Some(cache) = cache {
+        cache.replace(entries.into_iter().flatten());
+    }
+
     if let Some(left) = left.as_mut() {
+        let mut right_sections = HashMap::<String, &mut ObjSection>::new();
+        if let Some(right) = right.as_mut() {
+            for section in &mut right.sections {
+                right_sections.entry(section.name.clone()).or_insert(section);
+            }
+        }
         for left_section in &mut left.sections {
             if left_section.kind == ObjSectionKind::Code {
-                for left_symbol in &mut left_section.symbols {
-                    if let Some((right, (right_section_idx, right_symbol_idx))) =
-                        right.as_mut().and_then(|obj| {
-                            find_section_and_symbol(obj, &left_symbol.name).map(|s| (obj, s))
-                        })
-                    {
-                        let right_section = &mut right.sections[right_section_idx];
-                        let right_symbol = &mut right_section.symbols[right_symbol_idx];
-                        left_symbol.diff_symbol = Some(right_symbol.name.clone());
-                        right_symbol.diff_symbol = Some(left_symbol.name.clone());
-                        diff_code(
-                            config,
-                            left.architecture,
-                            &left_section.data,
-                            &right_section.data,
-                            left_symbol,
-                            right_symbol,
-                            &left_section.relocations,
-                            &right_section.relocations,
-                            &left.line_info,
-                            &right.line_info,
-                        )?;
-                    } else {
-                        no_diff_code(
-                            left.architecture,
-                            &left_section.data,
-                            left_symbol,
-                            &left_section.relocations,
-                            &left.line_info,
-                        )?;
-                    }
-                }
-            } else if let Some(right_section) = right
-                .as_mut()
-                .and_then(|obj| obj.sections.iter_mut().find(|s| s.name == left_section.name))
-            {
+                continue;
+            }
+            if let Some(right_section) = right_sections.get_mut(&left_section.name) {
                 if left_section.kind == ObjSectionKind::Data {
-                    diff_data(config.data_alg, left_section, right_section)?;
+                    ensure!(!config.cancel.is_cancelled(), "Cancelled");
+                    diff_data(config, left_section, right_section)?;
                 } else if left_section.kind == ObjSectionKind::Bss {
                     diff_bss_symbols(&mut left_section.symbols, &mut right_section.symbols)?;
                 }
@@ -89,21 +291,7 @@ pub fn diff_objs(
     }
     if let Some(right) = right.as_mut() {
         for right_section in right.sections.iter_mut() {
-            if right_section.kind == ObjSectionKind::Code {
-                for right_symbol in &mut right_section.symbols {
-                    if right_symbol.instructions.is_empty() {
-                        no_diff_code(
-                            right.architecture,
-                            &right_section.data,
-                            right_symbol,
-                            &right_section.relocations,
-                            &right.line_info,
-                        )?;
-                    }
-                }
-            } else if right_section.kind == ObjSectionKind::Data
-                && right_section.data_diff.is_empty()
-            {
+            if right_section.kind == ObjSectionKind::Data && right_section.data_diff.is_empty() {
                 no_diff_data(right_section);
             }
         }
@@ -113,3 +301,240 @@ pub fn diff_objs(
     }
     Ok(())
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::PathBuf;
+
+    use filetime::FileTime;
+    use object::{Relocation, RelocationKind};
+
+    use super::*;
+    use crate::{
+        arch::{find_reloc, ObjArch, RelocFormat},
+        obj::{ObjInsArg, ObjInsArgValue, ObjLineInfo, ObjReloc, ObjSymbolFlagSet, ObjSymbolFlags},
+    };
+
+    /// Architecture of big-endian words, with the opcode in the top byte:
+    /// `0x00` is `nop`, `0x10` branches by the signed low half, `0x20` is an
+    /// indirect branch and any other opcode takes a register and an immediate,
+    /// or a relocation in its place.
+    pub(crate) struct TestArch;
+
+    impl ObjArch for TestArch {
+        fn process_code(
+            &self,
+            data: &[u8],
+            address: u64,
+            _section_index: usize,
+            relocs: &[ObjReloc],
+            _line_info: &Option<ObjLineInfo>,
+        ) -> Result<ProcessCodeResult> {
+            let mut ops = Vec::new();
+            let mut insts = Vec::new();
+            for (i, word) in data.chunks_exact(4).enumerate() {
+                let code = u32::from_be_bytes(word.try_into()?);
+                let address = address + i as u64 * 4;
+                let op = (code >> 24) as u8;
+                let reloc = find_reloc(relocs, address, address + 4).cloned();
+                let reg =
+                    ObjInsArg::Arg(ObjInsArgValue::Register(format!("r{}", code >> 16 & 0xff)));
+                let (mnemonic, args) = match op {
+                    0x00 => ("nop".to_string(), vec![]),
+                    0x10 => ("b".to_string(), vec![ObjInsArg::BranchOffset(code as i16 as i32)]),
+                    0x20 => ("jr".to_string(), vec![reg]),
+                    _ if reloc.is_some() => (format!("op{op:02x}"), vec![reg, ObjInsArg::Reloc]),
+                    _ => (format!("op{op:02x}"), vec![
+                        reg,
+                        ObjInsArg::Arg(ObjInsArgValue::Signed(code as i16)),
+                    ]),
+                };
+                let branch_dest =
+                    (op == 0x10).then(|| (address as i64 + code as i16 as i64) as u32);
+                ops.push(op);
+                insts.push(ObjIns {
+                    address: address as u32,
+                    code,
+                    op,
+                    mnemonic,
+                    args,
+                    reloc,
+                    branch_dest,
+                    line: None,
+                    orig: None,
+                    delay_slot: None,
+                });
+            }
+            Ok(ProcessCodeResult { ops, insts })
+        }
+
+        fn implicit_addend(
+            &self,
+            _section: &ObjSection,
+            _address: u64,
+            _reloc: &Relocation,
+        ) -> Result<i64> {
+            unimplemented!()
+        }
+
+        fn reloc_name(&self, _kind: RelocationKind) -> Option<&str> { Some("R_TEST") }
+
+        fn display_reloc(&self, _kind: RelocationKind) -> Option<RelocFormat> {
+            Some(RelocFormat::default())
+        }
+
+        fn is_indirect_branch(&self, ins: &ObjIns) -> bool { ins.op == 0x20 }
+
+        fn ends_block(&self, ins: &ObjIns) -> bool { matches!(ins.op, 0x10 | 0x20) }
+    }
+
+    /// Global symbol of a function or data object.
+    pub(crate) fn symbol(name: &str, address: u64, size: u64) -> ObjSymbol {
+        ObjSymbol {
+            name: name.to_string(),
+            address,
+            section_address: address,
+            size,
+            size_known: true,
+            flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+            ..Default::default()
+        }
+    }
+
+    /// `.text` section of [`TestArch`] instruction words at address 0, with
+    /// functions starting at the given word indices and extending to the next.
+    pub(crate) fn code_section(words: &[u32], functions: &[(&str, usize)]) -> ObjSection {
+        let data = words.iter().flat_map(|word| word.to_be_bytes()).collect::<Vec<_>>();
+        let symbols = functions
+            .iter()
+            .enumerate()
+            .map(|(i, &(name, start))| {
+                let end = functions.get(i + 1).map_or(words.len(), |&(_, end)| end);
+                symbol(name, start as u64 * 4, (end - start) as u64 * 4)
+            })
+            .collect();
+        ObjSection {
+            name: ".text".to_string(),
+            kind: ObjSectionKind::Code,
+            address: 0,
+            size: data.len() as u64,
+            align: 4,
+            data,
+            index: 1,
+            symbols,
+            relocations: vec![],
+            jump_tables: vec![],
+            data_diff: vec![],
+            match_percent: 0.0,
+            truncated: false,
+        }
+    }
+
+    pub(crate) fn test_obj(sections: Vec<ObjSection>) -> ObjInfo {
+        ObjInfo {
+            arch: Arc::new(TestArch),
+            path: PathBuf::from("test.o"),
+            timestamp: FileTime::zero(),
+            sections,
+            common: vec![],
+            line_info: None,
+            debug_info: None,
+        }
+    }
+
+    /// Pseudo-random functions of `len` words each, and a copy with some of
+    /// their instructions replaced, inserted or removed.
+    #[cfg(feature = "parallel")]
+    fn edited_functions(count: usize, len: usize) -> (Vec<u32>, Vec<u32>) {
+        let mut state = 0x2545f491u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for _ in 0..count {
+            let function =
+                (0..len).map(|_| (next() % 8 + 1) << 24 | next() & 0xffff).collect::<Vec<_>>();
+            let mut edited = Vec::new();
+            for &word in &function {
+                match next() % 64 {
+                    0 => {}
+                    1 => edited.extend([word, 0x01000000]),
+                    2 => edited.push(word ^ 0x10),
+                    _ => edited.push(word),
+                }
+            }
+            // Keep the length so that functions start at the same indices
+            edited.resize(len, 0);
+            left.extend(function);
+            right.extend(edited);
+        }
+        (left, right)
+    }
+
+    fn symbols(obj: &ObjInfo) -> impl Iterator<Item = &ObjSymbol> {
+        obj.sections.iter().flat_map(|s| &s.symbols)
+    }
+
+    #[test]
+    fn same_named_symbols_pair_in_order() {
+        let config = DiffObjConfig::default();
+        let words = [0x01000001, 0x02000002, 0x03000003, 0x04000004];
+        let functions = [("local", 0), ("local", 1), ("f", 3)];
+        let mut left = test_obj(vec![code_section(&words, &functions)]);
+        let mut right = test_obj(vec![code_section(&words, &functions)]);
+        diff_objs(&config, Some(&mut left), Some(&mut right)).unwrap();
+        for symbol in symbols(&left).chain(symbols(&right)) {
+            assert_eq!(symbol.diff_symbol.as_deref(), Some(symbol.name.as_str()));
+            assert_eq!(symbol.match_percent, Some(100.0), "{}", symbol.name);
+        }
+
+        // Pairing in order, the first `local` on the left diffs against the first on the right
+        let swapped = [0x02000002, 0x03000003, 0x01000001, 0x04000004];
+        let mut right =
+            test_obj(vec![code_section(&swapped, &[("local", 0), ("local", 2), ("f", 3)])]);
+        diff_objs(&config, Some(&mut left), Some(&mut right)).unwrap();
+        let percents = symbols(&left).map(|s| s.match_percent).collect::<Vec<_>>();
+        assert_eq!(percents, [Some(0.0), Some(0.0), Some(100.0)]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_serial() {
+        let config = DiffObjConfig::default();
+        let (left_words, right_words) = edited_functions(64, 32);
+        // Every name is used twice, as for local statics in different files
+        let names = (0..64).map(|i| format!("f{}", i / 2)).collect::<Vec<_>>();
+        let functions =
+            names.iter().enumerate().map(|(i, name)| (name.as_str(), i * 32)).collect::<Vec<_>>();
+        let mut left = test_obj(vec![code_section(&left_words, &functions)]);
+        // One more function on the left only
+        let mut right = test_obj(vec![code_section(&right_words[..63 * 32], &functions[..63])]);
+        let (mut serial_left, mut serial_right) = (left.clone(), right.clone());
+
+        diff_objs(&config, Some(&mut left), Some(&mut right)).unwrap();
+        for job in code_jobs(Some(&mut serial_left), Some(&mut serial_right)) {
+            job.run(&config, None).unwrap();
+        }
+        for (obj, serial) in [(&left, &serial_left), (&right, &serial_right)] {
+            for (symbol, expected) in symbols(obj).zip(symbols(serial)) {
+                assert_eq!(symbol.diff_symbol, expected.diff_symbol);
+                assert_eq!(symbol.match_percent, expected.match_percent, "{}", symbol.name);
+                assert_eq!(symbol.diff_score, expected.diff_score, "{}", symbol.name);
+                assert_eq!(
+                    format!("{:?}", symbol.instructions),
+                    format!("{:?}", expected.instructions),
+                    "{}",
+                    symbol.name
+                );
+            }
+        }
+        // The diffs aren't all trivial
+        assert!(symbols(&left).any(|s| s.match_percent.is_some_and(|p| p < 100.0)));
+        assert!(symbols(&left).any(|s| s.match_percent == Some(100.0)));
+        assert_eq!(symbols(&left).last().unwrap().diff_symbol, None);
+    }
+}
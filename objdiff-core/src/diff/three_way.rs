@@ -0,0 +1,192 @@
+use crate::{diff::code::resolve_branches, obj::ObjInsDiff};
+
+/// Whether the last edit moved an instruction row closer to or further from the target.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ThreeWayKind {
+    #[default]
+    None,
+    Closer,
+    Further,
+}
+
+/// Target, previous base and current base instructions aligned into rows.
+#[derive(Debug, Clone, Default)]
+pub struct ThreeWayDiff {
+    /// Target instructions, diffed against the current base
+    pub target: Vec<ObjInsDiff>,
+    /// Previous base instructions, diffed against the target
+    pub prev: Vec<ObjInsDiff>,
+    /// Current base instructions, diffed against the target
+    pub base: Vec<ObjInsDiff>,
+    pub kinds: Vec<ThreeWayKind>,
+}
+
+/// A row of a two-way diff: the target's cell and the base's cell.
+type Row<'a> = (&'a ObjInsDiff, &'a ObjInsDiff);
+
+impl ThreeWayDiff {
+    fn push(&mut self, target: ObjInsDiff, prev: Option<Row>, base: Option<Row>) {
+        // A missing side has nothing differing from the target in this row
+        let matches = |row: Option<Row>| {
+            row.map_or(true, |(target, base)| target.kind.is_match() && base.kind.is_match())
+        };
+        self.kinds.push(match (matches(prev), matches(base)) {
+            (false, true) => ThreeWayKind::Closer,
+            (true, false) => ThreeWayKind::Further,
+            _ => ThreeWayKind::None,
+        });
+        self.target.push(target);
+        self.prev.push(prev.map(|(_, ins)| ins.clone()).unwrap_or_default());
+        self.base.push(base.map(|(_, ins)| ins.clone()).unwrap_or_default());
+    }
+}
+
+/// Aligns two diffs sharing the same target function: the target against the
+/// current base, and the target against the previous base. Rows are matched
+/// on target instructions; instructions inserted by either base are paired up
+/// where both bases insert at the same position.
+pub fn align_three_way(
+    target_base: (&[ObjInsDiff], &[ObjInsDiff]),
+    target_prev: (&[ObjInsDiff], &[ObjInsDiff]),
+) -> ThreeWayDiff {
+    let (target, base) = target_base;
+    let (prev_target, prev) = target_prev;
+    let mut out = ThreeWayDiff::default();
+    let (mut i, mut j) = (0, 0);
+    while i < target.len() || j < prev.len() {
+        let base_insert = i < target.len() && target[i].ins.is_none();
+        let prev_insert = j < prev.len() && prev_target[j].ins.is_none();
+        if base_insert || prev_insert {
+            let base_row = base_insert.then(|| (&target[i], &base[i]));
+            let prev_row = prev_insert.then(|| (&prev_target[j], &prev[j]));
+            out.push(ObjInsDiff::default(), prev_row, base_row);
+            i += base_insert as usize;
+            j += prev_insert as usize;
+        } else if i < target.len() && j < prev.len() {
+            out.push(
+                target[i].clone(),
+                Some((&prev_target[j], &prev[j])),
+                Some((&target[i], &base[i])),
+            );
+            i += 1;
+            j += 1;
+        } else if i < target.len() {
+            out.push(target[i].clone(), None, Some((&target[i], &base[i])));
+            i += 1;
+        } else {
+            out.push(ObjInsDiff::default(), Some((&prev_target[j], &prev[j])), None);
+            j += 1;
+        }
+    }
+    for column in [&mut out.target, &mut out.prev, &mut out.base] {
+        for ins in column.iter_mut() {
+            ins.branch_from = None;
+            ins.branch_to = None;
+            // Refers to rows of the two-way diff
+            ins.moved = None;
+        }
+        resolve_branches(column);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{ObjIns, ObjInsDiffKind};
+
+    /// Two-way diff from rows of target address, base address and row kind.
+    fn two_way(rows: &[(Option<u32>, Option<u32>, ObjInsDiffKind)]) -> [Vec<ObjInsDiff>; 2] {
+        let cell = |address: Option<u32>, kind| ObjInsDiff {
+            ins: address.map(|address| ObjIns {
+                address,
+                code: 0,
+                op: 0,
+                mnemonic: String::new(),
+                args: vec![],
+                reloc: None,
+                branch_dest: None,
+                line: None,
+                orig: None,
+                delay_slot: None,
+            }),
+            kind,
+            ..Default::default()
+        };
+        let target = rows.iter().map(|&(address, _, kind)| cell(address, kind)).collect();
+        let base = rows.iter().map(|&(_, address, kind)| cell(address, kind)).collect();
+        [target, base]
+    }
+
+    fn addresses(column: &[ObjInsDiff]) -> Vec<Option<u32>> {
+        column.iter().map(|row| row.ins.as_ref().map(|ins| ins.address)).collect()
+    }
+
+    const NONE: ObjInsDiffKind = ObjInsDiffKind::None;
+    const INSERT: ObjInsDiffKind = ObjInsDiffKind::Insert;
+    const SAME: ThreeWayKind = ThreeWayKind::None;
+    const FURTHER: ThreeWayKind = ThreeWayKind::Further;
+
+    #[test]
+    fn insert_on_one_side() {
+        // The current base inserts 0x104 after the first instruction
+        let [target, base] = two_way(&[
+            (Some(0), Some(0x100), NONE),
+            (None, Some(0x104), INSERT),
+            (Some(4), Some(0x108), NONE),
+            (Some(8), Some(0x10c), NONE),
+        ]);
+        let [prev_target, prev] = two_way(&[
+            (Some(0), Some(0x200), NONE),
+            (Some(4), Some(0x204), NONE),
+            (Some(8), Some(0x208), NONE),
+        ]);
+        let diff = align_three_way((&target, &base), (&prev_target, &prev));
+        assert_eq!(addresses(&diff.target), [Some(0), None, Some(4), Some(8)]);
+        assert_eq!(addresses(&diff.prev), [Some(0x200), None, Some(0x204), Some(0x208)]);
+        assert_eq!(addresses(&diff.base), [Some(0x100), Some(0x104), Some(0x108), Some(0x10c)]);
+        assert_eq!(diff.kinds, [SAME, FURTHER, SAME, SAME]);
+
+        // Removing the insertion again brings the base closer
+        let diff = align_three_way((&prev_target, &prev), (&target, &base));
+        assert_eq!(addresses(&diff.prev), [Some(0x100), Some(0x104), Some(0x108), Some(0x10c)]);
+        assert_eq!(addresses(&diff.base), [Some(0x200), None, Some(0x204), Some(0x208)]);
+        assert_eq!(diff.kinds[1], ThreeWayKind::Closer);
+    }
+
+    #[test]
+    fn insert_on_both_sides() {
+        // Both bases insert after the first instruction, and the current base
+        // also inserts before the last one
+        let [target, base] = two_way(&[
+            (Some(0), Some(0x100), NONE),
+            (None, Some(0x104), INSERT),
+            (Some(4), Some(0x108), NONE),
+            (None, Some(0x10c), INSERT),
+            (Some(8), Some(0x110), NONE),
+        ]);
+        let [prev_target, prev] = two_way(&[
+            (Some(0), Some(0x200), NONE),
+            (None, Some(0x204), INSERT),
+            (Some(4), Some(0x208), NONE),
+            (Some(8), Some(0x20c), NONE),
+        ]);
+        let diff = align_three_way((&target, &base), (&prev_target, &prev));
+        assert_eq!(addresses(&diff.target), [Some(0), None, Some(4), None, Some(8)]);
+        assert_eq!(addresses(&diff.prev), [
+            Some(0x200),
+            Some(0x204),
+            Some(0x208),
+            None,
+            Some(0x20c)
+        ]);
+        assert_eq!(addresses(&diff.base), [
+            Some(0x100),
+            Some(0x104),
+            Some(0x108),
+            Some(0x10c),
+            Some(0x110)
+        ]);
+        assert_eq!(diff.kinds, [SAME, SAME, SAME, FURTHER, SAME]);
+    }
+}
@@ -0,0 +1,479 @@
+//! Demangling of names mangled by GCC 2.x, following `cplus-dem.c`.
+
+use crate::obj::dwarf::{declaration, parameter_list, Type};
+
+/// Operator names after `__`, e.g. `__pl` for `operator+`
+const OPERATORS: &[(&str, &str)] = &[
+    ("nw", "new"),
+    ("dl", "delete"),
+    ("vn", "new []"),
+    ("vd", "delete []"),
+    ("as", "="),
+    ("ne", "!="),
+    ("eq", "=="),
+    ("ge", ">="),
+    ("gt", ">"),
+    ("le", "<="),
+    ("lt", "<"),
+    ("pl", "+"),
+    ("apl", "+="),
+    ("mi", "-"),
+    ("ami", "-="),
+    ("ml", "*"),
+    ("aml", "*="),
+    ("dv", "/"),
+    ("adv", "/="),
+    ("md", "%"),
+    ("amd", "%="),
+    ("aa", "&&"),
+    ("oo", "||"),
+    ("nt", "!"),
+    ("pp", "++"),
+    ("mm", "--"),
+    ("co", "~"),
+    ("er", "^"),
+    ("aer", "^="),
+    ("ad", "&"),
+    ("aad", "&="),
+    ("or", "|"),
+    ("aor", "|="),
+    ("ls", "<<"),
+    ("als", "<<="),
+    ("rs", ">>"),
+    ("ars", ">>="),
+    ("rf", "->"),
+    ("rm", "->*"),
+    ("vc", "[]"),
+    ("cl", "()"),
+    ("cm", ","),
+    ("mn", "<?"),
+    ("mx", ">?"),
+    ("cn", "?:"),
+];
+
+/// Demangles a GCC 2.x symbol name, e.g. `foo__3BarPCc` to `Bar::foo(const char*)`.
+pub(super) fn demangle(name: &str) -> Option<String> {
+    if let Some(rest) = name.strip_prefix("_GLOBAL_") {
+        let [marker, kind, marker2, ..] = rest.as_bytes() else {
+            return None;
+        };
+        if !is_marker(*marker as char) || !is_marker(*marker2 as char) {
+            return None;
+        }
+        let kind = match kind {
+            b'I' => "constructors",
+            b'D' => "destructors",
+            _ => return None,
+        };
+        let key = &rest[3..];
+        return Some(format!(
+            "global {kind} keyed to {}",
+            demangle(key).unwrap_or_else(|| key.to_string())
+        ));
+    }
+    if let Some(rest) = name.strip_prefix("_vt").filter(|rest| rest.starts_with(is_marker)) {
+        return vtable(&rest[1..]);
+    }
+    if let Some(rest) = name.strip_prefix("__thunk_") {
+        let (delta, rest) = rest.split_once('_')?;
+        let delta = delta.parse::<u64>().ok()?;
+        return Some(format!("virtual function thunk (delta:-{delta}) for {}", demangle(rest)?));
+    }
+    for (prefix, suffix) in [("__ti", "type_info node"), ("__tf", "type_info function")] {
+        if let Some(rest) = name.strip_prefix(prefix) {
+            let mut parser = Parser::new(rest);
+            if let Some(ty) = parser.ty().filter(|_| parser.rest.is_empty()) {
+                return Some(format!("{} {suffix}", declaration(&ty, "")));
+            }
+        }
+    }
+    if let Some(rest) = name.strip_prefix('_') {
+        // Destructor, e.g. `_$_3Foo`
+        if let Some(rest) = rest.strip_prefix(is_marker).and_then(|rest| rest.strip_prefix('_')) {
+            let mut parser = Parser::new(rest);
+            let (class, base) = parser.class_name()?;
+            return parser.rest.is_empty().then(|| format!("{class}::~{base}(void)"));
+        }
+        // Static data member, e.g. `_3Foo$bar`
+        if rest.starts_with(is_class_start) {
+            let mut parser = Parser::new(rest);
+            if let Some((class, _)) = parser.class_name() {
+                if let Some(member) = parser.rest.strip_prefix(is_marker) {
+                    if !member.is_empty() && !member.contains(is_marker) {
+                        return Some(format!("{class}::{member}"));
+                    }
+                }
+            }
+        }
+    }
+    // The function name ends at the first `__` followed by a valid signature,
+    // as names and types can contain `__` themselves
+    let bytes = name.as_bytes();
+    (0..bytes.len().saturating_sub(1))
+        .filter(|&pos| bytes[pos] == b'_' && bytes[pos + 1] == b'_')
+        .find_map(|pos| function(&name[..pos], &name[pos + 2..]))
+}
+
+/// Demangles a function from its name and the signature following `__`.
+fn function(name: &str, signature: &str) -> Option<String> {
+    let mut parser = Parser::new(signature);
+    let mut suffix = "";
+    let class = match parser.peek()? {
+        b'F' => {
+            parser.next();
+            None
+        }
+        // Const or static member function
+        b'C' | b'S' => {
+            let qualifier = parser.next()?;
+            let (class, base) = parser.class_name()?;
+            if qualifier == b'C' {
+                suffix = " const";
+                parser.types.push(Type::Const(Box::new(Type::Named(class.clone()))));
+            } else {
+                parser.types.push(Type::Named(class.clone()));
+            }
+            Some((class, base))
+        }
+        _ => {
+            let (class, base) = parser.class_name()?;
+            parser.types.push(Type::Named(class.clone()));
+            Some((class, base))
+        }
+    };
+    let name = match name {
+        // Constructor, e.g. `__3Foo`
+        "" => class.as_ref()?.1.to_string(),
+        _ => match name.strip_prefix("__") {
+            Some(operator) if !operator.is_empty() => operator_name(operator)?,
+            _ => name.to_string(),
+        },
+    };
+    let (params, variadic) = parser.args(None)?;
+    let params = params.iter().map(|param| declaration(param, "")).collect();
+    let params = parameter_list(params, variadic);
+    Some(match class {
+        Some((class, _)) => format!("{class}::{name}({params}){suffix}"),
+        None => format!("{name}({params}){suffix}"),
+    })
+}
+
+/// Returns the name of an operator, e.g. `operator+` for `pl` or
+/// `operator int` for the conversion `opi`.
+fn operator_name(operator: &str) -> Option<String> {
+    if let Some(rest) = operator.strip_prefix("op") {
+        let mut parser = Parser::new(rest);
+        let ty = parser.ty()?;
+        return parser.rest.is_empty().then(|| format!("operator {}", declaration(&ty, "")));
+    }
+    let (_, symbol) = OPERATORS.iter().find(|(code, _)| *code == operator)?;
+    if symbol.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        Some(format!("operator {symbol}"))
+    } else {
+        Some(format!("operator{symbol}"))
+    }
+}
+
+/// Demangles a virtual table name following `_vt$`, e.g. `3Foo` or `3Bar$3Foo`.
+fn vtable(name: &str) -> Option<String> {
+    let mut parser = Parser::new(name);
+    let mut names = vec![];
+    loop {
+        if parser.rest.starts_with(is_class_start) {
+            names.push(parser.class_name()?.0);
+        } else {
+            let len = parser.rest.find(is_marker).unwrap_or(parser.rest.len());
+            names.push(parser.rest[..len].to_string());
+            parser.rest = &parser.rest[len..];
+        }
+        if parser.rest.is_empty() {
+            break;
+        }
+        parser.rest = parser.rest.strip_prefix(is_marker)?;
+    }
+    Some(format!("{} virtual table", names.join("::")))
+}
+
+/// Separator of the parts of special names, depending on the assembler.
+fn is_marker(c: char) -> bool { c == '$' || c == '.' }
+
+fn is_class_start(c: char) -> bool { c.is_ascii_digit() || c == 'Q' || c == 't' }
+
+fn builtin(c: u8) -> Option<&'static str> {
+    Some(match c {
+        b'v' => "void",
+        b'c' => "char",
+        b's' => "short",
+        b'i' => "int",
+        b'l' => "long",
+        b'x' => "long long",
+        b'f' => "float",
+        b'd' => "double",
+        b'r' => "long double",
+        b'b' => "bool",
+        b'w' => "wchar_t",
+        _ => return None,
+    })
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    /// Parameter types so far, for back references by `T` and `N`. The class
+    /// of a member function comes first.
+    types: Vec<Type>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(rest: &'a str) -> Self { Self { rest, types: vec![] } }
+
+    fn peek(&self) -> Option<u8> { self.rest.bytes().next() }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek().filter(u8::is_ascii)?;
+        self.rest = &self.rest[1..];
+        Some(c)
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.rest = &self.rest[1..];
+        }
+        matched
+    }
+
+    /// Reads a decimal number, e.g. the length of a name.
+    fn number(&mut self) -> Option<usize> {
+        let len = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        let value = self.rest[..len].parse().ok()?;
+        self.rest = &self.rest[len..];
+        Some(value)
+    }
+
+    /// Reads a single digit, or several followed by `_`, e.g. `3` or `12_`.
+    fn count(&mut self) -> Option<usize> {
+        let len = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        if len > 1 && self.rest.as_bytes().get(len) == Some(&b'_') {
+            let value = self.rest[..len].parse().ok()?;
+            self.rest = &self.rest[len + 1..];
+            return Some(value);
+        }
+        let value = self.rest.get(..1)?.parse().ok()?;
+        self.rest = &self.rest[1..];
+        Some(value)
+    }
+
+    /// Reads a single digit, or several between underscores, e.g. `3` or `_12_`.
+    fn underscored_count(&mut self) -> Option<usize> {
+        if !self.eat(b'_') {
+            return self.count();
+        }
+        let value = self.number()?;
+        self.eat(b'_').then_some(value)
+    }
+
+    /// Reads a name preceded by its length, e.g. `3Foo`.
+    fn source_name(&mut self) -> Option<&'a str> {
+        let len = self.number()?;
+        let name = self.rest.get(..len)?;
+        self.rest = &self.rest[len..];
+        Some(name)
+    }
+
+    /// Reads a class name, e.g. `3Foo`, `Q23Foo3Bar` or `t3Foo1Zi`. Returns
+    /// the qualified name and the innermost name without template arguments,
+    /// which names constructors and destructors.
+    fn class_name(&mut self) -> Option<(String, &'a str)> {
+        if !self.eat(b'Q') {
+            return self.class_component();
+        }
+        let count = self.underscored_count()?;
+        let mut names = Vec::with_capacity(count);
+        let mut base = "";
+        for _ in 0..count {
+            let (name, name_base) = self.class_component()?;
+            names.push(name);
+            base = name_base;
+        }
+        (count > 0).then(|| (names.join("::"), base))
+    }
+
+    fn class_component(&mut self) -> Option<(String, &'a str)> {
+        if !self.eat(b't') {
+            let name = self.source_name()?;
+            return Some((name.to_string(), name));
+        }
+        // Template, e.g. `t3Foo2Zii3` for `Foo<int, 3>`
+        let name = self.source_name()?;
+        let count = self.count()?;
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            if self.eat(b'Z') {
+                args.push(declaration(&self.ty()?, ""));
+            } else {
+                args.push(self.template_value()?);
+            }
+        }
+        let mut args = args.join(", ");
+        if args.ends_with('>') {
+            args.push(' ');
+        }
+        Some((format!("{name}<{args}>"), name))
+    }
+
+    /// Reads a non-type template argument: its type, then its value.
+    fn template_value(&mut self) -> Option<String> {
+        let Type::Named(ty) = self.ty()? else {
+            return None;
+        };
+        let negative = self.eat(b'm');
+        let value = self.underscored_count()?;
+        Some(match (ty.as_str(), negative, value) {
+            ("bool", false, 0) => "false".to_string(),
+            ("bool", false, 1) => "true".to_string(),
+            ("bool", ..) => return None,
+            ("char", false, 0x20..=0x7e) => format!("'{}'", value as u8 as char),
+            (_, true, _) => format!("-{value}"),
+            _ => value.to_string(),
+        })
+    }
+
+    fn ty(&mut self) -> Option<Type> {
+        if self.rest.starts_with(is_class_start) {
+            return Some(Type::Named(self.class_name()?.0));
+        }
+        Some(match self.next()? {
+            b'C' => Type::Const(Box::new(self.ty()?)),
+            b'V' => Type::Volatile(Box::new(self.ty()?)),
+            b'P' => Type::Pointer(Box::new(self.ty()?)),
+            b'R' => Type::Reference(Box::new(self.ty()?)),
+            b'A' => {
+                let count = self.number()?;
+                if !self.eat(b'_') {
+                    return None;
+                }
+                Type::Array(Box::new(self.ty()?), Some(count as u64))
+            }
+            // Function, e.g. `Fi_v` for `void (int)`
+            b'F' => {
+                let (params, variadic) = self.args(Some(b'_'))?;
+                Type::Function(Box::new(self.ty()?), params, variadic)
+            }
+            // Pointer to member, e.g. `M3Fooi` for `int Foo::*`
+            b'M' => {
+                let (class, _) = self.class_name()?;
+                // Qualifiers of member functions, which aren't written out
+                let qualifiers = self.rest.bytes().take_while(|c| matches!(c, b'C' | b'V')).count();
+                if self.rest.as_bytes().get(qualifiers) == Some(&b'F') {
+                    self.rest = &self.rest[qualifiers..];
+                }
+                Type::MemberPointer(Box::new(self.ty()?), class)
+            }
+            // Explicitly named class
+            b'G' => self.ty()?,
+            b'U' => Type::Named(format!("unsigned {}", builtin(self.next()?)?)),
+            b'S' => match builtin(self.next()?)? {
+                "char" => Type::Named("signed char".to_string()),
+                name => Type::Named(name.to_string()),
+            },
+            c => Type::Named(builtin(c)?.to_string()),
+        })
+    }
+
+    /// Reads parameter types up to `end`, or the end of the name if `None`.
+    /// Returns them with whether the function is variadic.
+    fn args(&mut self, end: Option<u8>) -> Option<(Vec<Type>, bool)> {
+        let mut params = vec![];
+        let mut variadic = false;
+        loop {
+            match end {
+                Some(end) if self.eat(end) => break,
+                None if self.rest.is_empty() => break,
+                _ if variadic => return None,
+                _ => {}
+            }
+            match self.peek()? {
+                b'e' => {
+                    self.next();
+                    variadic = true;
+                }
+                // Repeats of an earlier parameter, e.g. `T1` or `N21`
+                b'T' | b'N' => {
+                    let repeats = if self.next()? == b'N' { self.count()? } else { 1 };
+                    let index = self.count()?;
+                    let ty = self.types.get(index)?.clone();
+                    for _ in 0..repeats {
+                        self.types.push(ty.clone());
+                        params.push(ty.clone());
+                    }
+                }
+                _ => {
+                    let ty = self.ty()?;
+                    self.types.push(ty.clone());
+                    params.push(ty);
+                }
+            }
+        }
+        // `v` alone means no parameters
+        if matches!(params.as_slice(), [Type::Named(name)] if name == "void") {
+            params.clear();
+        }
+        Some((params, variadic))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangle_names() {
+        for (mangled, demangled) in [
+            // Constructors and destructors
+            ("__3Foo", "Foo::Foo(void)"),
+            ("__3Fooi", "Foo::Foo(int)"),
+            ("__3FooRC3Foo", "Foo::Foo(const Foo&)"),
+            ("_$_3Foo", "Foo::~Foo(void)"),
+            ("_._3Foo", "Foo::~Foo(void)"),
+            // Functions and member functions
+            ("foo__Fv", "foo(void)"),
+            ("foo__FiPCce", "foo(int, const char*, ...)"),
+            ("bar__3FooPCc", "Foo::bar(const char*)"),
+            ("bar__C3Foo", "Foo::bar(void) const"),
+            ("bar__S3Fooi", "Foo::bar(int)"),
+            ("bar__3FooUiT1", "Foo::bar(unsigned int, unsigned int)"),
+            // Operators
+            ("__pl__3FooRC3Foo", "Foo::operator+(const Foo&)"),
+            ("__as__3FooRC3Foo", "Foo::operator=(const Foo&)"),
+            ("__eq__C3FooRC3Foo", "Foo::operator==(const Foo&) const"),
+            ("__vc__3Fooi", "Foo::operator[](int)"),
+            ("__nw__FUi", "operator new(unsigned int)"),
+            ("__dl__FPv", "operator delete(void*)"),
+            ("__opi__3Foo", "Foo::operator int(void)"),
+            // Qualified names and templates
+            ("bar__Q23Foo3Bazi", "Foo::Baz::bar(int)"),
+            ("__Q23Foo3Baz", "Foo::Baz::Baz(void)"),
+            ("get__t3Vec1Zi", "Vec<int>::get(void)"),
+            // Special names
+            ("_3Foo$count", "Foo::count"),
+            ("_vt$3Foo", "Foo virtual table"),
+            ("_vt.3Foo", "Foo virtual table"),
+            ("_vt$3Bar$3Foo", "Bar::Foo virtual table"),
+            ("_GLOBAL_$I$main", "global constructors keyed to main"),
+            ("_GLOBAL_.D.bar__3Foo", "global destructors keyed to Foo::bar(void)"),
+            ("__thunk_4_bar__3Foo", "virtual function thunk (delta:-4) for Foo::bar(void)"),
+            ("__ti3Foo", "Foo type_info node"),
+        ] {
+            assert_eq!(demangle(mangled).as_deref(), Some(demangled), "{mangled}");
+        }
+    }
+
+    #[test]
+    fn c_names() {
+        for name in
+            ["main", "_start", "__start", "__main", "__bss_start", "my__var", "do__it", "func__"]
+        {
+            assert_eq!(demangle(name), None, "{name}");
+        }
+    }
+}
@@ -0,0 +1,99 @@
+mod gnuv2;
+
+use serde::{Deserialize, Serialize};
+
+/// C++ name mangling scheme used to demangle symbol names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Demangler {
+    /// Detects the scheme of each symbol name
+    #[default]
+    Auto,
+    /// Leaves symbol names mangled
+    None,
+    /// Metrowerks CodeWarrior, e.g. `__ct__3FooFv`
+    CodeWarrior,
+    /// GCC 2.x, e.g. `__3Foo` or `_$_3Foo`
+    GnuV2,
+    /// Itanium C++ ABI used by GCC 3+ and Clang, e.g. `_ZN3FooC1Ev`
+    Itanium,
+}
+
+impl Demangler {
+    pub const ALL: [Demangler; 5] = [
+        Demangler::Auto,
+        Demangler::None,
+        Demangler::CodeWarrior,
+        Demangler::GnuV2,
+        Demangler::Itanium,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Demangler::Auto => "Auto",
+            Demangler::None => "None",
+            Demangler::CodeWarrior => "CodeWarrior",
+            Demangler::GnuV2 => "GNU v2",
+            Demangler::Itanium => "Itanium",
+        }
+    }
+
+    /// Demangles a symbol name, or returns `None` if it isn't mangled in this
+    /// scheme. [`Demangler::Auto`] demangles names starting with `_Z` as
+    /// Itanium, and otherwise only tries the `preferred` scheme of the
+    /// architecture, as CodeWarrior and GCC 2.x names can look alike.
+    pub fn demangle(self, name: &str, preferred: Demangler) -> Option<String> {
+        match self {
+            Demangler::Auto if name.starts_with("_Z") => {
+                Demangler::Itanium.demangle(name, preferred)
+            }
+            Demangler::Auto => match preferred {
+                Demangler::CodeWarrior | Demangler::GnuV2 => preferred.demangle(name, preferred),
+                _ => None,
+            },
+            Demangler::None => None,
+            Demangler::CodeWarrior => cwdemangle::demangle(name, &Default::default()),
+            Demangler::GnuV2 => gnuv2::demangle(name),
+            Demangler::Itanium => {
+                cpp_demangle::Symbol::new(name).ok()?.demangle(&Default::default()).ok()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_detection() {
+        let auto = |name: &str| Demangler::Auto.demangle(name, Demangler::GnuV2);
+        assert_eq!(auto("_ZN3Foo3barEi").as_deref(), Some("Foo::bar(int)"));
+        assert_eq!(auto("bar__3Fooi").as_deref(), Some("Foo::bar(int)"));
+        // Only Itanium and the preferred scheme are tried
+        assert_eq!(Demangler::Auto.demangle("bar__3Fooi", Demangler::Itanium), None);
+        assert_eq!(Demangler::Auto.demangle("bar__3Fooi", Demangler::None), None);
+    }
+
+    #[test]
+    fn c_names() {
+        for name in [
+            "main",
+            "_start",
+            "__start",
+            "__main",
+            "__bss_start",
+            "_gp_disp",
+            "my__var",
+            "do__it",
+            "__osSetSR",
+            "__libc_start_main",
+            "_Zero",
+            "func__",
+        ] {
+            for preferred in [Demangler::GnuV2, Demangler::Itanium] {
+                assert_eq!(Demangler::Auto.demangle(name, preferred), None, "{name}");
+            }
+        }
+    }
+}
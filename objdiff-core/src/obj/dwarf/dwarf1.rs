@@ -0,0 +1,1055 @@
+use std::{borrow::Cow, collections::BTreeMap, io::Cursor, ops::Range};
+
+use anyhow::{bail, Result};
+use byteorder::{BigEndian, ReadBytesExt};
+use object::{File, Object, ObjectSection};
+
+use super::{declaration, function_declaration, section_data, Type};
+use crate::obj::{
+    ObjDebugInfo, ObjFunctionInfo, ObjGlobalInfo, ObjInlinedCall, ObjLineInfo, ObjLocation,
+    ObjMember, ObjSourceLine, ObjTypeInfo, ObjTypeKind, ObjVarLocation, ObjVariable,
+};
+
+const DW1_TAG_ARRAY_TYPE: u16 = 0x0001;
+const DW1_TAG_CLASS_TYPE: u16 = 0x0002;
+const DW1_TAG_ENUMERATION_TYPE: u16 = 0x0004;
+const DW1_TAG_FORMAL_PARAMETER: u16 = 0x0005;
+const DW1_TAG_GLOBAL_SUBROUTINE: u16 = 0x0006;
+const DW1_TAG_GLOBAL_VARIABLE: u16 = 0x0007;
+const DW1_TAG_LEXICAL_BLOCK: u16 = 0x000b;
+const DW1_TAG_LOCAL_VARIABLE: u16 = 0x000c;
+const DW1_TAG_MEMBER: u16 = 0x000d;
+const DW1_TAG_POINTER_TYPE: u16 = 0x000f;
+const DW1_TAG_REFERENCE_TYPE: u16 = 0x0010;
+const DW1_TAG_COMPILE_UNIT: u16 = 0x0011;
+const DW1_TAG_STRUCTURE_TYPE: u16 = 0x0013;
+const DW1_TAG_SUBROUTINE: u16 = 0x0014;
+const DW1_TAG_SUBROUTINE_TYPE: u16 = 0x0015;
+const DW1_TAG_TYPEDEF: u16 = 0x0016;
+const DW1_TAG_UNION_TYPE: u16 = 0x0017;
+const DW1_TAG_UNSPECIFIED_PARAMETERS: u16 = 0x0018;
+const DW1_TAG_INHERITANCE: u16 = 0x001c;
+const DW1_TAG_INLINED_SUBROUTINE: u16 = 0x001d;
+const DW1_TAG_PTR_TO_MEMBER_TYPE: u16 = 0x001f;
+
+const DW1_AT_SIBLING: u16 = 0x0012;
+const DW1_AT_LOCATION: u16 = 0x0023;
+const DW1_AT_NAME: u16 = 0x0038;
+const DW1_AT_FUND_TYPE: u16 = 0x0055;
+const DW1_AT_MOD_FUND_TYPE: u16 = 0x0063;
+const DW1_AT_USER_DEF_TYPE: u16 = 0x0072;
+const DW1_AT_MOD_U_D_TYPE: u16 = 0x0083;
+const DW1_AT_SUBSCR_DATA: u16 = 0x00a3;
+const DW1_AT_BYTE_SIZE: u16 = 0x00b6;
+const DW1_AT_BIT_OFFSET: u16 = 0x00c5;
+const DW1_AT_BIT_SIZE: u16 = 0x00d6;
+const DW1_AT_ELEMENT_LIST: u16 = 0x00f4;
+const DW1_AT_LOW_PC: u16 = 0x0111;
+const DW1_AT_HIGH_PC: u16 = 0x0121;
+const DW1_AT_CONTAINING_TYPE: u16 = 0x01d2;
+/// Metrowerks extension
+const DW1_AT_MW_MANGLED: u16 = 0x2008;
+
+const DW1_FORM_ADDR: u16 = 0x1;
+const DW1_FORM_REF: u16 = 0x2;
+const DW1_FORM_BLOCK2: u16 = 0x3;
+const DW1_FORM_BLOCK4: u16 = 0x4;
+const DW1_FORM_DATA2: u16 = 0x5;
+const DW1_FORM_DATA4: u16 = 0x6;
+const DW1_FORM_DATA8: u16 = 0x7;
+const DW1_FORM_STRING: u16 = 0x8;
+
+const DW1_OP_REG: u8 = 0x1;
+const DW1_OP_BASEREG: u8 = 0x2;
+const DW1_OP_ADDR: u8 = 0x3;
+const DW1_OP_CONST: u8 = 0x4;
+const DW1_OP_ADD: u8 = 0x7;
+
+const DW1_MOD_POINTER_TO: u8 = 0x1;
+const DW1_MOD_REFERENCE_TO: u8 = 0x2;
+const DW1_MOD_CONST: u8 = 0x3;
+const DW1_MOD_VOLATILE: u8 = 0x4;
+
+const DW1_FMT_FT_C_C: u8 = 0x0;
+const DW1_FMT_FT_C_X: u8 = 0x1;
+const DW1_FMT_FT_X_C: u8 = 0x2;
+const DW1_FMT_FT_X_X: u8 = 0x3;
+const DW1_FMT_UT_C_C: u8 = 0x4;
+const DW1_FMT_UT_C_X: u8 = 0x5;
+const DW1_FMT_UT_X_C: u8 = 0x6;
+const DW1_FMT_UT_X_X: u8 = 0x7;
+const DW1_FMT_ET: u8 = 0x8;
+
+/// Nesting of types followed before giving up, in case of malformed cycles
+const MAX_TYPE_DEPTH: usize = 32;
+
+pub(super) fn read(
+    obj_file: &File<'_>,
+    line_info: &mut ObjLineInfo,
+    debug_info: &mut ObjDebugInfo,
+) -> Result<()> {
+    let data = section_data(obj_file, ".debug")?.unwrap_or_default();
+    let entries = Entries::parse(&data).unwrap_or_else(|e| {
+        log::warn!("Failed to read DWARF 1 entries: {e:#}");
+        Entries::default()
+    });
+    read_lines(obj_file, &entries, line_info)?;
+    if let Err(e) = entries.functions(&mut debug_info.functions) {
+        log::warn!("Failed to read DWARF 1 functions: {e:#}");
+    }
+    entries.globals(&mut debug_info.globals);
+    if let Err(e) = entries.types(&mut debug_info.types) {
+        log::warn!("Failed to read DWARF 1 types: {e:#}");
+    }
+    Ok(())
+}
+
+fn read_lines(obj_file: &File<'_>, entries: &Entries, info: &mut ObjLineInfo) -> Result<()> {
+    let Some(section) = obj_file.section_by_name(".line") else {
+        return Ok(());
+    };
+    if section.size() == 0 {
+        return Ok(());
+    }
+    let data = section_data(obj_file, ".line")?.unwrap_or_default();
+    let mut reader = Cursor::new(data.as_ref());
+    // Line tables appear in the same order as their compile units
+    let mut unit_names = entries
+        .iter()
+        .filter(|entry| entry.tag == Some(DW1_TAG_COMPILE_UNIT))
+        .map(|entry| entry.name().unwrap_or_default());
+    while reader.position() + 8 <= data.len() as u64 {
+        let start = reader.position();
+        let size = reader.read_u32::<BigEndian>()?;
+        if size < 8 {
+            break;
+        }
+        let base_address = reader.read_u32::<BigEndian>()? as u64;
+        let file = info.file_index(&unit_names.next().unwrap_or_default());
+        while reader.position() < start + size as u64 {
+            let line_number = reader.read_u32::<BigEndian>()? as u64;
+            let statement_pos = reader.read_u16::<BigEndian>()?;
+            if statement_pos != 0xFFFF {
+                log::warn!("Unhandled statement pos {}", statement_pos);
+            }
+            let address_delta = reader.read_u32::<BigEndian>()? as u64;
+            info.rows
+                .insert(base_address + address_delta, ObjSourceLine { file, line: line_number });
+        }
+    }
+    Ok(())
+}
+
+enum AttrValue<'a> {
+    Address(u32),
+    Reference(u32),
+    Block(&'a [u8]),
+    Data(u64),
+    String(&'a [u8]),
+}
+
+/// Debugging information entry of a `.debug` section.
+struct Entry<'a> {
+    /// `None` for null entries, which end a list of siblings
+    tag: Option<u16>,
+    attrs: Vec<(u16, AttrValue<'a>)>,
+    /// Offset of the next entry in the section
+    end: u32,
+}
+
+impl<'a> Entry<'a> {
+    fn attr(&self, attr: u16) -> Option<&AttrValue<'a>> {
+        self.attrs.iter().find(|(a, _)| *a == attr).map(|(_, value)| value)
+    }
+
+    fn string(&self, attr: u16) -> Option<Cow<'a, str>> {
+        match self.attr(attr) {
+            Some(AttrValue::String(s)) => Some(String::from_utf8_lossy(s)),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> Option<Cow<'a, str>> { self.string(DW1_AT_NAME) }
+
+    fn data(&self, attr: u16) -> Option<u64> {
+        match self.attr(attr) {
+            Some(AttrValue::Data(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn address(&self, attr: u16) -> Option<u64> {
+        match self.attr(attr) {
+            Some(AttrValue::Address(address)) => Some(*address as u64),
+            _ => None,
+        }
+    }
+
+    fn reference(&self, attr: u16) -> Option<u32> {
+        match self.attr(attr) {
+            Some(AttrValue::Reference(offset)) => Some(*offset),
+            _ => None,
+        }
+    }
+
+    fn block(&self, attr: u16) -> Option<&'a [u8]> {
+        match self.attr(attr) {
+            Some(AttrValue::Block(block)) => Some(block),
+            _ => None,
+        }
+    }
+
+    fn pc_range(&self) -> Option<Range<u64>> {
+        Some(self.address(DW1_AT_LOW_PC)?..self.address(DW1_AT_HIGH_PC)?)
+    }
+}
+
+/// Entries of a `.debug` section, by offset.
+#[derive(Default)]
+struct Entries<'a> {
+    entries: BTreeMap<u32, Entry<'a>>,
+}
+
+impl<'a> Entries<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        let mut reader = Cursor::new(data);
+        while reader.position() + 4 <= data.len() as u64 {
+            let start = reader.position();
+            let length = reader.read_u32::<BigEndian>()? as u64;
+            // Entries shorter than a tag are padding
+            if length < 6 {
+                let end = start + length.max(4);
+                entries.insert(start as u32, Entry { tag: None, attrs: vec![], end: end as u32 });
+                reader.set_position(end);
+                continue;
+            }
+            let end = start + length;
+            let tag = reader.read_u16::<BigEndian>()?;
+            let mut attrs = vec![];
+            while reader.position() < end {
+                let attr = reader.read_u16::<BigEndian>()?;
+                let value = match attr & 0xF {
+                    DW1_FORM_ADDR => AttrValue::Address(reader.read_u32::<BigEndian>()?),
+                    DW1_FORM_REF => AttrValue::Reference(reader.read_u32::<BigEndian>()?),
+                    DW1_FORM_BLOCK2 | DW1_FORM_BLOCK4 => {
+                        let len = if attr & 0xF == DW1_FORM_BLOCK2 {
+                            reader.read_u16::<BigEndian>()? as usize
+                        } else {
+                            reader.read_u32::<BigEndian>()? as usize
+                        };
+                        let offset = reader.position() as usize;
+                        let Some(block) = data.get(offset..offset + len) else {
+                            bail!("DWARF 1 block at {offset:#x} out of bounds");
+                        };
+                        reader.set_position((offset + len) as u64);
+                        AttrValue::Block(block)
+                    }
+                    DW1_FORM_DATA2 => AttrValue::Data(reader.read_u16::<BigEndian>()? as u64),
+                    DW1_FORM_DATA4 => AttrValue::Data(reader.read_u32::<BigEndian>()? as u64),
+                    DW1_FORM_DATA8 => AttrValue::Data(reader.read_u64::<BigEndian>()?),
+                    DW1_FORM_STRING => {
+                        let offset = reader.position() as usize;
+                        let len = data[offset..].iter().position(|&b| b == 0).unwrap_or(0);
+                        reader.set_position((offset + len + 1) as u64);
+                        AttrValue::String(&data[offset..offset + len])
+                    }
+                    form => bail!("Unknown DWARF 1 attribute form {form:#x}"),
+                };
+                attrs.push((attr, value));
+            }
+            entries.insert(start as u32, Entry { tag: Some(tag), attrs, end: end as u32 });
+            reader.set_position(end);
+        }
+        Ok(Self { entries })
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Entry<'a>> { self.entries.values() }
+
+    fn get(&self, offset: u32) -> Option<&Entry<'a>> { self.entries.get(&offset) }
+
+    /// Returns the children of an entry, which follow it up to its sibling.
+    fn children(&self, entry: &Entry<'a>) -> Vec<&Entry<'a>> {
+        let mut children = vec![];
+        let Some(sibling) = entry.reference(DW1_AT_SIBLING) else {
+            return children;
+        };
+        let mut offset = entry.end;
+        while offset < sibling {
+            let Some(child) = self.get(offset) else {
+                break;
+            };
+            if child.tag.is_some() {
+                children.push(child);
+            }
+            offset = child.reference(DW1_AT_SIBLING).filter(|&s| s > offset).unwrap_or(child.end);
+        }
+        children
+    }
+
+    fn functions(&self, functions: &mut Vec<ObjFunctionInfo>) -> Result<()> {
+        for entry in self.iter() {
+            if !matches!(entry.tag, Some(DW1_TAG_GLOBAL_SUBROUTINE | DW1_TAG_SUBROUTINE)) {
+                continue;
+            }
+            let Some(range) = entry.pc_range() else {
+                continue;
+            };
+            let mut function = ObjFunctionInfo {
+                name: entry.name().unwrap_or_default().into_owned(),
+                linkage_name: entry.string(DW1_AT_MW_MANGLED).map(Cow::into_owned),
+                address: range.start,
+                size: range.end - range.start,
+                external: entry.tag == Some(DW1_TAG_GLOBAL_SUBROUTINE),
+                prototype: String::new(),
+                uses: vec![],
+                params: vec![],
+                locals: vec![],
+                inlined: vec![],
+            };
+            let mut variadic = false;
+            for child in self.children(entry) {
+                match child.tag {
+                    Some(DW1_TAG_FORMAL_PARAMETER) => {
+                        function.params.push(self.variable(child, &range, None)?);
+                        self.entry_type(child, 0)?.uses(true, &mut function.uses);
+                    }
+                    Some(DW1_TAG_UNSPECIFIED_PARAMETERS) => variadic = true,
+                    _ => self.scope_children(child, &range, None, 0, &mut function)?,
+                }
+            }
+            let params = function.params.iter().map(|param| param.declaration.clone()).collect();
+            let ret = self.entry_type(entry, 0)?;
+            ret.uses(true, &mut function.uses);
+            function.prototype = function_declaration(&ret, &function.name, params, variadic);
+            functions.push(function);
+        }
+        Ok(())
+    }
+
+    /// Collects the variables defined at the top level of compile units.
+    fn globals(&self, globals: &mut Vec<ObjGlobalInfo>) {
+        let units = self.iter().filter(|entry| entry.tag == Some(DW1_TAG_COMPILE_UNIT));
+        for entry in units.flat_map(|unit| self.children(unit)) {
+            if !matches!(entry.tag, Some(DW1_TAG_GLOBAL_VARIABLE | DW1_TAG_LOCAL_VARIABLE)) {
+                continue;
+            }
+            let Some(name) = entry.name() else {
+                continue;
+            };
+            let address = match entry.block(DW1_AT_LOCATION).and_then(location) {
+                Some(ObjLocation::Address(address)) => Some(address),
+                _ => None,
+            };
+            let ty = self.entry_type(entry, 0).unwrap_or_else(|_| Type::unknown());
+            let mut uses = vec![];
+            ty.uses(true, &mut uses);
+            globals.push(ObjGlobalInfo {
+                declaration: declaration(&ty, &name),
+                uses,
+                name: name.into_owned(),
+                linkage_name: entry.string(DW1_AT_MW_MANGLED).map(Cow::into_owned),
+                address,
+                external: entry.tag == Some(DW1_TAG_GLOBAL_VARIABLE),
+            });
+        }
+    }
+
+    fn types(&self, types: &mut Vec<ObjTypeInfo>) -> Result<()> {
+        for entry in self.iter() {
+            let Some(name) = entry.name() else {
+                continue;
+            };
+            let info = match entry.tag {
+                Some(DW1_TAG_TYPEDEF) => self.typedef_info(entry, name.into_owned())?,
+                _ => self.type_info(entry, name.into_owned(), false)?,
+            };
+            types.extend(info);
+        }
+        Ok(())
+    }
+
+    /// Reads a typedef, or the anonymous struct, union or enum it names.
+    fn typedef_info(&self, entry: &Entry<'a>, name: String) -> Result<Option<ObjTypeInfo>> {
+        if let Some(target) = self.anonymous_target(entry) {
+            if let Some(info) = self.type_info(target, name.clone(), true)? {
+                return Ok(Some(info));
+            }
+        }
+        let ty = self.entry_type(entry, 0)?;
+        let mut uses = vec![];
+        ty.uses(true, &mut uses);
+        Ok(Some(ObjTypeInfo {
+            kind: ObjTypeKind::Typedef,
+            typedef: Some(declaration(&ty, &name)),
+            uses,
+            name,
+            anonymous: false,
+            size: None,
+            bases: vec![],
+            members: vec![],
+            enumerators: vec![],
+        }))
+    }
+
+    /// Returns the unnamed type a typedef names.
+    fn anonymous_target(&self, entry: &Entry<'a>) -> Option<&Entry<'a>> {
+        let target = entry.reference(DW1_AT_USER_DEF_TYPE).and_then(|offset| self.get(offset))?;
+        target.name().is_none().then_some(target)
+    }
+
+    /// Refers to a typedef, or to the anonymous struct, union or enum it
+    /// names, as read by [`Self::typedef_info`].
+    fn typedef_type(&self, entry: &Entry<'a>, name: String) -> Type {
+        // Declarations aren't read as types, leaving the typedef
+        let target = self.anonymous_target(entry).filter(|t| t.data(DW1_AT_BYTE_SIZE).is_some());
+        match target.and_then(|target| aggregate_kind(target.tag)) {
+            Some(kind) => Type::User { kind, name, anonymous: true },
+            None => Type::User { kind: ObjTypeKind::Typedef, name, anonymous: false },
+        }
+    }
+
+    /// Reads the definition of a struct, class, union or enum. Entries
+    /// without a size are declarations.
+    fn type_info(
+        &self,
+        entry: &Entry<'a>,
+        name: String,
+        anonymous: bool,
+    ) -> Result<Option<ObjTypeInfo>> {
+        let Some(kind) = aggregate_kind(entry.tag) else {
+            return Ok(None);
+        };
+        let Some(size) = entry.data(DW1_AT_BYTE_SIZE) else {
+            return Ok(None);
+        };
+        let mut info = ObjTypeInfo {
+            kind,
+            name,
+            anonymous,
+            size: Some(size),
+            bases: vec![],
+            members: vec![],
+            enumerators: vec![],
+            typedef: None,
+            uses: vec![],
+        };
+        for child in self.children(entry) {
+            match child.tag {
+                Some(DW1_TAG_MEMBER) => {
+                    let name = child.name().unwrap_or_default().into_owned();
+                    let ty = self.entry_type(child, 0)?;
+                    ty.uses(true, &mut info.uses);
+                    info.members.push(ObjMember {
+                        declaration: declaration(&ty, &name),
+                        name,
+                        offset: child.block(DW1_AT_LOCATION).and_then(member_offset),
+                        bit_size: child.data(DW1_AT_BIT_SIZE),
+                        bit_offset: child.data(DW1_AT_BIT_OFFSET),
+                    });
+                }
+                Some(DW1_TAG_INHERITANCE) => {
+                    self.entry_type(child, 0)?.uses(true, &mut info.uses);
+                    let base = child.reference(DW1_AT_USER_DEF_TYPE).and_then(|o| self.get(o));
+                    let name = base.and_then(|base| base.name()).unwrap_or_default();
+                    info.bases.push(name.into_owned());
+                }
+                _ => {}
+            }
+        }
+        if let Some(block) = entry.block(DW1_AT_ELEMENT_LIST) {
+            info.enumerators = element_list(block)?;
+        }
+        Ok(Some(info))
+    }
+
+    /// Collects the local variables and inlined calls of a scope entry.
+    fn scope_children(
+        &self,
+        entry: &Entry<'a>,
+        range: &Range<u64>,
+        inlined_from: Option<&str>,
+        depth: usize,
+        function: &mut ObjFunctionInfo,
+    ) -> Result<()> {
+        match entry.tag {
+            Some(DW1_TAG_LOCAL_VARIABLE) => {
+                function.locals.push(self.variable(entry, range, inlined_from)?);
+            }
+            Some(DW1_TAG_FORMAL_PARAMETER) if inlined_from.is_some() => {
+                function.locals.push(self.variable(entry, range, inlined_from)?);
+            }
+            Some(DW1_TAG_LEXICAL_BLOCK) => {
+                let range = entry.pc_range().unwrap_or_else(|| range.clone());
+                for child in self.children(entry) {
+                    self.scope_children(child, &range, inlined_from, depth, function)?;
+                }
+            }
+            Some(DW1_TAG_INLINED_SUBROUTINE) => {
+                let range = entry.pc_range().unwrap_or_else(|| range.clone());
+                let name = entry.name().unwrap_or_default().into_owned();
+                for child in self.children(entry) {
+                    self.scope_children(child, &range, Some(&name), depth + 1, function)?;
+                }
+                function.inlined.push(ObjInlinedCall {
+                    name,
+                    ranges: vec![range],
+                    call_file: None,
+                    call_line: None,
+                    depth,
+                });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn variable(
+        &self,
+        entry: &Entry<'a>,
+        range: &Range<u64>,
+        inlined_from: Option<&str>,
+    ) -> Result<ObjVariable> {
+        let name = entry.name().unwrap_or_default().into_owned();
+        let location = entry.block(DW1_AT_LOCATION).and_then(location);
+        Ok(ObjVariable {
+            declaration: declaration(&self.entry_type(entry, 0)?, &name),
+            name,
+            inlined_from: inlined_from.map(str::to_string),
+            locations: location
+                .map(|location| ObjVarLocation { range: range.clone(), location })
+                .into_iter()
+                .collect(),
+        })
+    }
+
+    /// Reads the type of an entry from its type attributes, or `void` if it
+    /// has none.
+    fn entry_type(&self, entry: &Entry<'a>, depth: usize) -> Result<Type> {
+        if depth > MAX_TYPE_DEPTH {
+            return Ok(Type::unknown());
+        }
+        if let Some(AttrValue::Data(fund_type)) = entry.attr(DW1_AT_FUND_TYPE) {
+            return Ok(fund_type_name(*fund_type as u16));
+        }
+        if let Some(offset) = entry.reference(DW1_AT_USER_DEF_TYPE) {
+            return self.user_type(offset, depth + 1);
+        }
+        if let Some(block) = entry.block(DW1_AT_MOD_FUND_TYPE) {
+            let (modifiers, fund_type) = split_modified(block, 2)?;
+            let fund_type = u16::from_be_bytes(fund_type.try_into()?);
+            return Ok(apply_modifiers(modifiers, fund_type_name(fund_type)));
+        }
+        if let Some(block) = entry.block(DW1_AT_MOD_U_D_TYPE) {
+            let (modifiers, offset) = split_modified(block, 4)?;
+            let ty = self.user_type(u32::from_be_bytes(offset.try_into()?), depth + 1)?;
+            return Ok(apply_modifiers(modifiers, ty));
+        }
+        Ok(Type::void())
+    }
+
+    fn user_type(&self, offset: u32, depth: usize) -> Result<Type> {
+        let Some(entry) = self.get(offset) else {
+            return Ok(Type::unknown());
+        };
+        if let Some(kind) = aggregate_kind(entry.tag) {
+            return Ok(match entry.name() {
+                Some(name) => Type::User { kind, name: name.into_owned(), anonymous: false },
+                None => Type::anonymous(kind),
+            });
+        }
+        Ok(match entry.tag {
+            Some(DW1_TAG_TYPEDEF) => match entry.name() {
+                Some(name) => self.typedef_type(entry, name.into_owned()),
+                None => self.entry_type(entry, depth)?,
+            },
+            Some(DW1_TAG_POINTER_TYPE) => Type::Pointer(Box::new(self.entry_type(entry, depth)?)),
+            Some(DW1_TAG_REFERENCE_TYPE) => {
+                Type::Reference(Box::new(self.entry_type(entry, depth)?))
+            }
+            Some(DW1_TAG_ARRAY_TYPE) => match entry.block(DW1_AT_SUBSCR_DATA) {
+                Some(block) => self.array_type(block, depth)?,
+                None => Type::Array(Box::new(Type::unknown()), None),
+            },
+            Some(DW1_TAG_SUBROUTINE_TYPE) => {
+                let ret = self.entry_type(entry, depth)?;
+                let mut params = vec![];
+                let mut variadic = false;
+                for child in self.children(entry) {
+                    match child.tag {
+                        Some(DW1_TAG_FORMAL_PARAMETER) => {
+                            params.push(self.entry_type(child, depth)?)
+                        }
+                        Some(DW1_TAG_UNSPECIFIED_PARAMETERS) => variadic = true,
+                        _ => {}
+                    }
+                }
+                Type::Function(Box::new(ret), params, variadic)
+            }
+            Some(DW1_TAG_PTR_TO_MEMBER_TYPE) => {
+                let class = entry
+                    .reference(DW1_AT_CONTAINING_TYPE)
+                    .and_then(|offset| self.get(offset))
+                    .and_then(|class| class.name())
+                    .unwrap_or_default();
+                Type::MemberPointer(Box::new(self.entry_type(entry, depth)?), class.into_owned())
+            }
+            _ => entry.name().map_or_else(Type::unknown, |name| Type::Named(name.into_owned())),
+        })
+    }
+
+    /// Reads an array type from its subscript data: the bounds of each
+    /// dimension followed by the element type.
+    fn array_type(&self, block: &'a [u8], depth: usize) -> Result<Type> {
+        let mut reader = Cursor::new(block);
+        let mut counts = vec![];
+        let mut element = Type::unknown();
+        while (reader.position() as usize) < block.len() {
+            let format = reader.read_u8()?;
+            // Index type, a fundamental type or user-defined type reference
+            match format {
+                DW1_FMT_FT_C_C | DW1_FMT_FT_C_X | DW1_FMT_FT_X_C | DW1_FMT_FT_X_X => {
+                    reader.read_u16::<BigEndian>()?;
+                }
+                DW1_FMT_UT_C_C | DW1_FMT_UT_C_X | DW1_FMT_UT_X_C | DW1_FMT_UT_X_X => {
+                    reader.read_u32::<BigEndian>()?;
+                }
+                DW1_FMT_ET => {
+                    let attr = reader.read_u16::<BigEndian>()?;
+                    let offset = reader.position() as usize;
+                    let len = match attr {
+                        DW1_AT_FUND_TYPE => 2,
+                        DW1_AT_USER_DEF_TYPE => 4,
+                        DW1_AT_MOD_FUND_TYPE | DW1_AT_MOD_U_D_TYPE => {
+                            2 + reader.read_u16::<BigEndian>()? as usize
+                        }
+                        attr => bail!("Unknown DWARF 1 element type attribute {attr:#x}"),
+                    };
+                    let Some(value) = block.get(offset..offset + len) else {
+                        bail!("DWARF 1 element type out of bounds");
+                    };
+                    let value = match attr {
+                        DW1_AT_FUND_TYPE => {
+                            AttrValue::Data(u16::from_be_bytes(value.try_into()?) as u64)
+                        }
+                        DW1_AT_USER_DEF_TYPE => {
+                            AttrValue::Reference(u32::from_be_bytes(value.try_into()?))
+                        }
+                        _ => AttrValue::Block(&value[2..]),
+                    };
+                    let entry = Entry { tag: None, attrs: vec![(attr, value)], end: 0 };
+                    element = self.entry_type(&entry, depth)?;
+                    break;
+                }
+                format => bail!("Unknown DWARF 1 subscript format {format:#x}"),
+            }
+            // Bounds, each a constant or a location description
+            let mut bound = |constant: bool| -> Result<Option<u32>> {
+                if constant {
+                    Ok(Some(reader.read_u32::<BigEndian>()?))
+                } else {
+                    let len = reader.read_u16::<BigEndian>()? as u64;
+                    reader.set_position(reader.position() + len);
+                    Ok(None)
+                }
+            };
+            let low = bound(matches!(format & 0x3, 0 | 1))?;
+            let high = bound(matches!(format & 0x3, 0 | 2))?;
+            counts.push(low.zip(high).map(|(low, high)| (high.wrapping_sub(low) as u64) + 1));
+        }
+        Ok(counts.into_iter().rev().fold(element, |ty, count| Type::Array(Box::new(ty), count)))
+    }
+}
+
+fn aggregate_kind(tag: Option<u16>) -> Option<ObjTypeKind> {
+    match tag {
+        Some(DW1_TAG_STRUCTURE_TYPE) => Some(ObjTypeKind::Struct),
+        Some(DW1_TAG_CLASS_TYPE) => Some(ObjTypeKind::Class),
+        Some(DW1_TAG_UNION_TYPE) => Some(ObjTypeKind::Union),
+        Some(DW1_TAG_ENUMERATION_TYPE) => Some(ObjTypeKind::Enum),
+        _ => None,
+    }
+}
+
+/// Splits a modified type attribute into its modifiers and the type they apply to.
+fn split_modified(block: &[u8], type_len: usize) -> Result<(&[u8], &[u8])> {
+    if block.len() < type_len {
+        bail!("DWARF 1 modified type too short");
+    }
+    Ok(block.split_at(block.len() - type_len))
+}
+
+/// Applies type modifiers, listed outermost first.
+fn apply_modifiers(modifiers: &[u8], ty: Type) -> Type {
+    modifiers.iter().rev().fold(ty, |ty, modifier| match *modifier {
+        DW1_MOD_POINTER_TO => Type::Pointer(Box::new(ty)),
+        DW1_MOD_REFERENCE_TO => Type::Reference(Box::new(ty)),
+        DW1_MOD_CONST => Type::Const(Box::new(ty)),
+        DW1_MOD_VOLATILE => Type::Volatile(Box::new(ty)),
+        _ => ty,
+    })
+}
+
+fn fund_type_name(fund_type: u16) -> Type {
+    let name = match fund_type {
+        0x0001 => "char",
+        0x0002 => "signed char",
+        0x0003 => "unsigned char",
+        0x0004 => "short",
+        0x0005 => "signed short",
+        0x0006 => "unsigned short",
+        0x0007 => "int",
+        0x0008 => "signed int",
+        0x0009 => "unsigned int",
+        0x000a => "long",
+        0x000b => "signed long",
+        0x000c => "unsigned long",
+        0x000d => return Type::Pointer(Box::new(Type::void())),
+        0x000e => "float",
+        0x000f => "double",
+        0x0010 => "long double",
+        0x0011 => "complex",
+        0x0012 => "double complex",
+        0x0014 => "void",
+        0x0015 => "bool",
+        0x0016 => "long double complex",
+        0x8008 => "long long",
+        0x8108 => "signed long long",
+        0x8208 => "unsigned long long",
+        _ => return Type::Named(format!("fund_type_{fund_type:#x}")),
+    };
+    Type::Named(name.to_string())
+}
+
+/// Reads the constants of an enum, which are listed last to first as a
+/// 4-byte value followed by a name.
+fn element_list(block: &[u8]) -> Result<Vec<(String, i64)>> {
+    let mut reader = Cursor::new(block);
+    let mut enumerators = vec![];
+    while (reader.position() as usize) < block.len() {
+        let value = reader.read_i32::<BigEndian>()? as i64;
+        let offset = reader.position() as usize;
+        let len = block[offset..].iter().position(|&b| b == 0).unwrap_or(block.len() - offset);
+        let name = String::from_utf8_lossy(&block[offset..offset + len]).into_owned();
+        reader.set_position((offset + len + 1) as u64);
+        enumerators.push((name, value));
+    }
+    enumerators.reverse();
+    Ok(enumerators)
+}
+
+/// Reads the offset of a member from its location description, which adds a
+/// constant to the address of the containing object.
+fn member_offset(block: &[u8]) -> Option<u64> {
+    match block {
+        [DW1_OP_CONST, value @ .., DW1_OP_ADD] => {
+            Some(u32::from_be_bytes(value.try_into().ok()?) as u64)
+        }
+        _ => None,
+    }
+}
+
+/// Value on the stack of a location description.
+#[derive(Copy, Clone)]
+enum StackValue {
+    Register(u16),
+    /// Contents of a base register plus an offset
+    Memory(u16, i64),
+    Constant(i64),
+}
+
+/// Reads a location description of the forms compilers emit for variables:
+/// a register, a base register plus a constant offset, or an address.
+fn location(block: &[u8]) -> Option<ObjLocation> {
+    let mut reader = Cursor::new(block);
+    let mut stack = vec![];
+    while (reader.position() as usize) < block.len() {
+        let op = reader.read_u8().ok()?;
+        let operand = if op == DW1_OP_ADD { 0 } else { reader.read_u32::<BigEndian>().ok()? };
+        let value = match op {
+            DW1_OP_REG => StackValue::Register(operand as u16),
+            DW1_OP_BASEREG => StackValue::Memory(operand as u16, 0),
+            DW1_OP_ADDR => StackValue::Constant(operand as i64),
+            DW1_OP_CONST => StackValue::Constant(operand as i32 as i64),
+            DW1_OP_ADD => match (stack.pop()?, stack.pop()?) {
+                (StackValue::Constant(a), StackValue::Constant(b)) => StackValue::Constant(a + b),
+                (StackValue::Constant(a), StackValue::Memory(register, b))
+                | (StackValue::Memory(register, b), StackValue::Constant(a)) => {
+                    StackValue::Memory(register, a + b)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        stack.push(value);
+    }
+    match stack.as_slice() {
+        [StackValue::Register(register)] => Some(ObjLocation::Register(*register)),
+        [StackValue::Memory(register, offset)] => {
+            Some(ObjLocation::Memory { register: *register, offset: *offset })
+        }
+        [StackValue::Constant(address)] => Some(ObjLocation::Address(*address as u64)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object::{write, Architecture, BinaryFormat, Endianness, SectionKind};
+
+    use super::*;
+
+    /// Writes `.debug` entries as CodeWarrior lays them out: every entry has a
+    /// sibling reference, and lists of children end with a null entry.
+    #[derive(Default)]
+    struct DebugWriter {
+        data: Vec<u8>,
+    }
+
+    impl DebugWriter {
+        /// Writes an entry, returning its offset.
+        fn entry(&mut self, tag: u16, attrs: impl FnOnce(&mut Self)) -> u32 {
+            self.parent(tag, attrs, |_| {})
+        }
+
+        /// Writes an entry followed by its children, returning its offset.
+        fn parent(
+            &mut self,
+            tag: u16,
+            attrs: impl FnOnce(&mut Self),
+            children: impl FnOnce(&mut Self),
+        ) -> u32 {
+            let start = self.data.len();
+            self.data.extend(0u32.to_be_bytes());
+            self.data.extend(tag.to_be_bytes());
+            self.data.extend(DW1_AT_SIBLING.to_be_bytes());
+            let sibling = self.data.len();
+            self.data.extend(0u32.to_be_bytes());
+            attrs(self);
+            let length = (self.data.len() - start) as u32;
+            self.data[start..start + 4].copy_from_slice(&length.to_be_bytes());
+            let len = self.data.len();
+            children(self);
+            if self.data.len() != len {
+                self.data.extend(4u32.to_be_bytes());
+            }
+            let end = self.data.len() as u32;
+            self.data[sibling..sibling + 4].copy_from_slice(&end.to_be_bytes());
+            start as u32
+        }
+
+        fn string(&mut self, attr: u16, value: &str) {
+            self.data.extend(attr.to_be_bytes());
+            self.data.extend(value.as_bytes());
+            self.data.push(0);
+        }
+
+        fn u16(&mut self, attr: u16, value: u16) {
+            self.data.extend(attr.to_be_bytes());
+            self.data.extend(value.to_be_bytes());
+        }
+
+        fn u32(&mut self, attr: u16, value: u32) {
+            self.data.extend(attr.to_be_bytes());
+            self.data.extend(value.to_be_bytes());
+        }
+
+        fn block(&mut self, attr: u16, value: &[u8]) {
+            self.data.extend(attr.to_be_bytes());
+            self.data.extend((value.len() as u16).to_be_bytes());
+            self.data.extend(value);
+        }
+    }
+
+    /// Location operation with a 32-bit operand.
+    fn op(op: u8, operand: u32) -> Vec<u8> {
+        let mut block = vec![op];
+        block.extend(operand.to_be_bytes());
+        block
+    }
+
+    const FT_INTEGER: u16 = 0x7;
+    const FT_FLOAT: u16 = 0xe;
+
+    /// Compile unit of `main.c` as written by CodeWarrior for PowerPC:
+    ///
+    /// ```c
+    /// struct Vec { float x, y; };
+    /// int gCount;
+    /// float scale(struct Vec* v, float f) {
+    ///     int i;
+    ///     { int j; }
+    /// }
+    /// ```
+    fn debug_section() -> Vec<u8> {
+        let mut w = DebugWriter::default();
+        w.parent(
+            DW1_TAG_COMPILE_UNIT,
+            |w| {
+                w.string(DW1_AT_NAME, "main.c");
+                w.u32(DW1_AT_LOW_PC, 0x100);
+                w.u32(DW1_AT_HIGH_PC, 0x140);
+            },
+            |w| {
+                let vec = w.parent(
+                    DW1_TAG_STRUCTURE_TYPE,
+                    |w| {
+                        w.string(DW1_AT_NAME, "Vec");
+                        w.u32(DW1_AT_BYTE_SIZE, 8);
+                    },
+                    |w| {
+                        for (name, offset) in [("x", 0), ("y", 4)] {
+                            w.entry(DW1_TAG_MEMBER, |w| {
+                                w.string(DW1_AT_NAME, name);
+                                w.u16(DW1_AT_FUND_TYPE, FT_FLOAT);
+                                let mut location = op(DW1_OP_CONST, offset);
+                                location.push(DW1_OP_ADD);
+                                w.block(DW1_AT_LOCATION, &location);
+                            });
+                        }
+                    },
+                );
+                w.entry(DW1_TAG_GLOBAL_VARIABLE, |w| {
+                    w.string(DW1_AT_NAME, "gCount");
+                    w.u16(DW1_AT_FUND_TYPE, FT_INTEGER);
+                    w.block(DW1_AT_LOCATION, &op(DW1_OP_ADDR, 0x2000));
+                });
+                w.parent(
+                    DW1_TAG_GLOBAL_SUBROUTINE,
+                    |w| {
+                        w.string(DW1_AT_NAME, "scale");
+                        w.u32(DW1_AT_LOW_PC, 0x100);
+                        w.u32(DW1_AT_HIGH_PC, 0x140);
+                        w.u16(DW1_AT_FUND_TYPE, FT_FLOAT);
+                    },
+                    |w| {
+                        w.entry(DW1_TAG_FORMAL_PARAMETER, |w| {
+                            w.string(DW1_AT_NAME, "v");
+                            let mut ty = vec![DW1_MOD_POINTER_TO];
+                            ty.extend(vec.to_be_bytes());
+                            w.block(DW1_AT_MOD_U_D_TYPE, &ty);
+                            w.block(DW1_AT_LOCATION, &op(DW1_OP_REG, 3));
+                        });
+                        w.entry(DW1_TAG_FORMAL_PARAMETER, |w| {
+                            w.string(DW1_AT_NAME, "f");
+                            w.u16(DW1_AT_FUND_TYPE, FT_FLOAT);
+                            w.block(DW1_AT_LOCATION, &op(DW1_OP_REG, 33));
+                        });
+                        w.entry(DW1_TAG_LOCAL_VARIABLE, |w| {
+                            w.string(DW1_AT_NAME, "i");
+                            w.u16(DW1_AT_FUND_TYPE, FT_INTEGER);
+                            let mut location = op(DW1_OP_BASEREG, 1);
+                            location.extend(op(DW1_OP_CONST, 8));
+                            location.push(DW1_OP_ADD);
+                            w.block(DW1_AT_LOCATION, &location);
+                        });
+                        w.parent(
+                            DW1_TAG_LEXICAL_BLOCK,
+                            |w| {
+                                w.u32(DW1_AT_LOW_PC, 0x110);
+                                w.u32(DW1_AT_HIGH_PC, 0x120);
+                            },
+                            |w| {
+                                w.entry(DW1_TAG_LOCAL_VARIABLE, |w| {
+                                    w.string(DW1_AT_NAME, "j");
+                                    w.u16(DW1_AT_FUND_TYPE, FT_INTEGER);
+                                    w.block(DW1_AT_LOCATION, &op(DW1_OP_REG, 31));
+                                });
+                            },
+                        );
+                    },
+                );
+            },
+        );
+        w.data
+    }
+
+    /// Line table of `scale`: lines 3, 4 and 6 at offsets 0x0, 0x8 and 0x20.
+    fn line_section() -> Vec<u8> {
+        let mut data = vec![0; 4];
+        data.extend(0x100u32.to_be_bytes());
+        for (line, offset) in [(3u32, 0x0u32), (4, 0x8), (6, 0x20)] {
+            data.extend(line.to_be_bytes());
+            data.extend(0xFFFFu16.to_be_bytes());
+            data.extend(offset.to_be_bytes());
+        }
+        let size = data.len() as u32;
+        data[..4].copy_from_slice(&size.to_be_bytes());
+        data
+    }
+
+    fn object(debug: &[u8], line: &[u8]) -> Vec<u8> {
+        let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::PowerPc, Endianness::Big);
+        for (name, data) in [(".debug", debug), (".line", line)] {
+            let section = obj.add_section(vec![], name.as_bytes().to_vec(), SectionKind::Debug);
+            obj.append_section_data(section, data, 1);
+        }
+        obj.write().unwrap()
+    }
+
+    fn read_object(data: &[u8]) -> (ObjLineInfo, ObjDebugInfo) {
+        let obj_file = File::parse(data).unwrap();
+        let mut line_info = ObjLineInfo::default();
+        let mut debug_info = ObjDebugInfo::default();
+        read(&obj_file, &mut line_info, &mut debug_info).unwrap();
+        (line_info, debug_info)
+    }
+
+    #[test]
+    fn codewarrior_unit() {
+        let (line_info, debug_info) = read_object(&object(&debug_section(), &line_section()));
+        assert_eq!(line_info.files, ["main.c"]);
+        let lines = line_info.rows.iter().map(|(&a, l)| (a, l.line)).collect::<Vec<_>>();
+        assert_eq!(lines, [(0x100, 3), (0x108, 4), (0x120, 6)]);
+
+        let [scale] = debug_info.functions.as_slice() else {
+            panic!("expected one function");
+        };
+        assert_eq!((scale.address, scale.size, scale.external), (0x100, 0x40, true));
+        assert_eq!(scale.prototype, "float scale(struct Vec* v, float f)");
+        let locations = |vars: &[ObjVariable]| {
+            vars.iter()
+                .map(|var| {
+                    let [location] = var.locations.as_slice() else {
+                        panic!("expected one location for {}", var.name);
+                    };
+                    (var.declaration.clone(), location.range.clone(), location.location)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(locations(&scale.params), [
+            ("struct Vec* v".to_string(), 0x100..0x140, ObjLocation::Register(3)),
+            ("float f".to_string(), 0x100..0x140, ObjLocation::Register(33)),
+        ]);
+        assert_eq!(locations(&scale.locals), [
+            ("int i".to_string(), 0x100..0x140, ObjLocation::Memory { register: 1, offset: 8 }),
+            ("int j".to_string(), 0x110..0x120, ObjLocation::Register(31)),
+        ]);
+
+        let [global] = debug_info.globals.as_slice() else {
+            panic!("expected one global");
+        };
+        assert_eq!(
+            (global.declaration.as_str(), global.address, global.external),
+            ("int gCount", Some(0x2000), true)
+        );
+
+        let [vec] = debug_info.types.as_slice() else {
+            panic!("expected one type");
+        };
+        assert_eq!((vec.kind, vec.name.as_str(), vec.size), (ObjTypeKind::Struct, "Vec", Some(8)));
+        let members =
+            vec.members.iter().map(|m| (m.declaration.as_str(), m.offset)).collect::<Vec<_>>();
+        assert_eq!(members, [("float x", Some(0)), ("float y", Some(4))]);
+    }
+
+    #[test]
+    fn unreadable_entries() {
+        // An unknown attribute form can't be skipped, but line numbers are still read
+        let mut w = DebugWriter::default();
+        w.entry(DW1_TAG_COMPILE_UNIT, |w| w.u16(0x0039, 0));
+        let (line_info, debug_info) = read_object(&object(&w.data, &line_section()));
+        assert_eq!(line_info.rows.len(), 3);
+        assert!(debug_info.functions.is_empty());
+    }
+}
@@ -0,0 +1,857 @@
+use std::{borrow::Cow, collections::HashMap, ops::Range, path::Path};
+
+use anyhow::Result;
+use gimli::{
+    AttributeValue, CfaRule, DebuggingInformationEntry, EntriesTreeNode, Expression, Operation,
+    UnitOffset, UnwindSection,
+};
+use object::{Endianness, File, Object};
+
+use super::{declaration, function_declaration, section_data, Type};
+use crate::obj::{
+    ObjDebugInfo, ObjFunctionInfo, ObjGlobalInfo, ObjInlinedCall, ObjLineInfo, ObjLocation,
+    ObjMember, ObjSourceLine, ObjTypeInfo, ObjTypeKind, ObjTypeRef, ObjVarLocation, ObjVariable,
+};
+
+type Reader<'a> = gimli::EndianSlice<'a, gimli::RunTimeEndian>;
+
+type Entry<'u, 'a> = DebuggingInformationEntry<'u, 'u, Reader<'a>>;
+
+/// Nesting of types followed before giving up, in case of malformed cycles
+const MAX_TYPE_DEPTH: usize = 32;
+
+/// Abstract origins and specifications followed to find an attribute
+const MAX_ORIGIN_DEPTH: usize = 4;
+
+pub(super) fn read(
+    obj_file: &File<'_>,
+    line_info: &mut ObjLineInfo,
+    debug_info: &mut ObjDebugInfo,
+) -> Result<()> {
+    let dwarf_cow = gimli::Dwarf::load(|id| {
+        Ok::<_, anyhow::Error>(section_data(obj_file, id.name())?.unwrap_or(Cow::Borrowed(&[][..])))
+    })?;
+    let endian = match obj_file.endianness() {
+        Endianness::Little => gimli::RunTimeEndian::Little,
+        Endianness::Big => gimli::RunTimeEndian::Big,
+    };
+    let dwarf = dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, endian));
+    read_lines(&dwarf, line_info)?;
+
+    let address_size = if obj_file.is_64() { 8 } else { 4 };
+    let debug_frame = section_data(obj_file, ".debug_frame")?;
+    let eh_frame = section_data(obj_file, ".eh_frame")?;
+    let frames = match (&debug_frame, &eh_frame) {
+        (Some(data), _) => {
+            let mut section = gimli::DebugFrame::new(data, endian);
+            section.set_address_size(address_size);
+            CallFrames::Debug(section)
+        }
+        (None, Some(data)) => {
+            let mut section = gimli::EhFrame::new(data, endian);
+            section.set_address_size(address_size);
+            CallFrames::Eh(section)
+        }
+        (None, None) => CallFrames::None,
+    };
+    if let Err(e) = read_entries(&dwarf, &frames, debug_info) {
+        log::warn!("Failed to read DWARF debug info: {e:#}");
+    }
+    Ok(())
+}
+
+fn read_lines(dwarf: &gimli::Dwarf<Reader<'_>>, info: &mut ObjLineInfo) -> Result<()> {
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+        if let Some(program) = unit.line_program.clone() {
+            // Line program file index to index in `info.files`
+            let mut files = HashMap::new();
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                let Some(line) = row.line() else {
+                    continue;
+                };
+                let file = match files.get(&row.file_index()) {
+                    Some(&file) => file,
+                    None => {
+                        let path = match row.file(header) {
+                            Some(entry) => file_path(dwarf, &unit, header, entry)?,
+                            None => String::new(),
+                        };
+                        let file = info.file_index(&path);
+                        files.insert(row.file_index(), file);
+                        file
+                    }
+                };
+                info.rows.insert(row.address(), ObjSourceLine { file, line: line.get() });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Joins a line program file entry with its directory.
+fn file_path<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    header: &gimli::LineProgramHeader<R>,
+    entry: &gimli::FileEntry<R>,
+) -> Result<String> {
+    let name = dwarf.attr_string(unit, entry.path_name())?.to_string_lossy()?.into_owned();
+    let Some(directory) = entry.directory(header) else {
+        return Ok(name);
+    };
+    let directory = dwarf.attr_string(unit, directory)?.to_string_lossy()?.into_owned();
+    if directory.is_empty() || Path::new(&name).is_absolute() {
+        return Ok(name);
+    }
+    Ok(format!("{}/{}", directory.trim_end_matches('/'), name))
+}
+
+/// Where a function's frame base is held within each range of addresses,
+/// as a register and offset.
+type FrameBase = Vec<(Range<u64>, u16, i64)>;
+
+/// Call frame information, used to resolve frame bases given as the
+/// canonical frame address.
+enum CallFrames<'a> {
+    None,
+    Debug(gimli::DebugFrame<Reader<'a>>),
+    Eh(gimli::EhFrame<Reader<'a>>),
+}
+
+impl CallFrames<'_> {
+    /// Returns where the canonical frame address is held throughout the
+    /// function containing an address.
+    fn cfa(&self, address: u64) -> Result<FrameBase> {
+        match self {
+            CallFrames::None => Ok(vec![]),
+            CallFrames::Debug(section) => cfa_rows(section, address),
+            CallFrames::Eh(section) => cfa_rows(section, address),
+        }
+    }
+}
+
+fn cfa_rows<'a, S: UnwindSection<Reader<'a>>>(section: &S, address: u64) -> Result<FrameBase> {
+    let bases = gimli::BaseAddresses::default().set_eh_frame(0).set_text(0);
+    let fde = match section.fde_for_address(&bases, address, S::cie_from_offset) {
+        Ok(fde) => fde,
+        Err(gimli::Error::NoUnwindInfoForAddress) => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut ctx = gimli::UnwindContext::new();
+    let mut table = fde.rows(section, &bases, &mut ctx)?;
+    let mut rows = vec![];
+    while let Some(row) = table.next_row()? {
+        if let CfaRule::RegisterAndOffset { register, offset } = row.cfa() {
+            rows.push((row.start_address()..row.end_address(), register.0, *offset));
+        }
+    }
+    Ok(rows)
+}
+
+fn read_entries(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    frames: &CallFrames<'_>,
+    debug_info: &mut ObjDebugInfo,
+) -> Result<()> {
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let reader = UnitReader { dwarf, unit: &unit, frames };
+        let mut tree = unit.entries_tree(None)?;
+        reader.items(tree.root()?, debug_info)?;
+    }
+    Ok(())
+}
+
+struct UnitReader<'u, 'a> {
+    dwarf: &'u gimli::Dwarf<Reader<'a>>,
+    unit: &'u gimli::Unit<Reader<'a>>,
+    frames: &'u CallFrames<'a>,
+}
+
+impl<'u, 'a> UnitReader<'u, 'a> {
+    /// Collects the functions, global variables and types defined under an
+    /// entry, such as a compile unit, namespace or class.
+    fn items(
+        &self,
+        node: EntriesTreeNode<'_, '_, '_, Reader<'a>>,
+        debug_info: &mut ObjDebugInfo,
+    ) -> Result<()> {
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            match entry.tag() {
+                gimli::DW_TAG_subprogram => {
+                    if let Some(function) = self.function(child)? {
+                        debug_info.functions.push(function);
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_variable => {
+                    if let Some(global) = self.global(entry)? {
+                        debug_info.globals.push(global);
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_typedef => {
+                    if let Some(info) = self.typedef_info(entry)? {
+                        debug_info.types.push(info);
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_structure_type
+                | gimli::DW_TAG_class_type
+                | gimli::DW_TAG_union_type
+                | gimli::DW_TAG_enumeration_type => {
+                    if let Some(name) = self.string(entry, gimli::DW_AT_name)? {
+                        if let Some(info) = self.type_info(entry.offset(), name, false)? {
+                            debug_info.types.push(info);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            // Namespaces, and types nested in classes
+            self.items(child, debug_info)?;
+        }
+        Ok(())
+    }
+
+    fn function(
+        &self,
+        node: EntriesTreeNode<'_, '_, '_, Reader<'a>>,
+    ) -> Result<Option<ObjFunctionInfo>> {
+        let entry = self.unit.entry(node.entry().offset())?;
+        let ranges = self.ranges(&entry)?;
+        // Declarations and abstract instances of inlined functions have no code
+        let (Some(start), Some(end)) =
+            (ranges.iter().map(|r| r.start).min(), ranges.iter().map(|r| r.end).max())
+        else {
+            return Ok(None);
+        };
+        let frame_base = self.frame_base(&entry, start..end)?;
+        let mut function = ObjFunctionInfo {
+            name: self.string(&entry, gimli::DW_AT_name)?.unwrap_or_default(),
+            linkage_name: match self.string(&entry, gimli::DW_AT_linkage_name)? {
+                Some(name) => Some(name),
+                None => self.string(&entry, gimli::DW_AT_MIPS_linkage_name)?,
+            },
+            address: start,
+            size: end - start,
+            external: self.flag(&entry, gimli::DW_AT_external)?,
+            prototype: String::new(),
+            uses: vec![],
+            params: vec![],
+            locals: vec![],
+            inlined: vec![],
+        };
+        let mut prototype_params = vec![];
+        let mut variadic = false;
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            match child.entry().tag() {
+                gimli::DW_TAG_formal_parameter => {
+                    let param = self.variable(child.entry(), &ranges, &frame_base, None)?;
+                    let artificial = matches!(
+                        child.entry().attr_value(gimli::DW_AT_artificial)?,
+                        Some(AttributeValue::Flag(true))
+                    );
+                    if !artificial {
+                        prototype_params.push(param.declaration.clone());
+                        self.entry_type(child.entry(), 0)?.uses(true, &mut function.uses);
+                    }
+                    function.params.push(param);
+                }
+                gimli::DW_TAG_unspecified_parameters => variadic = true,
+                _ => self.scope(child, &ranges, &frame_base, None, 0, &mut function)?,
+            }
+        }
+        let ret = self.entry_type(&entry, 0)?;
+        ret.uses(true, &mut function.uses);
+        function.prototype = function_declaration(&ret, &function.name, prototype_params, variadic);
+        Ok(Some(function))
+    }
+
+    fn global(&self, entry: &Entry<'_, 'a>) -> Result<Option<ObjGlobalInfo>> {
+        if is_declaration(entry)? {
+            return Ok(None);
+        }
+        let Some(name) = self.string(entry, gimli::DW_AT_name)? else {
+            return Ok(None);
+        };
+        let address = match entry.attr_value(gimli::DW_AT_location)? {
+            Some(AttributeValue::Exprloc(expr)) => {
+                match expr.operations(self.unit.encoding()).next() {
+                    Ok(Some(Operation::Address { address })) => Some(address),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let ty = self.entry_type(entry, 0)?;
+        let mut uses = vec![];
+        ty.uses(true, &mut uses);
+        Ok(Some(ObjGlobalInfo {
+            declaration: declaration(&ty, &name),
+            uses,
+            name,
+            linkage_name: match self.string(entry, gimli::DW_AT_linkage_name)? {
+                Some(name) => Some(name),
+                None => self.string(entry, gimli::DW_AT_MIPS_linkage_name)?,
+            },
+            address,
+            external: self.flag(entry, gimli::DW_AT_external)?,
+        }))
+    }
+
+    /// Reads a typedef, or the anonymous struct, union or enum it names.
+    fn typedef_info(&self, entry: &Entry<'_, 'a>) -> Result<Option<ObjTypeInfo>> {
+        let Some(name) = self.string(entry, gimli::DW_AT_name)? else {
+            return Ok(None);
+        };
+        if let Some(AttributeValue::UnitRef(offset)) = entry.attr_value(gimli::DW_AT_type)? {
+            if self.anonymous_kind(&self.unit.entry(offset)?)?.is_some() {
+                return self.type_info(offset, name, true);
+            }
+        }
+        let ty = self.entry_type(entry, 0)?;
+        let mut uses = vec![];
+        ty.uses(true, &mut uses);
+        Ok(Some(ObjTypeInfo {
+            kind: ObjTypeKind::Typedef,
+            typedef: Some(declaration(&ty, &name)),
+            uses,
+            name,
+            anonymous: false,
+            size: None,
+            bases: vec![],
+            members: vec![],
+            enumerators: vec![],
+        }))
+    }
+
+    /// Refers to a typedef, or to the anonymous struct, union or enum it
+    /// names, as read by [`Self::typedef_info`].
+    fn typedef_type(&self, entry: &Entry<'_, 'a>, name: String) -> Result<Type> {
+        if let Some(AttributeValue::UnitRef(offset)) = entry.attr_value(gimli::DW_AT_type)? {
+            if let Some(kind) = self.anonymous_kind(&self.unit.entry(offset)?)? {
+                return Ok(Type::User { kind, name, anonymous: true });
+            }
+        }
+        Ok(Type::User { kind: ObjTypeKind::Typedef, name, anonymous: false })
+    }
+
+    /// Returns the kind of a struct, class, union or enum entry without a name.
+    fn anonymous_kind(&self, entry: &Entry<'_, 'a>) -> Result<Option<ObjTypeKind>> {
+        if entry.attr_value(gimli::DW_AT_name)?.is_some() {
+            return Ok(None);
+        }
+        Ok(aggregate_kind(entry.tag()))
+    }
+
+    /// Reads the definition of a struct, class, union or enum.
+    fn type_info(
+        &self,
+        offset: UnitOffset,
+        name: String,
+        anonymous: bool,
+    ) -> Result<Option<ObjTypeInfo>> {
+        let entry = self.unit.entry(offset)?;
+        if is_declaration(&entry)? {
+            return Ok(None);
+        }
+        let Some(kind) = aggregate_kind(entry.tag()) else {
+            return Ok(None);
+        };
+        let mut info = ObjTypeInfo {
+            kind,
+            name,
+            anonymous,
+            size: entry.attr_value(gimli::DW_AT_byte_size)?.and_then(|value| value.udata_value()),
+            bases: vec![],
+            members: vec![],
+            enumerators: vec![],
+            typedef: None,
+            uses: vec![],
+        };
+        let mut tree = self.unit.entries_tree(Some(offset))?;
+        let mut children = tree.root()?.children();
+        while let Some(child) = children.next()? {
+            let child = child.entry();
+            match child.tag() {
+                // Static members are declarations
+                gimli::DW_TAG_member if !is_declaration(child)? => {
+                    info.members.push(self.member(child, &mut info.uses)?)
+                }
+                gimli::DW_TAG_inheritance => {
+                    if let Some(AttributeValue::UnitRef(base)) =
+                        child.attr_value(gimli::DW_AT_type)?
+                    {
+                        self.resolve_type(base, 0)?.uses(true, &mut info.uses);
+                        let base = self.unit.entry(base)?;
+                        info.bases.push(self.string(&base, gimli::DW_AT_name)?.unwrap_or_default());
+                    }
+                }
+                gimli::DW_TAG_enumerator => {
+                    let value = match child.attr_value(gimli::DW_AT_const_value)? {
+                        Some(AttributeValue::Udata(value)) => value as i64,
+                        Some(value) => value.sdata_value().unwrap_or_default(),
+                        None => 0,
+                    };
+                    let name = self.string(child, gimli::DW_AT_name)?.unwrap_or_default();
+                    info.enumerators.push((name, value));
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(info))
+    }
+
+    /// Reads a data member, collecting the types it uses.
+    fn member(&self, entry: &Entry<'_, 'a>, uses: &mut Vec<ObjTypeRef>) -> Result<ObjMember> {
+        let name = self.string(entry, gimli::DW_AT_name)?.unwrap_or_default();
+        let udata = |attr| -> Result<Option<u64>> {
+            Ok(entry.attr_value(attr)?.and_then(|value| value.udata_value()))
+        };
+        let mut offset = match entry.attr_value(gimli::DW_AT_data_member_location)? {
+            Some(AttributeValue::Exprloc(expr)) => {
+                match expr.operations(self.unit.encoding()).next() {
+                    Ok(Some(Operation::PlusConstant { value })) => Some(value),
+                    _ => None,
+                }
+            }
+            Some(value) => value.udata_value(),
+            // Union members
+            None => Some(0),
+        };
+        let mut bit_offset = udata(gimli::DW_AT_bit_offset)?;
+        if let Some(data_bit_offset) = udata(gimli::DW_AT_data_bit_offset)? {
+            offset = Some(data_bit_offset / 8);
+            bit_offset = Some(data_bit_offset % 8);
+        }
+        let ty = self.entry_type(entry, 0)?;
+        ty.uses(true, uses);
+        Ok(ObjMember {
+            declaration: declaration(&ty, &name),
+            name,
+            offset,
+            bit_size: udata(gimli::DW_AT_bit_size)?,
+            bit_offset,
+        })
+    }
+
+    /// Collects the local variables and inlined calls of a scope entry.
+    fn scope(
+        &self,
+        node: EntriesTreeNode<'_, '_, '_, Reader<'a>>,
+        ranges: &[Range<u64>],
+        frame_base: &FrameBase,
+        inlined_from: Option<&str>,
+        depth: usize,
+        function: &mut ObjFunctionInfo,
+    ) -> Result<()> {
+        let entry = node.entry();
+        let (inlined_from, depth) = match entry.tag() {
+            gimli::DW_TAG_variable => {
+                function.locals.push(self.variable(entry, ranges, frame_base, inlined_from)?);
+                return Ok(());
+            }
+            gimli::DW_TAG_formal_parameter if inlined_from.is_some() => {
+                function.locals.push(self.variable(entry, ranges, frame_base, inlined_from)?);
+                return Ok(());
+            }
+            gimli::DW_TAG_lexical_block => (inlined_from.map(str::to_string), depth),
+            gimli::DW_TAG_inlined_subroutine => {
+                let name = self.string(entry, gimli::DW_AT_name)?.unwrap_or_default();
+                function.inlined.push(ObjInlinedCall {
+                    name: name.clone(),
+                    ranges: self.ranges(entry)?,
+                    call_file: self.call_file(entry)?,
+                    call_line: entry
+                        .attr_value(gimli::DW_AT_call_line)?
+                        .and_then(|value| value.udata_value()),
+                    depth,
+                });
+                (Some(name), depth + 1)
+            }
+            _ => return Ok(()),
+        };
+        let scope_ranges = self.ranges(entry)?;
+        let ranges = if scope_ranges.is_empty() { ranges } else { &scope_ranges };
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            self.scope(child, ranges, frame_base, inlined_from.as_deref(), depth, function)?;
+        }
+        Ok(())
+    }
+
+    fn variable(
+        &self,
+        entry: &Entry<'_, 'a>,
+        ranges: &[Range<u64>],
+        frame_base: &FrameBase,
+        inlined_from: Option<&str>,
+    ) -> Result<ObjVariable> {
+        let name = self.string(entry, gimli::DW_AT_name)?.unwrap_or_default();
+        let mut locations = vec![];
+        match entry.attr_value(gimli::DW_AT_location)? {
+            Some(AttributeValue::Exprloc(expr)) => {
+                for range in ranges {
+                    locations.extend(self.locations(range, expr, frame_base));
+                }
+            }
+            Some(attr) => {
+                if let Some(mut iter) = self.dwarf.attr_locations(self.unit, attr)? {
+                    while let Some(entry) = iter.next()? {
+                        let range = entry.range.begin..entry.range.end;
+                        locations.extend(self.locations(&range, entry.data, frame_base));
+                    }
+                }
+            }
+            None => {}
+        }
+        merge_locations(&mut locations);
+        Ok(ObjVariable {
+            declaration: declaration(&self.entry_type(entry, 0)?, &name),
+            name,
+            inlined_from: inlined_from.map(str::to_string),
+            locations,
+        })
+    }
+
+    /// Resolves a location expression of the forms compilers emit for
+    /// variables: a register, a register or frame base plus an offset, or
+    /// an address. Values split into pieces resolve to their first piece.
+    fn locations(
+        &self,
+        range: &Range<u64>,
+        expr: Expression<Reader<'a>>,
+        frame_base: &FrameBase,
+    ) -> Vec<ObjVarLocation> {
+        let mut ops = expr.operations(self.unit.encoding());
+        let Ok(Some(op)) = ops.next() else {
+            return vec![];
+        };
+        if !matches!(ops.next(), Ok(None | Some(Operation::Piece { .. }))) {
+            return vec![];
+        }
+        let location = match op {
+            Operation::Register { register } => ObjLocation::Register(register.0),
+            Operation::RegisterOffset { register, offset, .. } => {
+                ObjLocation::Memory { register: register.0, offset }
+            }
+            Operation::Address { address } => ObjLocation::Address(address),
+            Operation::FrameOffset { offset } => {
+                return frame_locations(frame_base, range, offset);
+            }
+            _ => return vec![],
+        };
+        vec![ObjVarLocation { range: range.clone(), location }]
+    }
+
+    fn frame_base(&self, entry: &Entry<'_, 'a>, range: Range<u64>) -> Result<FrameBase> {
+        match entry.attr_value(gimli::DW_AT_frame_base)? {
+            Some(AttributeValue::Exprloc(expr)) => self.frame_base_rows(range, expr),
+            Some(attr) => {
+                let mut rows = vec![];
+                if let Some(mut iter) = self.dwarf.attr_locations(self.unit, attr)? {
+                    while let Some(entry) = iter.next()? {
+                        rows.extend(
+                            self.frame_base_rows(entry.range.begin..entry.range.end, entry.data)?,
+                        );
+                    }
+                }
+                Ok(rows)
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    fn frame_base_rows(
+        &self,
+        range: Range<u64>,
+        expr: Expression<Reader<'a>>,
+    ) -> Result<FrameBase> {
+        let mut ops = expr.operations(self.unit.encoding());
+        Ok(match ops.next() {
+            Ok(Some(Operation::Register { register })) => vec![(range, register.0, 0)],
+            Ok(Some(Operation::RegisterOffset { register, offset, .. })) => {
+                vec![(range, register.0, offset)]
+            }
+            Ok(Some(Operation::CallFrameCFA)) => self
+                .frames
+                .cfa(range.start)?
+                .into_iter()
+                .filter_map(|(cfa_range, register, offset)| {
+                    let start = cfa_range.start.max(range.start);
+                    let end = cfa_range.end.min(range.end);
+                    (start < end).then_some((start..end, register, offset))
+                })
+                .collect(),
+            _ => vec![],
+        })
+    }
+
+    fn ranges(&self, entry: &Entry<'_, 'a>) -> Result<Vec<Range<u64>>> {
+        let mut ranges = vec![];
+        let mut iter = self.dwarf.die_ranges(self.unit, entry)?;
+        while let Some(range) = iter.next()? {
+            if range.begin < range.end {
+                ranges.push(range.begin..range.end);
+            }
+        }
+        Ok(ranges)
+    }
+
+    fn call_file(&self, entry: &Entry<'_, 'a>) -> Result<Option<String>> {
+        let Some(AttributeValue::FileIndex(index)) = entry.attr_value(gimli::DW_AT_call_file)?
+        else {
+            return Ok(None);
+        };
+        let Some(program) = &self.unit.line_program else {
+            return Ok(None);
+        };
+        match program.header().file(index) {
+            Some(file) => Ok(Some(file_path(self.dwarf, self.unit, program.header(), file)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads an attribute, following abstract origins and specifications to
+    /// the entries describing the source declaration.
+    fn attr(
+        &self,
+        entry: &Entry<'_, 'a>,
+        attr: gimli::DwAt,
+    ) -> Result<Option<AttributeValue<Reader<'a>>>> {
+        let mut entry = self.unit.entry(entry.offset())?;
+        for _ in 0..MAX_ORIGIN_DEPTH {
+            if let Some(value) = entry.attr_value(attr)? {
+                return Ok(Some(value));
+            }
+            let origin = match entry.attr_value(gimli::DW_AT_abstract_origin)? {
+                Some(origin) => Some(origin),
+                None => entry.attr_value(gimli::DW_AT_specification)?,
+            };
+            let Some(AttributeValue::UnitRef(offset)) = origin else {
+                return Ok(None);
+            };
+            entry = self.unit.entry(offset)?;
+        }
+        Ok(None)
+    }
+
+    fn flag(&self, entry: &Entry<'_, 'a>, attr: gimli::DwAt) -> Result<bool> {
+        Ok(matches!(self.attr(entry, attr)?, Some(AttributeValue::Flag(true))))
+    }
+
+    fn string(&self, entry: &Entry<'_, 'a>, attr: gimli::DwAt) -> Result<Option<String>> {
+        let Some(value) = self.attr(entry, attr)? else {
+            return Ok(None);
+        };
+        Ok(self.dwarf.attr_string(self.unit, value).ok().map(|s| s.to_string_lossy().into_owned()))
+    }
+
+    /// Reads the type of an entry, or `void` if it has none.
+    fn entry_type(&self, entry: &Entry<'_, 'a>, depth: usize) -> Result<Type> {
+        match self.attr(entry, gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(offset)) => self.resolve_type(offset, depth + 1),
+            Some(_) => Ok(Type::unknown()),
+            None => Ok(Type::void()),
+        }
+    }
+
+    fn resolve_type(&self, offset: UnitOffset, depth: usize) -> Result<Type> {
+        if depth > MAX_TYPE_DEPTH {
+            return Ok(Type::unknown());
+        }
+        let entry = self.unit.entry(offset)?;
+        let name = self.string(&entry, gimli::DW_AT_name)?;
+        if let Some(kind) = aggregate_kind(entry.tag()) {
+            return Ok(match name {
+                Some(name) => Type::User { kind, name, anonymous: false },
+                None => Type::anonymous(kind),
+            });
+        }
+        let inner = || -> Result<Box<Type>> { Ok(Box::new(self.entry_type(&entry, depth)?)) };
+        Ok(match entry.tag() {
+            gimli::DW_TAG_typedef => match name {
+                Some(name) => self.typedef_type(&entry, name)?,
+                None => *inner()?,
+            },
+            gimli::DW_TAG_pointer_type => Type::Pointer(inner()?),
+            gimli::DW_TAG_reference_type | gimli::DW_TAG_rvalue_reference_type => {
+                Type::Reference(inner()?)
+            }
+            gimli::DW_TAG_const_type => Type::Const(inner()?),
+            gimli::DW_TAG_volatile_type => Type::Volatile(inner()?),
+            gimli::DW_TAG_restrict_type | gimli::DW_TAG_atomic_type => *inner()?,
+            gimli::DW_TAG_array_type => {
+                let mut counts = vec![];
+                let mut tree = self.unit.entries_tree(Some(offset))?;
+                let mut children = tree.root()?.children();
+                while let Some(child) = children.next()? {
+                    let child = child.entry();
+                    if child.tag() != gimli::DW_TAG_subrange_type {
+                        continue;
+                    }
+                    let attr = |attr| -> Result<Option<u64>> {
+                        Ok(child.attr_value(attr)?.and_then(|value| value.udata_value()))
+                    };
+                    let count = match attr(gimli::DW_AT_count)? {
+                        Some(count) => Some(count),
+                        None => attr(gimli::DW_AT_upper_bound)?.map(|upper| {
+                            upper + 1 - attr(gimli::DW_AT_lower_bound).ok().flatten().unwrap_or(0)
+                        }),
+                    };
+                    counts.push(count);
+                }
+                let element = self.entry_type(&entry, depth)?;
+                counts.into_iter().rev().fold(element, |ty, count| Type::Array(Box::new(ty), count))
+            }
+            gimli::DW_TAG_subroutine_type => {
+                let mut params = vec![];
+                let mut variadic = false;
+                let mut tree = self.unit.entries_tree(Some(offset))?;
+                let mut children = tree.root()?.children();
+                while let Some(child) = children.next()? {
+                    match child.entry().tag() {
+                        gimli::DW_TAG_formal_parameter => {
+                            params.push(self.entry_type(child.entry(), depth)?)
+                        }
+                        gimli::DW_TAG_unspecified_parameters => variadic = true,
+                        _ => {}
+                    }
+                }
+                Type::Function(inner()?, params, variadic)
+            }
+            gimli::DW_TAG_ptr_to_member_type => {
+                let class = match entry.attr_value(gimli::DW_AT_containing_type)? {
+                    Some(AttributeValue::UnitRef(class)) => {
+                        self.string(&self.unit.entry(class)?, gimli::DW_AT_name)?
+                    }
+                    _ => None,
+                };
+                Type::MemberPointer(inner()?, class.unwrap_or_default())
+            }
+            _ => name.map_or_else(Type::unknown, Type::Named),
+        })
+    }
+}
+
+fn aggregate_kind(tag: gimli::DwTag) -> Option<ObjTypeKind> {
+    match tag {
+        gimli::DW_TAG_structure_type => Some(ObjTypeKind::Struct),
+        gimli::DW_TAG_class_type => Some(ObjTypeKind::Class),
+        gimli::DW_TAG_union_type => Some(ObjTypeKind::Union),
+        gimli::DW_TAG_enumeration_type => Some(ObjTypeKind::Enum),
+        _ => None,
+    }
+}
+
+fn is_declaration(entry: &Entry<'_, '_>) -> Result<bool> {
+    Ok(matches!(entry.attr_value(gimli::DW_AT_declaration)?, Some(AttributeValue::Flag(true))))
+}
+
+/// Resolves a location relative to the frame base over a range of addresses.
+fn frame_locations(frame_base: &FrameBase, range: &Range<u64>, offset: i64) -> Vec<ObjVarLocation> {
+    frame_base
+        .iter()
+        .filter_map(|(frame_range, register, base_offset)| {
+            let start = frame_range.start.max(range.start);
+            let end = frame_range.end.min(range.end);
+            (start < end).then(|| ObjVarLocation {
+                range: start..end,
+                location: ObjLocation::Memory { register: *register, offset: base_offset + offset },
+            })
+        })
+        .collect()
+}
+
+/// Merges adjacent ranges holding a variable in the same place.
+fn merge_locations(locations: &mut Vec<ObjVarLocation>) {
+    locations.sort_by_key(|loc| loc.range.start);
+    locations.dedup_by(|next, prev| {
+        if prev.location == next.location && prev.range.end == next.range.start {
+            prev.range.end = next.range.end;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `gcc -O1 -gdwarf-2 -gstrict-dwarf -fno-pic -fdebug-prefix-map=$PWD=. -c dwarf2-x86_64.c`
+    const X86_64: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/dwarf2-x86_64.o");
+
+    #[test]
+    fn gcc_unit() {
+        // Read rather than included, as the ELF headers must be aligned
+        let data = std::fs::read(X86_64).unwrap();
+        let obj_file = File::parse(data.as_slice()).unwrap();
+        let mut line_info = ObjLineInfo::default();
+        let mut debug_info = ObjDebugInfo::default();
+        read(&obj_file, &mut line_info, &mut debug_info).unwrap();
+        assert_eq!(line_info.files, ["./dwarf2-x86_64.c"]);
+        assert_eq!(line_info.line(0x0), Some(14));
+        assert_eq!(line_info.line(0x16), Some(10));
+        assert_eq!(line_info.line(0x52), Some(19));
+
+        let [func] = debug_info.functions.as_slice() else {
+            panic!("expected one function");
+        };
+        assert_eq!((func.address, func.size, func.external), (0x0, 0x5c, true));
+        assert_eq!(func.prototype, "float length_sq(const struct Vec* v, int n)");
+        let variable = |var: &ObjVariable| {
+            let locations = var.locations.iter().map(|l| (l.range.clone(), l.location));
+            (var.declaration.clone(), var.inlined_from.clone(), locations.collect::<Vec<_>>())
+        };
+        let rdi = ObjLocation::Register(5);
+        let rsi = ObjLocation::Register(4);
+        assert_eq!(func.params.iter().map(variable).collect::<Vec<_>>(), [
+            ("const struct Vec* v".to_string(), None, vec![(0x0..0x5c, rdi)]),
+            ("int n".to_string(), None, vec![(0x0..0x4b, rsi), (0x56..0x5c, rsi)]),
+        ]);
+        let xmm3 = ObjLocation::Register(20);
+        let rax = ObjLocation::Register(0);
+        let dot = Some("dot".to_string());
+        assert_eq!(func.locals.iter().map(variable).collect::<Vec<_>>(), [
+            ("float sum".to_string(), None, vec![(0x10..0x4b, xmm3)]),
+            ("int i".to_string(), None, vec![(0x10..0x42, rax)]),
+            ("const struct Vec* b".to_string(), dot.clone(), vec![]),
+            ("const struct Vec* a".to_string(), dot, vec![]),
+        ]);
+        let [inlined] = func.inlined.as_slice() else {
+            panic!("expected one inlined call");
+        };
+        assert_eq!(inlined.name, "dot");
+        assert_eq!(inlined.ranges, vec![0x10..0x1b]);
+
+        let globals = debug_info
+            .globals
+            .iter()
+            .map(|g| (g.declaration.as_str(), g.external))
+            .collect::<Vec<_>>();
+        assert_eq!(globals, [("u32 gCount", true), ("int sTable[4]", false)]);
+
+        let types = debug_info
+            .types
+            .iter()
+            .map(|t| (t.kind, t.name.as_str(), t.size, t.typedef.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, [
+            (ObjTypeKind::Struct, "Vec", Some(8), None),
+            (ObjTypeKind::Typedef, "u32", None, Some("unsigned int u32")),
+        ]);
+        let members =
+            debug_info.types[0].members.iter().map(|m| (m.declaration.as_str(), m.offset));
+        assert_eq!(members.collect::<Vec<_>>(), [("float x", Some(0)), ("float y", Some(4))]);
+    }
+}
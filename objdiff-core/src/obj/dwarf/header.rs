@@ -0,0 +1,469 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::obj::{ObjDebugInfo, ObjMember, ObjSectionKind, ObjSymbol, ObjTypeInfo, ObjTypeKind};
+
+impl ObjDebugInfo {
+    /// Writes C declarations of every type, global and function, with types
+    /// following the types they use.
+    pub fn header(&self) -> String {
+        let mut out = String::new();
+        self.write_types(&mut out, 0..self.types.len());
+        if !self.globals.is_empty() {
+            out.push('\n');
+        }
+        for global in &self.globals {
+            let storage = if global.external { "" } else { "static " };
+            writeln!(out, "{storage}{};", global.declaration).unwrap();
+        }
+        if !self.functions.is_empty() {
+            out.push('\n');
+        }
+        // Inline functions are described by each compile unit using them
+        let mut seen = HashSet::new();
+        for function in self.functions.iter().filter(|f| seen.insert(&f.prototype)) {
+            let storage = if function.external { "" } else { "static " };
+            writeln!(out, "{storage}{};", function.prototype).unwrap();
+        }
+        out
+    }
+
+    /// Writes the C declaration of a function, or of a global in a data
+    /// section.
+    pub fn symbol_declaration(&self, symbol: &ObjSymbol, kind: ObjSectionKind) -> Option<String> {
+        let (external, declaration) = match kind {
+            ObjSectionKind::Code => {
+                self.function(symbol).map(|function| (function.external, &function.prototype))?
+            }
+            _ => self.global(symbol).map(|global| (global.external, &global.declaration))?,
+        };
+        let storage = if external { "" } else { "static " };
+        Some(format!("{storage}{declaration};\n"))
+    }
+
+    /// Writes the C declaration of a function or global symbol, preceded by
+    /// the definitions of the types it uses.
+    pub fn symbol_header(&self, symbol: &ObjSymbol, kind: ObjSectionKind) -> Option<String> {
+        let declaration = self.symbol_declaration(symbol, kind)?;
+        let uses = match kind {
+            ObjSectionKind::Code => &self.function(symbol)?.uses,
+            _ => &self.global(symbol)?.uses,
+        };
+        let used = uses.iter().filter_map(|used| {
+            self.types.iter().position(|t| t.kind == used.kind && t.name == used.name)
+        });
+        let mut out = String::new();
+        self.write_types(&mut out, used);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&declaration);
+        Some(out)
+    }
+
+    /// Writes the definitions of types and the types they use, each after the
+    /// types it contains. Types only pointed to before their definition are
+    /// declared up front.
+    fn write_types(&self, out: &mut String, roots: impl Iterator<Item = usize>) {
+        let mut by_key = HashMap::new();
+        for (index, info) in self.types.iter().enumerate() {
+            by_key.entry((info.kind, info.name.as_str())).or_insert(index);
+        }
+        let mut visitor = TypeVisitor {
+            info: self,
+            by_key,
+            visited: vec![Visit::None; self.types.len()],
+            order: vec![],
+            forward: vec![],
+            pending: vec![],
+        };
+        for index in roots {
+            visitor.define(index);
+        }
+        while let Some(index) = visitor.pending.pop() {
+            visitor.visit(index);
+        }
+        for &index in &visitor.forward {
+            let info = &self.types[index];
+            writeln!(out, "{} {};", info.kind.keyword(), info.name).unwrap();
+        }
+        if !visitor.forward.is_empty() {
+            out.push('\n');
+        }
+        let mut prev_multiline = false;
+        for (i, index) in visitor.order.into_iter().enumerate() {
+            let definition = self.types[index].definition();
+            // Blank lines around multi-line definitions
+            let multiline = definition.lines().nth(1).is_some();
+            if i > 0 && (multiline || prev_multiline) {
+                out.push('\n');
+            }
+            out.push_str(&definition);
+            prev_multiline = multiline;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Visit {
+    None,
+    InProgress,
+    Done,
+}
+
+struct TypeVisitor<'a> {
+    info: &'a ObjDebugInfo,
+    by_key: HashMap<(ObjTypeKind, &'a str), usize>,
+    visited: Vec<Visit>,
+    /// Types in the order to define them
+    order: Vec<usize>,
+    /// Types to declare before any definitions
+    forward: Vec<usize>,
+    /// Declared types left to define
+    pending: Vec<usize>,
+}
+
+impl TypeVisitor<'_> {
+    /// Whether a type can be declared without its definition.
+    fn declarable(&self, index: usize) -> bool {
+        let info = &self.info.types[index];
+        matches!(info.kind, ObjTypeKind::Struct | ObjTypeKind::Class | ObjTypeKind::Union)
+            && !info.anonymous
+    }
+
+    /// Queues a type to define, declaring it up front if not yet defined.
+    fn declare(&mut self, index: usize) {
+        if self.visited[index] == Visit::Done {
+            return;
+        }
+        if !self.forward.contains(&index) {
+            self.forward.push(index);
+        }
+        self.pending.push(index);
+    }
+
+    /// Returns the types used by a type, with whether each is used by value,
+    /// leaving out the type itself: a struct can point to itself without a
+    /// declaration.
+    fn uses(&self, index: usize) -> Vec<(usize, bool)> {
+        let info = self.info;
+        info.types[index]
+            .uses
+            .iter()
+            .filter_map(|used| {
+                let &found = self.by_key.get(&(used.kind, used.name.as_str()))?;
+                (found != index).then_some((found, used.by_value))
+            })
+            .collect()
+    }
+
+    /// Defines a type, and for a typedef the types it names by value: using a
+    /// typedef by value needs the complete aliased type.
+    fn define(&mut self, index: usize) {
+        self.visit(index);
+        if self.visited[index] != Visit::Done || self.info.types[index].typedef.is_none() {
+            return;
+        }
+        for (used, by_value) in self.uses(index) {
+            if by_value && self.visited[used] != Visit::Done {
+                self.define(used);
+            }
+        }
+    }
+
+    /// Defines a type after the types it needs.
+    fn visit(&mut self, index: usize) {
+        match self.visited[index] {
+            Visit::Done => return,
+            // Contains itself through other types; the best we can do is declare it
+            Visit::InProgress => {
+                if self.declarable(index) && !self.forward.contains(&index) {
+                    self.forward.push(index);
+                }
+                return;
+            }
+            Visit::None => {}
+        }
+        self.visited[index] = Visit::InProgress;
+        // A typedef only needs the types it names declared until it's used by
+        // value, which lets `typedef struct Foo Foo;` precede `struct Foo`
+        let typedef = self.info.types[index].typedef.is_some();
+        for (used, by_value) in self.uses(index) {
+            if by_value && !typedef {
+                self.define(used);
+            } else if !self.declarable(used) {
+                self.visit(used);
+            } else {
+                self.declare(used);
+            }
+        }
+        self.visited[index] = Visit::Done;
+        self.order.push(index);
+    }
+}
+
+impl ObjTypeInfo {
+    /// Writes a C definition of the type, with the offsets of its members.
+    pub fn definition(&self) -> String {
+        if let Some(typedef) = &self.typedef {
+            return format!("typedef {typedef};\n");
+        }
+        let mut out = String::new();
+        if self.anonymous {
+            out.push_str("typedef ");
+        }
+        out.push_str(self.kind.keyword());
+        if !self.anonymous {
+            write!(out, " {}", self.name).unwrap();
+        }
+        if !self.bases.is_empty() {
+            let access = if self.kind == ObjTypeKind::Class { "public " } else { "" };
+            let bases = self.bases.iter().map(|base| format!("{access}{base}"));
+            write!(out, " : {}", bases.collect::<Vec<_>>().join(", ")).unwrap();
+        }
+        out.push_str(" {\n");
+        for (name, value) in &self.enumerators {
+            writeln!(out, "    {name} = {value},").unwrap();
+        }
+        for member in &self.members {
+            writeln!(out, "    {}", member_definition(member)).unwrap();
+        }
+        out.push('}');
+        if self.anonymous {
+            write!(out, " {}", self.name).unwrap();
+        }
+        out.push(';');
+        if let Some(size) = self.size.filter(|_| self.kind != ObjTypeKind::Enum) {
+            write!(out, " // size {size:#x}").unwrap();
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Writes a member declaration, e.g. `/* 0x0008 */ u32 flags : 3;`.
+fn member_definition(member: &ObjMember) -> String {
+    let mut out = match member.offset {
+        Some(offset) => format!("/* {offset:#06x} */ {}", member.declaration),
+        None => member.declaration.clone(),
+    };
+    if let Some(bit_size) = member.bit_size {
+        write!(out, " : {bit_size}").unwrap();
+    }
+    out.push(';');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diff::tests::symbol,
+        obj::{ObjFunctionInfo, ObjGlobalInfo, ObjTypeRef},
+    };
+
+    fn uses(uses: &[(ObjTypeKind, &str, bool)]) -> Vec<ObjTypeRef> {
+        uses.iter()
+            .map(|&(kind, name, by_value)| ObjTypeRef { kind, name: name.to_string(), by_value })
+            .collect()
+    }
+
+    fn ty(
+        kind: ObjTypeKind,
+        name: &str,
+        members: &[(&str, &str, u64)],
+        used: &[(ObjTypeKind, &str, bool)],
+    ) -> ObjTypeInfo {
+        ObjTypeInfo {
+            kind,
+            name: name.to_string(),
+            anonymous: false,
+            size: Some(members.last().map_or(4, |&(_, _, offset)| offset + 4)),
+            bases: vec![],
+            members: members
+                .iter()
+                .map(|&(name, declaration, offset)| ObjMember {
+                    name: name.to_string(),
+                    declaration: declaration.to_string(),
+                    offset: Some(offset),
+                    bit_size: None,
+                    bit_offset: None,
+                })
+                .collect(),
+            enumerators: vec![],
+            typedef: None,
+            uses: uses(used),
+        }
+    }
+
+    fn typedef(name: &str, declaration: &str, used: &[(ObjTypeKind, &str, bool)]) -> ObjTypeInfo {
+        ObjTypeInfo {
+            typedef: Some(declaration.to_string()),
+            size: None,
+            ..ty(ObjTypeKind::Typedef, name, &[], used)
+        }
+    }
+
+    /// Types listed before the types they use, with `Node` and `Edge`
+    /// pointing to each other.
+    fn debug_info() -> ObjDebugInfo {
+        use ObjTypeKind::*;
+        ObjDebugInfo {
+            functions: vec![ObjFunctionInfo {
+                name: "walk".to_string(),
+                linkage_name: None,
+                address: 0,
+                size: 0,
+                external: true,
+                prototype: "void walk(struct Edge* edge)".to_string(),
+                uses: uses(&[(Struct, "Edge", true)]),
+                params: vec![],
+                locals: vec![],
+                inlined: vec![],
+            }],
+            globals: vec![ObjGlobalInfo {
+                name: "origin".to_string(),
+                linkage_name: None,
+                declaration: "Point origin".to_string(),
+                uses: uses(&[(Struct, "Point", true)]),
+                address: None,
+                external: false,
+            }],
+            types: vec![
+                ty(
+                    Struct,
+                    "Node",
+                    &[
+                        ("kind", "Kind kind", 0),
+                        ("value", "union Value value", 4),
+                        ("edges", "struct Edge* edges", 8),
+                        ("handler", "Handler handler", 12),
+                    ],
+                    &[
+                        (Typedef, "Kind", true),
+                        (Union, "Value", true),
+                        (Struct, "Edge", false),
+                        (Typedef, "Handler", true),
+                    ],
+                ),
+                ty(
+                    Struct,
+                    "Edge",
+                    &[("from", "struct Node* from", 0), ("to", "struct Node* to", 4)],
+                    &[(Struct, "Node", false)],
+                ),
+                typedef("Handler", "void (*Handler)(struct Node*)", &[(Struct, "Node", false)]),
+                ty(Union, "Value", &[("i", "int i", 0), ("f", "float f", 0)], &[]),
+                typedef("Kind", "enum Kind Kind", &[(Enum, "Kind", true)]),
+                ObjTypeInfo {
+                    enumerators: vec![("KIND_A".to_string(), 0), ("KIND_B".to_string(), 1)],
+                    ..ty(Enum, "Kind", &[], &[])
+                },
+                ObjTypeInfo {
+                    anonymous: true,
+                    ..ty(Struct, "Point", &[("x", "int x", 0), ("y", "int y", 4)], &[])
+                },
+            ],
+        }
+    }
+
+    /// Definitions after the types they contain, with `Edge` and `Node`
+    /// declared up front for the types pointing to them
+    const HEADER: &str = "\
+struct Edge;
+struct Node;
+
+enum Kind {
+    KIND_A = 0,
+    KIND_B = 1,
+};
+
+typedef enum Kind Kind;
+
+union Value {
+    /* 0x0000 */ int i;
+    /* 0x0000 */ float f;
+}; // size 0x4
+
+typedef void (*Handler)(struct Node*);
+
+struct Node {
+    /* 0x0000 */ Kind kind;
+    /* 0x0004 */ union Value value;
+    /* 0x0008 */ struct Edge* edges;
+    /* 0x000c */ Handler handler;
+}; // size 0x10
+
+struct Edge {
+    /* 0x0000 */ struct Node* from;
+    /* 0x0004 */ struct Node* to;
+}; // size 0x8
+
+typedef struct {
+    /* 0x0000 */ int x;
+    /* 0x0004 */ int y;
+} Point; // size 0x8
+
+static Point origin;
+
+void walk(struct Edge* edge);
+";
+
+    const WALK: &str = "\
+struct Node;
+
+struct Edge {
+    /* 0x0000 */ struct Node* from;
+    /* 0x0004 */ struct Node* to;
+}; // size 0x8
+
+enum Kind {
+    KIND_A = 0,
+    KIND_B = 1,
+};
+
+typedef enum Kind Kind;
+
+union Value {
+    /* 0x0000 */ int i;
+    /* 0x0000 */ float f;
+}; // size 0x4
+
+typedef void (*Handler)(struct Node*);
+
+struct Node {
+    /* 0x0000 */ Kind kind;
+    /* 0x0004 */ union Value value;
+    /* 0x0008 */ struct Edge* edges;
+    /* 0x000c */ Handler handler;
+}; // size 0x10
+
+void walk(struct Edge* edge);
+";
+
+    const ORIGIN: &str = "\
+typedef struct {
+    /* 0x0000 */ int x;
+    /* 0x0004 */ int y;
+} Point; // size 0x8
+
+static Point origin;
+";
+
+    #[test]
+    fn header() {
+        assert_eq!(debug_info().header(), HEADER);
+    }
+
+    #[test]
+    fn symbol_header() {
+        let info = debug_info();
+        let walk = info.symbol_header(&symbol("walk", 0, 4), ObjSectionKind::Code).unwrap();
+        assert_eq!(walk, WALK);
+        let origin = info.symbol_header(&symbol("origin", 0, 8), ObjSectionKind::Data).unwrap();
+        assert_eq!(origin, ORIGIN);
+        assert_eq!(info.symbol_header(&symbol("missing", 0x100, 4), ObjSectionKind::Code), None);
+    }
+}
@@ -0,0 +1,216 @@
+mod dwarf1;
+#[cfg(feature = "dwarf")]
+mod dwarf2;
+mod header;
+
+use std::{borrow::Cow, collections::HashSet};
+
+use anyhow::Result;
+use object::{
+    Endianness, File, Object, ObjectSection, ObjectSymbol, RelocationKind, RelocationTarget,
+};
+
+use crate::obj::{ObjDebugInfo, ObjLineInfo, ObjTypeKind, ObjTypeRef};
+
+/// Reads line numbers, functions, globals and types from DWARF 1 and DWARF 2+
+/// sections. Errors reading anything but line numbers are logged rather than
+/// returned, as the object can be diffed without it.
+pub(super) fn read(obj_file: &File<'_>) -> Result<(Option<ObjLineInfo>, Option<ObjDebugInfo>)> {
+    let mut line_info = ObjLineInfo::default();
+    let mut debug_info = ObjDebugInfo::default();
+    dwarf1::read(obj_file, &mut line_info, &mut debug_info)?;
+    #[cfg(feature = "dwarf")]
+    dwarf2::read(obj_file, &mut line_info, &mut debug_info)?;
+    // Each compile unit describes the types it uses
+    let mut seen = HashSet::new();
+    debug_info.types.retain(|info| seen.insert((info.kind, info.name.clone())));
+    Ok((
+        (!line_info.rows.is_empty()).then_some(line_info),
+        (!debug_info.functions.is_empty()
+            || !debug_info.globals.is_empty()
+            || !debug_info.types.is_empty())
+        .then_some(debug_info),
+    ))
+}
+
+/// Reads a debug section with its relocations applied. In relocatable objects,
+/// addresses and references between entries are left for the linker to fill in.
+fn section_data<'data>(obj_file: &File<'data>, name: &str) -> Result<Option<Cow<'data, [u8]>>> {
+    let Some(section) = obj_file.section_by_name(name) else {
+        return Ok(None);
+    };
+    let mut data = section.uncompressed_data()?;
+    let big_endian = obj_file.endianness() == Endianness::Big;
+    for (offset, reloc) in section.relocations() {
+        let RelocationTarget::Symbol(symbol_index) = reloc.target() else {
+            continue;
+        };
+        let symbol = obj_file.symbol_by_index(symbol_index)?.address() as i64;
+        let field_range = offset as usize..offset as usize + reloc.size() as usize / 8;
+        let Some(field) = data.get(field_range.clone()) else {
+            continue;
+        };
+        let implicit_addend = match (reloc.has_implicit_addend(), field.len()) {
+            (false, _) => 0,
+            (true, 4) if big_endian => i32::from_be_bytes(field.try_into()?) as i64,
+            (true, 4) => i32::from_le_bytes(field.try_into()?) as i64,
+            (true, 8) if big_endian => i64::from_be_bytes(field.try_into()?),
+            (true, 8) => i64::from_le_bytes(field.try_into()?),
+            _ => continue,
+        };
+        let value = match reloc.kind() {
+            RelocationKind::Absolute => symbol + reloc.addend() + implicit_addend,
+            // Section addresses are zero, so PC-relative pointers resolve
+            // against their offset in the section
+            RelocationKind::Relative => symbol + reloc.addend() + implicit_addend - offset as i64,
+            _ => continue,
+        };
+        let field = &mut data.to_mut()[field_range];
+        match field.len() {
+            4 if big_endian => field.copy_from_slice(&(value as u32).to_be_bytes()),
+            4 => field.copy_from_slice(&(value as u32).to_le_bytes()),
+            8 if big_endian => field.copy_from_slice(&(value as u64).to_be_bytes()),
+            _ => field.copy_from_slice(&(value as u64).to_le_bytes()),
+        }
+    }
+    Ok(Some(data))
+}
+
+/// Type of a variable or function, as much as needed to write C declarations.
+#[derive(Debug, Clone)]
+pub(super) enum Type {
+    /// Fundamental or unknown type, e.g. `int`
+    Named(String),
+    /// Struct, class, union, enum or typedef, keyed as in
+    /// [`ObjDebugInfo::types`]. Anonymous types are named by their typedef.
+    User {
+        kind: ObjTypeKind,
+        name: String,
+        anonymous: bool,
+    },
+    Pointer(Box<Type>),
+    Reference(Box<Type>),
+    Const(Box<Type>),
+    Volatile(Box<Type>),
+    /// Array with its element count, if known
+    Array(Box<Type>, Option<u64>),
+    /// Function with its return type, parameter types and whether it's variadic
+    Function(Box<Type>, Vec<Type>, bool),
+    /// Pointer to a member of the named class
+    MemberPointer(Box<Type>, String),
+}
+
+impl Type {
+    fn void() -> Self { Type::Named("void".to_string()) }
+
+    fn unknown() -> Self { Type::Named("?".to_string()) }
+
+    /// Struct, class, union or enum without a name, which can't be referred to.
+    fn anonymous(kind: ObjTypeKind) -> Self {
+        Type::Named(format!("{} <anonymous>", kind.keyword()))
+    }
+
+    /// Name of a fundamental or user-defined type, e.g. `struct Foo`.
+    fn type_name(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Type::Named(name) => Some(Cow::Borrowed(name)),
+            Type::User { kind: ObjTypeKind::Typedef, name, .. }
+            | Type::User { name, anonymous: true, .. } => Some(Cow::Borrowed(name)),
+            Type::User { kind, name, .. } => Some(Cow::Owned(format!("{} {name}", kind.keyword()))),
+            _ => None,
+        }
+    }
+
+    /// Collects the user-defined types this type names. Types behind
+    /// pointers, references and function types are not used by value.
+    fn uses(&self, by_value: bool, out: &mut Vec<ObjTypeRef>) {
+        match self {
+            Type::Named(_) => {}
+            Type::User { kind, name, .. } => {
+                let used = ObjTypeRef { kind: *kind, name: name.clone(), by_value };
+                if !out.contains(&used) {
+                    out.push(used);
+                }
+            }
+            Type::Const(inner) | Type::Volatile(inner) | Type::Array(inner, _) => {
+                inner.uses(by_value, out)
+            }
+            Type::Pointer(inner) | Type::Reference(inner) | Type::MemberPointer(inner, _) => {
+                inner.uses(false, out)
+            }
+            Type::Function(ret, params, _) => {
+                for ty in std::iter::once(ret.as_ref()).chain(params) {
+                    ty.uses(false, out);
+                }
+            }
+        }
+    }
+}
+
+/// Writes a C declaration of `name` with type `ty`, e.g. `int (*name)[4]`.
+/// An empty name writes an abstract declaration, e.g. `char*`.
+pub(super) fn declaration(ty: &Type, name: &str) -> String {
+    match ty {
+        Type::Named(_) | Type::User { .. } => {
+            join_declarator(&ty.type_name().unwrap_or_default(), name)
+        }
+        Type::Const(inner) | Type::Volatile(inner) => {
+            let qualifier = if matches!(ty, Type::Const(_)) { "const" } else { "volatile" };
+            match inner.type_name() {
+                Some(type_name) => join_declarator(&format!("{qualifier} {type_name}"), name),
+                // Qualifies the pointer itself, e.g. `char* const name`
+                _ => declaration(inner, &join_declarator(qualifier, name)),
+            }
+        }
+        Type::Pointer(inner) | Type::Reference(inner) | Type::MemberPointer(inner, _) => {
+            let declarator = match ty {
+                Type::Pointer(_) => format!("*{name}"),
+                Type::Reference(_) => format!("&{name}"),
+                Type::MemberPointer(_, class) => format!("{class}::*{name}"),
+                _ => unreachable!(),
+            };
+            match inner.as_ref() {
+                Type::Array(..) | Type::Function(..) => {
+                    declaration(inner, &format!("({declarator})"))
+                }
+                _ => declaration(inner, &declarator),
+            }
+        }
+        Type::Array(inner, count) => match count {
+            Some(count) => declaration(inner, &format!("{name}[{count}]")),
+            None => declaration(inner, &format!("{name}[]")),
+        },
+        Type::Function(ret, params, variadic) => {
+            let params = params.iter().map(|param| declaration(param, "")).collect::<Vec<_>>();
+            declaration(ret, &format!("{name}({})", parameter_list(params, *variadic)))
+        }
+    }
+}
+
+/// Writes a C function declaration, e.g. `int strlen(const char* s)`, from the
+/// declarations of its parameters.
+fn function_declaration(ret: &Type, name: &str, params: Vec<String>, variadic: bool) -> String {
+    declaration(ret, &format!("{name}({})", parameter_list(params, variadic)))
+}
+
+pub(super) fn parameter_list(mut params: Vec<String>, variadic: bool) -> String {
+    if variadic {
+        params.push("...".to_string());
+    } else if params.is_empty() {
+        params.push("void".to_string());
+    }
+    params.join(", ")
+}
+
+/// Joins a type name with a declarator, attaching pointer and reference
+/// markers to the type name: `char* name` rather than `char *name`.
+fn join_declarator(type_name: &str, declarator: &str) -> String {
+    let markers = declarator.len() - declarator.trim_start_matches(['*', '&']).len();
+    let (markers, rest) = declarator.split_at(markers);
+    match (markers.is_empty(), rest.is_empty()) {
+        (true, true) => type_name.to_string(),
+        (false, true) => format!("{type_name}{markers}"),
+        (true, false) => format!("{type_name} {rest}"),
+        (false, false) => format!("{type_name}{markers} {rest}"),
+    }
+}
@@ -1,17 +1,21 @@
-use std::{borrow::Cow, collections::BTreeMap, fs, io::Cursor, path::Path};
+use std::{collections::HashMap, fs, path::Path};
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use byteorder::{BigEndian, ReadBytesExt};
 use filetime::FileTime;
 use flagset::Flags;
 use object::{
-    elf, Architecture, Endianness, File, Object, ObjectSection, ObjectSymbol, RelocationKind,
-    RelocationTarget, SectionIndex, SectionKind, Symbol, SymbolKind, SymbolScope, SymbolSection,
+    elf, read::elf::Sym, Architecture, File, Object, ObjectSection, ObjectSymbol, RelocationKind,
+    RelocationTarget, SectionIndex, SectionKind, Symbol, SymbolIndex, SymbolKind, SymbolScope,
+    SymbolSection,
 };
 
-use crate::obj::{
-    ObjArchitecture, ObjInfo, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol,
-    ObjSymbolFlagSet, ObjSymbolFlags,
+use crate::{
+    arch::{find_reloc, new_arch, objdump::ObjArchObjdump, ObjArch, PendingReloc},
+    diff::DiffObjConfig,
+    obj::{
+        demangle::Demangler, dwarf, ObjInfo, ObjJumpTable, ObjReloc, ObjSection, ObjSectionKind,
+        ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags,
+    },
 };
 
 fn to_obj_section_kind(kind: SectionKind) -> Option<ObjSectionKind> {
@@ -23,7 +27,13 @@ fn to_obj_section_kind(kind: SectionKind) -> Option<ObjSectionKind> {
     }
 }
 
-fn to_obj_symbol(obj_file: &File<'_>, symbol: &Symbol<'_, '_>, addend: i64) -> Result<ObjSymbol> {
+fn to_obj_symbol(
+    arch: &dyn ObjArch,
+    demangler: Demangler,
+    obj_file: &File<'_>,
+    symbol: &Symbol<'_, '_>,
+    addend: i64,
+) -> Result<ObjSymbol> {
     let mut name = symbol.name().context("Failed to process symbol name")?;
     if name.is_empty() {
         log::warn!("Found empty sym: {symbol:?}");
@@ -52,14 +62,9 @@ fn to_obj_symbol(obj_file: &File<'_>, symbol: &Symbol<'_, '_>, addend: i64) -> R
     } else {
         symbol.address()
     };
-    let mut demangled_name = None;
-    #[cfg(feature = "ppc")]
-    if obj_file.architecture() == Architecture::PowerPc {
-        demangled_name = cwdemangle::demangle(name, &Default::default());
-    }
     Ok(ObjSymbol {
         name: name.to_string(),
-        demangled_name,
+        demangled_name: demangler.demangle(name, arch.demangler()),
         address: symbol.address(),
         section_address,
         size: symbol.size(),
@@ -69,6 +74,9 @@ fn to_obj_symbol(obj_file: &File<'_>, symbol: &Symbol<'_, '_>, addend: i64) -> R
         diff_symbol: None,
         instructions: vec![],
         match_percent: None,
+        diff_score: None,
+        truncated: false,
+        blocks: vec![],
     })
 }
 
@@ -88,19 +96,27 @@ fn filter_sections(obj_file: &File<'_>) -> Result<Vec<ObjSection>> {
             kind,
             address: section.address(),
             size: section.size(),
+            align: section.align(),
             data: data.to_vec(),
             index: section.index().0,
             symbols: Vec::new(),
             relocations: Vec::new(),
+            jump_tables: Vec::new(),
             data_diff: vec![],
             match_percent: 0.0,
+            truncated: false,
         });
     }
     result.sort_by(|a, b| a.name.cmp(&b.name));
     Ok(result)
 }
 
-fn symbols_by_section(obj_file: &File<'_>, section: &ObjSection) -> Result<Vec<ObjSymbol>> {
+fn symbols_by_section(
+    arch: &dyn ObjArch,
+    demangler: Demangler,
+    obj_file: &File<'_>,
+    section: &ObjSection,
+) -> Result<Vec<ObjSymbol>> {
     let mut result = Vec::<ObjSymbol>::new();
     for symbol in obj_file.symbols() {
         if symbol.kind() == SymbolKind::Section {
@@ -115,7 +131,7 @@ fn symbols_by_section(obj_file: &File<'_>, section: &ObjSection) -> Result<Vec<O
                         continue;
                     }
                 }
-                result.push(to_obj_symbol(obj_file, &symbol, 0)?);
+                result.push(to_obj_symbol(arch, demangler, obj_file, &symbol, 0)?);
             }
         }
     }
@@ -133,39 +149,84 @@ fn symbols_by_section(obj_file: &File<'_>, section: &ObjSection) -> Result<Vec<O
     Ok(result)
 }
 
-fn common_symbols(obj_file: &File<'_>) -> Result<Vec<ObjSymbol>> {
+fn common_symbols(
+    arch: &dyn ObjArch,
+    demangler: Demangler,
+    obj_file: &File<'_>,
+) -> Result<Vec<ObjSymbol>> {
     obj_file
         .symbols()
         .filter(Symbol::is_common)
-        .map(|symbol| to_obj_symbol(obj_file, &symbol, 0))
+        .map(|symbol| to_obj_symbol(arch, demangler, obj_file, &symbol, 0))
         .collect::<Result<Vec<ObjSymbol>>>()
 }
 
+/// Symbols grouped by section and sorted by address, for resolving section-relative
+/// relocation targets without scanning the whole symbol table.
+struct SectionSymbols {
+    by_section: HashMap<SectionIndex, Vec<SectionSymbol>>,
+}
+
+struct SectionSymbol {
+    address: u64,
+    size: u64,
+    is_section: bool,
+    index: SymbolIndex,
+}
+
+impl SectionSymbols {
+    fn new(obj_file: &File<'_>) -> Self {
+        let mut by_section = HashMap::<SectionIndex, Vec<SectionSymbol>>::new();
+        for symbol in obj_file.symbols() {
+            let Some(section_index) = symbol.section_index() else {
+                continue;
+            };
+            by_section.entry(section_index).or_default().push(SectionSymbol {
+                address: symbol.address(),
+                size: symbol.size(),
+                is_section: symbol.kind() == SymbolKind::Section,
+                index: symbol.index(),
+            });
+        }
+        // Stable sort: ties keep symbol table order
+        for symbols in by_section.values_mut() {
+            symbols.sort_by_key(|s| s.address);
+        }
+        Self { by_section }
+    }
+
+    /// Finds the first non-section symbol at `address`, otherwise the closest
+    /// sized symbol before it. The flag is set for an exact match.
+    fn find(&self, section_index: SectionIndex, address: u64) -> Option<(SymbolIndex, bool)> {
+        let symbols = self.by_section.get(&section_index)?;
+        let start = symbols.partition_point(|s| s.address < address);
+        if let Some(exact) =
+            symbols[start..].iter().take_while(|s| s.address == address).find(|s| !s.is_section)
+        {
+            return Some((exact.index, true));
+        }
+        symbols[..start].iter().rev().find(|s| s.size != 0).map(|s| (s.index, false))
+    }
+}
+
 fn find_section_symbol(
+    arch: &dyn ObjArch,
+    demangler: Demangler,
     obj_file: &File<'_>,
+    section_symbols: &SectionSymbols,
     target: &Symbol<'_, '_>,
     address: u64,
 ) -> Result<ObjSymbol> {
     let section_index =
         target.section_index().ok_or_else(|| anyhow::Error::msg("Unknown section index"))?;
     let section = obj_file.section_by_index(section_index)?;
-    let mut closest_symbol: Option<Symbol<'_, '_>> = None;
-    for symbol in obj_file.symbols() {
-        if !matches!(symbol.section_index(), Some(idx) if idx == section_index) {
-            continue;
+    let closest_symbol = match section_symbols.find(section_index, address) {
+        Some((idx, true)) => {
+            return to_obj_symbol(arch, demangler, obj_file, &obj_file.symbol_by_index(idx)?, 0)
         }
-        if symbol.kind() == SymbolKind::Section || symbol.address() != address {
-            if symbol.address() < address
-                && symbol.size() != 0
-                && (closest_symbol.is_none()
-                    || matches!(&closest_symbol, Some(s) if s.address() <= symbol.address()))
-            {
-                closest_symbol = Some(symbol);
-            }
-            continue;
-        }
-        return to_obj_symbol(obj_file, &symbol, 0);
-    }
+        Some((idx, false)) => Some(obj_file.symbol_by_index(idx)?),
+        None => None,
+    };
     let (name, offset) = closest_symbol
         .and_then(|s| s.name().map(|n| (n, s.address())).ok())
         .or_else(|| section.name().map(|n| (n, section.address())).ok())
@@ -183,178 +244,181 @@ fn find_section_symbol(
         diff_symbol: None,
         instructions: vec![],
         match_percent: None,
+        diff_score: None,
+        truncated: false,
+        blocks: vec![],
     })
 }
 
+/// Returns whether a symbol is a MIPS small common symbol, which `object`
+/// reports in an unknown section.
+fn is_mips_scommon(obj_file: &File<'_>, idx: SymbolIndex) -> bool {
+    if obj_file.architecture() != Architecture::Mips {
+        return false;
+    }
+    let shndx = match obj_file {
+        File::Elf32(elf) => elf.symbol_by_index(idx).map(|s| s.raw_symbol().st_shndx(elf.endian())),
+        File::Elf64(elf) => elf.symbol_by_index(idx).map(|s| s.raw_symbol().st_shndx(elf.endian())),
+        _ => return false,
+    };
+    shndx.is_ok_and(|shndx| shndx == elf::SHN_MIPS_SCOMMON)
+}
+
 fn relocations_by_section(
-    arch: ObjArchitecture,
+    arch: &dyn ObjArch,
+    demangler: Demangler,
     obj_file: &File<'_>,
+    section_symbols: &SectionSymbols,
     section: &ObjSection,
 ) -> Result<Vec<ObjReloc>> {
     let obj_section = obj_file.section_by_index(SectionIndex(section.index))?;
-    let mut relocations = Vec::<ObjReloc>::new();
+    let mut pending = Vec::new();
     for (address, reloc) in obj_section.relocations() {
+        let addend = if reloc.has_implicit_addend() {
+            arch.implicit_addend(section, address, &reloc)?
+        } else {
+            reloc.addend()
+        };
+        pending.push(PendingReloc { address, reloc, addend, unpaired: false });
+    }
+    arch.pair_relocs(&mut pending);
+
+    let mut relocations = Vec::<ObjReloc>::new();
+    for PendingReloc { address, reloc, addend, unpaired } in pending {
         let symbol = match reloc.target() {
             RelocationTarget::Symbol(idx) => obj_file
                 .symbol_by_index(idx)
                 .context("Failed to locate relocation target symbol")?,
             _ => bail!("Unhandled relocation target: {:?}", reloc.target()),
         };
-        let kind = match reloc.kind() {
-            RelocationKind::Absolute => ObjRelocKind::Absolute,
-            RelocationKind::Elf(kind) => match arch {
-                #[cfg(feature = "ppc")]
-                ObjArchitecture::PowerPc => match kind {
-                    elf::R_PPC_ADDR16_LO => ObjRelocKind::PpcAddr16Lo,
-                    elf::R_PPC_ADDR16_HI => ObjRelocKind::PpcAddr16Hi,
-                    elf::R_PPC_ADDR16_HA => ObjRelocKind::PpcAddr16Ha,
-                    elf::R_PPC_REL24 => ObjRelocKind::PpcRel24,
-                    elf::R_PPC_REL14 => ObjRelocKind::PpcRel14,
-                    elf::R_PPC_EMB_SDA21 => ObjRelocKind::PpcEmbSda21,
-                    _ => bail!("Unhandled PPC relocation type: {kind}"),
-                },
-                #[cfg(feature = "mips")]
-                ObjArchitecture::Mips => match kind {
-                    elf::R_MIPS_26 => ObjRelocKind::Mips26,
-                    elf::R_MIPS_HI16 => ObjRelocKind::MipsHi16,
-                    elf::R_MIPS_LO16 => ObjRelocKind::MipsLo16,
-                    elf::R_MIPS_GOT16 => ObjRelocKind::MipsGot16,
-                    elf::R_MIPS_CALL16 => ObjRelocKind::MipsCall16,
-                    elf::R_MIPS_GPREL16 => ObjRelocKind::MipsGpRel16,
-                    elf::R_MIPS_GPREL32 => ObjRelocKind::MipsGpRel32,
-                    _ => bail!("Unhandled MIPS relocation type: {kind}"),
-                },
-            },
-            _ => bail!("Unhandled relocation type: {:?}", reloc.kind()),
-        };
+        let kind = reloc.kind();
+        ensure!(arch.reloc_name(kind).is_some(), "Unhandled relocation type: {kind:?}");
         let target_section = match symbol.section() {
             SymbolSection::Common => Some(".comm".to_string()),
             SymbolSection::Section(idx) => {
                 obj_file.section_by_index(idx).and_then(|s| s.name().map(|s| s.to_string())).ok()
             }
-            _ => None,
-        };
-        let addend = if reloc.has_implicit_addend() {
-            let addend = u32::from_be_bytes(
-                section.data[address as usize..address as usize + 4].try_into()?,
-            );
-            match kind {
-                ObjRelocKind::Absolute => addend as i64,
-                #[cfg(feature = "mips")]
-                ObjRelocKind::MipsHi16 => ((addend & 0x0000FFFF) << 16) as i32 as i64,
-                #[cfg(feature = "mips")]
-                ObjRelocKind::MipsLo16
-                | ObjRelocKind::MipsGot16
-                | ObjRelocKind::MipsCall16
-                | ObjRelocKind::MipsGpRel16 => (addend & 0x0000FFFF) as i16 as i64,
-                #[cfg(feature = "mips")]
-                ObjRelocKind::MipsGpRel32 => addend as i32 as i64,
-                #[cfg(feature = "mips")]
-                ObjRelocKind::Mips26 => ((addend & 0x03FFFFFF) << 2) as i64,
-                _ => bail!("Unsupported implicit relocation {kind:?}"),
+            SymbolSection::Unknown if is_mips_scommon(obj_file, symbol.index()) => {
+                Some(".scommon".to_string())
             }
-        } else {
-            reloc.addend()
+            _ => None,
         };
         // println!("Reloc: {reloc:?}, symbol: {symbol:?}, addend: {addend:#X}");
         let target = match symbol.kind() {
             SymbolKind::Text | SymbolKind::Data | SymbolKind::Label | SymbolKind::Unknown => {
-                to_obj_symbol(obj_file, &symbol, addend)
+                to_obj_symbol(arch, demangler, obj_file, &symbol, addend)
             }
             SymbolKind::Section => {
                 ensure!(addend >= 0, "Negative addend in reloc: {addend}");
-                find_section_symbol(obj_file, &symbol, addend as u64)
+                find_section_symbol(
+                    arch,
+                    demangler,
+                    obj_file,
+                    section_symbols,
+                    &symbol,
+                    addend as u64,
+                )
             }
             kind => Err(anyhow!("Unhandled relocation symbol type {kind:?}")),
         }?;
-        relocations.push(ObjReloc { kind, address, target, target_section });
+        let sda_base = arch.sda_base(kind, target_section.as_deref());
+        relocations.push(ObjReloc { kind, address, target, target_section, unpaired, sda_base });
     }
+    // Sorted for binary search in ObjArch::process_code
+    relocations.sort_by_key(|r| r.address);
     Ok(relocations)
 }
 
-fn line_info(obj_file: &File<'_>) -> Result<Option<BTreeMap<u64, u64>>> {
-    // DWARF 1.1
-    let mut map = BTreeMap::new();
-    if let Some(section) = obj_file.section_by_name(".line") {
-        if section.size() == 0 {
-            return Ok(None);
-        }
-        let data = section.uncompressed_data()?;
-        let mut reader = Cursor::new(data.as_ref());
-
-        let size = reader.read_u32::<BigEndian>()?;
-        let base_address = reader.read_u32::<BigEndian>()? as u64;
-        while reader.position() < size as u64 {
-            let line_number = reader.read_u32::<BigEndian>()? as u64;
-            let statement_pos = reader.read_u16::<BigEndian>()?;
-            if statement_pos != 0xFFFF {
-                log::warn!("Unhandled statement pos {}", statement_pos);
-            }
-            let address_delta = reader.read_u32::<BigEndian>()? as u64;
-            map.insert(base_address + address_delta, line_number);
-        }
-    }
-
-    // DWARF 2+
-    #[cfg(feature = "dwarf")]
-    {
-        let dwarf_cow = gimli::Dwarf::load(|id| {
-            Ok::<_, gimli::Error>(
-                obj_file
-                    .section_by_name(id.name())
-                    .and_then(|section| section.uncompressed_data().ok())
-                    .unwrap_or(Cow::Borrowed(&[][..])),
-            )
-        })?;
-        let endian = match obj_file.endianness() {
-            Endianness::Little => gimli::RunTimeEndian::Little,
-            Endianness::Big => gimli::RunTimeEndian::Big,
-        };
-        let dwarf = dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, endian));
-        let mut iter = dwarf.units();
-        while let Some(header) = iter.next()? {
-            let unit = dwarf.unit(header)?;
-            if let Some(program) = unit.line_program.clone() {
-                let mut rows = program.rows();
-                while let Some((_header, row)) = rows.next_row()? {
-                    if let Some(line) = row.line() {
-                        map.insert(row.address(), line.get());
-                    }
+/// Finds tables of 32-bit code addresses referenced by the functions of a code
+/// section, where every entry is relocated to an address within the function.
+fn jump_tables_by_section(sections: &[ObjSection], section: &ObjSection) -> Vec<ObjJumpTable> {
+    let mut tables = Vec::<ObjJumpTable>::new();
+    for symbol in &section.symbols {
+        let range = symbol.address..symbol.address + symbol.size;
+        let first = section.relocations.partition_point(|r| r.address < range.start);
+        for reloc in section.relocations[first..].iter().take_while(|r| r.address < range.end) {
+            let Some(table_section) = sections.iter().find(|s| {
+                s.kind == ObjSectionKind::Data && reloc.target_section.as_ref() == Some(&s.name)
+            }) else {
+                continue;
+            };
+            let mut address = (reloc.target.address as i64 + reloc.target.addend) as u64;
+            let end = if reloc.target.size_known {
+                reloc.target.address + reloc.target.size
+            } else {
+                u64::MAX
+            };
+            let mut targets = Vec::new();
+            while address < end {
+                let Some(entry) = find_reloc(&table_section.relocations, address, address + 1)
+                    .filter(|r| {
+                        r.kind == RelocationKind::Absolute
+                            && r.target_section.as_ref() == Some(&section.name)
+                    })
+                else {
+                    break;
+                };
+                let target = (entry.target.address as i64 + entry.target.addend) as u64;
+                if !range.contains(&target) {
+                    break;
                 }
+                targets.push(target);
+                address += 4;
+            }
+            if !targets.is_empty() {
+                tables.push(ObjJumpTable {
+                    load_address: reloc.address,
+                    name: reloc.target.name.clone(),
+                    targets,
+                });
             }
         }
     }
-    if map.is_empty() {
-        return Ok(None);
-    }
-    Ok(Some(map))
+    tables.sort_by_key(|t| t.load_address);
+    // Symbols sharing an address reference the same tables
+    tables.dedup_by_key(|t| t.load_address);
+    tables
 }
 
-pub fn read(obj_path: &Path) -> Result<ObjInfo> {
+pub fn read(obj_path: &Path, config: &DiffObjConfig) -> Result<ObjInfo> {
     let (data, timestamp) = {
         let file = fs::File::open(obj_path)?;
         let timestamp = FileTime::from_last_modification_time(&file.metadata()?);
         (unsafe { memmap2::Mmap::map(&file) }?, timestamp)
     };
     let obj_file = File::parse(&*data)?;
-    let architecture = match obj_file.architecture() {
-        #[cfg(feature = "ppc")]
-        Architecture::PowerPc => ObjArchitecture::PowerPc,
-        #[cfg(feature = "mips")]
-        Architecture::Mips => ObjArchitecture::Mips,
-        _ => bail!("Unsupported architecture: {:?}", obj_file.architecture()),
+    let arch = match (new_arch(&obj_file), &config.objdump) {
+        (Some(arch), _) => arch?,
+        (None, Some(objdump)) => Box::new(ObjArchObjdump::new(obj_path, &obj_file, objdump)?),
+        (None, None) => bail!("Unsupported architecture: {:?}", obj_file.architecture()),
     };
+    let (line_info, debug_info) = dwarf::read(&obj_file)?;
     let mut result = ObjInfo {
-        architecture,
         path: obj_path.to_owned(),
         timestamp,
         sections: filter_sections(&obj_file)?,
-        common: common_symbols(&obj_file)?,
-        line_info: line_info(&obj_file)?,
+        common: common_symbols(arch.as_ref(), config.demangler, &obj_file)?,
+        line_info,
+        debug_info,
+        arch: arch.into(),
     };
+    let section_symbols = SectionSymbols::new(&obj_file);
     for section in &mut result.sections {
-        section.symbols = symbols_by_section(&obj_file, section)?;
-        section.relocations = relocations_by_section(architecture, &obj_file, section)?;
+        section.symbols =
+            symbols_by_section(result.arch.as_ref(), config.demangler, &obj_file, section)?;
+        section.relocations = relocations_by_section(
+            result.arch.as_ref(),
+            config.demangler,
+            &obj_file,
+            &section_symbols,
+            section,
+        )?;
+    }
+    for idx in 0..result.sections.len() {
+        if result.sections[idx].kind == ObjSectionKind::Code {
+            result.sections[idx].jump_tables =
+                jump_tables_by_section(&result.sections, &result.sections[idx]);
+        }
     }
     Ok(result)
 }
@@ -1,15 +1,14 @@
+pub mod demangle;
+mod dwarf;
 pub mod elf;
-#[cfg(feature = "mips")]
-pub mod mips;
-#[cfg(feature = "ppc")]
-pub mod ppc;
 
-use std::{collections::BTreeMap, fmt, path::PathBuf};
+use std::{collections::BTreeMap, fmt, ops::Range, path::PathBuf, sync::Arc};
 
 use filetime::FileTime;
 use flagset::{flags, FlagSet};
+use object::RelocationKind;
 
-use crate::util::ReallySigned;
+use crate::{arch::ObjArch, util::ReallySigned};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum ObjSectionKind {
@@ -35,14 +34,20 @@ pub struct ObjSection {
     pub kind: ObjSectionKind,
     pub address: u64,
     pub size: u64,
+    /// Alignment of the section, and the largest alignment of its symbols
+    pub align: u64,
     pub data: Vec<u8>,
     pub index: usize,
     pub symbols: Vec<ObjSymbol>,
     pub relocations: Vec<ObjReloc>,
+    /// Jump tables of functions in this section, sorted by load address
+    pub jump_tables: Vec<ObjJumpTable>,
 
     // Diff
     pub data_diff: Vec<ObjDataDiff>,
     pub match_percent: f32,
+    /// Diff exceeded the deadline and may be incomplete
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -50,6 +55,8 @@ pub enum ObjInsArgValue {
     Signed(i16),
     Unsigned(u16),
     Opaque(String),
+    /// Register, e.g. `r3`, `$a0` or `%eax`
+    Register(String),
 }
 
 impl ObjInsArgValue {
@@ -59,10 +66,35 @@ impl ObjInsArgValue {
             (ObjInsArgValue::Unsigned(a), ObjInsArgValue::Unsigned(b)) => a == b,
             (ObjInsArgValue::Signed(a), ObjInsArgValue::Unsigned(b))
             | (ObjInsArgValue::Unsigned(b), ObjInsArgValue::Signed(a)) => *a as u16 == *b,
-            (ObjInsArgValue::Opaque(a), ObjInsArgValue::Opaque(b)) => a == b,
+            (ObjInsArgValue::Opaque(a), ObjInsArgValue::Opaque(b))
+            | (ObjInsArgValue::Register(a), ObjInsArgValue::Register(b)) => a == b,
             _ => false,
         }
     }
+
+    /// Returns the integer value of the argument, parsing opaque arguments
+    /// such as `0x18` or `-8`.
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            ObjInsArgValue::Signed(v) => Some(*v as i64),
+            ObjInsArgValue::Unsigned(v) => Some(*v as i64),
+            ObjInsArgValue::Opaque(v) => parse_int(v),
+            ObjInsArgValue::Register(_) => None,
+        }
+    }
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal integer, optionally negative.
+pub(crate) fn parse_int(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let value = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => s.parse::<i64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
 }
 
 impl fmt::Display for ObjInsArgValue {
@@ -70,7 +102,7 @@ impl fmt::Display for ObjInsArgValue {
         match self {
             ObjInsArgValue::Signed(v) => write!(f, "{:#x}", ReallySigned(*v)),
             ObjInsArgValue::Unsigned(v) => write!(f, "{:#x}", v),
-            ObjInsArgValue::Opaque(v) => write!(f, "{}", v),
+            ObjInsArgValue::Opaque(v) | ObjInsArgValue::Register(v) => write!(f, "{}", v),
         }
     }
 }
@@ -119,6 +151,25 @@ pub struct ObjInsBranchTo {
     pub branch_idx: usize,
 }
 
+#[derive(Debug, Clone)]
+pub struct ObjInsMove {
+    /// Instruction index of the counterpart on the other side
+    pub ins_idx: usize,
+    /// Incrementing index for coloring
+    pub move_idx: usize,
+}
+
+/// Jump table dispatched by an indirect branch.
+#[derive(Debug, Clone)]
+pub struct ObjInsJumpTable {
+    /// Name of the symbol containing the table
+    pub name: String,
+    /// Target address of each entry
+    pub targets: Vec<u32>,
+    /// Branch to each entry's target instruction
+    pub branches: Vec<Option<ObjInsBranchTo>>,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
 pub enum ObjInsDiffKind {
     #[default]
@@ -128,6 +179,26 @@ pub enum ObjInsDiffKind {
     Replace,
     Delete,
     Insert,
+    /// Identical instruction at a different position on the other side
+    Moved,
+    /// Differing instruction matched by an equivalence rule
+    Equivalent,
+}
+
+impl ObjInsDiffKind {
+    /// Whether the row counts as matching.
+    pub fn is_match(self) -> bool {
+        matches!(self, ObjInsDiffKind::None | ObjInsDiffKind::Equivalent)
+    }
+}
+
+/// Branch delay slot an instruction occupies.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ObjDelaySlot {
+    /// Always executed
+    Always,
+    /// Executed only when a branch-likely instruction is taken
+    Likely,
 }
 
 #[derive(Debug, Clone)]
@@ -143,6 +214,8 @@ pub struct ObjIns {
     pub line: Option<u64>,
     /// Original (unsimplified) instruction
     pub orig: Option<String>,
+    /// Delay slot of the preceding branch
+    pub delay_slot: Option<ObjDelaySlot>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -156,6 +229,23 @@ pub struct ObjInsDiff {
     pub branch_to: Option<ObjInsBranchTo>,
     /// Arg diffs
     pub arg_diff: Vec<Option<ObjInsArgDiff>>,
+    /// Counterpart of a moved instruction
+    pub moved: Option<ObjInsMove>,
+    /// Jump table dispatched by the instruction
+    pub jump_table: Option<ObjInsJumpTable>,
+    /// Trailing alignment padding, excluded from the match percentage
+    pub padding: bool,
+}
+
+/// Basic block of a diffed function.
+#[derive(Debug, Clone)]
+pub struct ObjBlockDiff {
+    /// Rows of [`ObjSymbol::instructions`] covered by the block
+    pub rows: Range<usize>,
+    /// Whether the block was matched with a block on the other side
+    pub matched: bool,
+    /// Percentage of identical rows within the block
+    pub match_percent: f32,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
@@ -175,7 +265,7 @@ pub struct ObjDataDiff {
     pub symbol: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ObjSymbol {
     pub name: String,
     pub demangled_name: Option<String>,
@@ -190,69 +280,364 @@ pub struct ObjSymbol {
     pub diff_symbol: Option<String>,
     pub instructions: Vec<ObjInsDiff>,
     pub match_percent: Option<f32>,
+    /// Weighted difference score, lower is better
+    pub diff_score: Option<u64>,
+    /// Diff exceeded the deadline and may be incomplete
+    pub truncated: bool,
+    /// Basic blocks, when diffed with block matching enabled
+    pub blocks: Vec<ObjBlockDiff>,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum ObjArchitecture {
-    #[cfg(feature = "ppc")]
-    PowerPc,
-    #[cfg(feature = "mips")]
-    Mips,
-}
-
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ObjInfo {
-    pub architecture: ObjArchitecture,
+    pub arch: Arc<dyn ObjArch>,
     pub path: PathBuf,
     pub timestamp: FileTime,
     pub sections: Vec<ObjSection>,
     pub common: Vec<ObjSymbol>,
-    pub line_info: Option<BTreeMap<u64, u64>>,
+    pub line_info: Option<ObjLineInfo>,
+    pub debug_info: Option<ObjDebugInfo>,
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub enum ObjRelocKind {
-    Absolute,
-    #[cfg(feature = "ppc")]
-    PpcAddr16Hi,
-    #[cfg(feature = "ppc")]
-    PpcAddr16Ha,
-    #[cfg(feature = "ppc")]
-    PpcAddr16Lo,
-    // #[cfg(feature = "ppc")]
-    // PpcAddr32,
-    // #[cfg(feature = "ppc")]
-    // PpcRel32,
-    // #[cfg(feature = "ppc")]
-    // PpcAddr24,
-    #[cfg(feature = "ppc")]
-    PpcRel24,
-    // #[cfg(feature = "ppc")]
-    // PpcAddr14,
-    #[cfg(feature = "ppc")]
-    PpcRel14,
-    #[cfg(feature = "ppc")]
-    PpcEmbSda21,
-    #[cfg(feature = "mips")]
-    Mips26,
-    #[cfg(feature = "mips")]
-    MipsHi16,
-    #[cfg(feature = "mips")]
-    MipsLo16,
-    #[cfg(feature = "mips")]
-    MipsGot16,
-    #[cfg(feature = "mips")]
-    MipsCall16,
-    #[cfg(feature = "mips")]
-    MipsGpRel16,
-    #[cfg(feature = "mips")]
-    MipsGpRel32,
+/// Source position of an address.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ObjSourceLine {
+    /// Index into [`ObjLineInfo::files`]
+    pub file: usize,
+    pub line: u64,
+}
+
+/// Line number information from DWARF 1 `.line` or DWARF 2+ line programs.
+#[derive(Debug, Clone, Default)]
+pub struct ObjLineInfo {
+    /// Source file paths as recorded by the compiler
+    pub files: Vec<String>,
+    /// Source position of the address range starting at each key
+    pub rows: BTreeMap<u64, ObjSourceLine>,
+}
+
+impl ObjLineInfo {
+    pub fn location(&self, address: u64) -> Option<ObjSourceLine> {
+        self.rows.range(..=address).next_back().map(|(_, &location)| location)
+    }
+
+    pub fn line(&self, address: u64) -> Option<u64> {
+        self.location(address).map(|location| location.line)
+    }
+
+    /// Returns the index of a file path, adding it if new.
+    pub(crate) fn file_index(&mut self, path: &str) -> usize {
+        match self.files.iter().position(|file| file == path) {
+            Some(index) => index,
+            None => {
+                self.files.push(path.to_string());
+                self.files.len() - 1
+            }
+        }
+    }
+}
+
+/// Functions, global variables and types described by DWARF 1 or DWARF 2+
+/// debug information.
+#[derive(Debug, Clone, Default)]
+pub struct ObjDebugInfo {
+    pub functions: Vec<ObjFunctionInfo>,
+    pub globals: Vec<ObjGlobalInfo>,
+    /// Named types, once each
+    pub types: Vec<ObjTypeInfo>,
+}
+
+impl ObjDebugInfo {
+    /// Finds the debug information of a function symbol, by linkage name or
+    /// name, falling back to its address for DWARF without linkage names.
+    pub fn function(&self, symbol: &ObjSymbol) -> Option<&ObjFunctionInfo> {
+        let by_name = |f: &&ObjFunctionInfo| names_symbol(symbol, &f.name, &f.linkage_name);
+        let at_symbol = |f: &&ObjFunctionInfo| f.address == symbol.address;
+        self.functions
+            .iter()
+            .find(|f| at_symbol(f) && by_name(f))
+            .or_else(|| self.functions.iter().find(by_name))
+            .or_else(|| self.functions.iter().find(at_symbol))
+    }
+
+    /// Finds the debug information of a data symbol by linkage name or name.
+    /// Unlike functions, data symbols share addresses with their sections, so
+    /// the address only decides between globals of the same name.
+    pub fn global(&self, symbol: &ObjSymbol) -> Option<&ObjGlobalInfo> {
+        let by_name = |g: &&ObjGlobalInfo| names_symbol(symbol, &g.name, &g.linkage_name);
+        self.globals
+            .iter()
+            .find(|g| g.address == Some(symbol.address) && by_name(g))
+            .or_else(|| self.globals.iter().find(by_name))
+    }
+}
+
+/// Whether a debug information entry names a symbol, by linkage name or
+/// by its name within the demangled symbol name.
+fn names_symbol(symbol: &ObjSymbol, name: &str, linkage_name: &Option<String>) -> bool {
+    // Demangled name without parameters, e.g. `Class::method`
+    let demangled =
+        symbol.demangled_name.as_deref().and_then(|name| name.split('(').next()).unwrap_or("");
+    linkage_name.as_deref().unwrap_or(name) == symbol.name
+        || demangled == name
+        || demangled.strip_suffix(name).is_some_and(|s| s.ends_with("::"))
+}
+
+/// Debug information for a function.
+#[derive(Debug, Clone)]
+pub struct ObjFunctionInfo {
+    pub name: String,
+    /// Mangled name, when it differs from the name
+    pub linkage_name: Option<String>,
+    pub address: u64,
+    pub size: u64,
+    /// Whether the function is visible outside its compile unit
+    pub external: bool,
+    /// C declaration, e.g. `int strlen(const char* s)`
+    pub prototype: String,
+    /// Types named by the prototype
+    pub uses: Vec<ObjTypeRef>,
+    pub params: Vec<ObjVariable>,
+    /// Local variables, including those of nested scopes and inlined calls
+    pub locals: Vec<ObjVariable>,
+    /// Inlined calls, outermost first
+    pub inlined: Vec<ObjInlinedCall>,
+}
+
+impl ObjFunctionInfo {
+    /// Returns the parameters and local variables held at an address, with
+    /// where they're held.
+    pub fn variables_at(&self, address: u64) -> impl Iterator<Item = (&ObjVariable, ObjLocation)> {
+        self.params.iter().chain(&self.locals).flat_map(move |var| {
+            var.locations
+                .iter()
+                .filter(move |loc| loc.range.contains(&address))
+                .map(move |loc| (var, loc.location))
+        })
+    }
+
+    /// Returns the variables held in an instruction operand: a register, or a
+    /// memory operand such as `0x18(r1)` when hovering either half of it.
+    pub fn operand_variables(
+        &self,
+        arch: &dyn ObjArch,
+        ins: &ObjIns,
+        arg_idx: usize,
+    ) -> Vec<(&ObjVariable, ObjLocation)> {
+        fn register(arg: Option<&ObjInsArg>) -> Option<&str> {
+            match arg {
+                Some(ObjInsArg::Arg(ObjInsArgValue::Register(name))) => Some(name.as_str()),
+                _ => None,
+            }
+        }
+        let offset = |arg: Option<&ObjInsArg>| match arg {
+            Some(ObjInsArg::ArgWithBase(value)) => value.to_i64(),
+            _ => None,
+        };
+        let args = &ins.args;
+        let memory = match args.get(arg_idx) {
+            Some(ObjInsArg::ArgWithBase(_)) => {
+                offset(args.get(arg_idx)).zip(register(args.get(arg_idx + 1)))
+            }
+            Some(ObjInsArg::Arg(_)) if arg_idx > 0 => {
+                offset(args.get(arg_idx - 1)).zip(register(args.get(arg_idx)))
+            }
+            _ => None,
+        };
+        let name_is =
+            |reg: u16, name: &str| arch.dwarf_register_name(reg).is_some_and(|r| r == name);
+        self.variables_at(ins.address as u64)
+            .filter(|(_, location)| match (*location, memory) {
+                (ObjLocation::Memory { register, offset }, Some((off, base))) => {
+                    offset == off && name_is(register, base)
+                }
+                (ObjLocation::Register(reg), None) => {
+                    register(args.get(arg_idx)).is_some_and(|name| name_is(reg, name))
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Returns the inlined calls containing an address, outermost first.
+    pub fn inlined_at(&self, address: u64) -> impl Iterator<Item = &ObjInlinedCall> {
+        self.inlined
+            .iter()
+            .filter(move |call| call.ranges.iter().any(|range| range.contains(&address)))
+    }
+}
+
+/// Parameter or local variable of a function.
+#[derive(Debug, Clone)]
+pub struct ObjVariable {
+    pub name: String,
+    /// C declaration, e.g. `char* s`
+    pub declaration: String,
+    /// Name of the inlined function the variable belongs to
+    pub inlined_from: Option<String>,
+    /// Where the variable is held; empty if optimized out
+    pub locations: Vec<ObjVarLocation>,
+}
+
+/// Where a variable is held within a range of addresses.
+#[derive(Debug, Clone)]
+pub struct ObjVarLocation {
+    pub range: Range<u64>,
+    pub location: ObjLocation,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ObjLocation {
+    /// Held in a register, by DWARF register number
+    Register(u16),
+    /// Stored at an offset from a register, by DWARF register number
+    Memory { register: u16, offset: i64 },
+    /// Stored at a fixed address
+    Address(u64),
+}
+
+/// Global or file-local variable.
+#[derive(Debug, Clone)]
+pub struct ObjGlobalInfo {
+    pub name: String,
+    /// Mangled name, when it differs from the name
+    pub linkage_name: Option<String>,
+    /// C declaration, e.g. `int counts[4]`
+    pub declaration: String,
+    /// Types named by the declaration
+    pub uses: Vec<ObjTypeRef>,
+    pub address: Option<u64>,
+    /// Whether the variable is visible outside its compile unit
+    pub external: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ObjTypeKind {
+    Struct,
+    Class,
+    Union,
+    Enum,
+    Typedef,
+}
+
+impl ObjTypeKind {
+    /// C keyword introducing the type.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ObjTypeKind::Struct => "struct",
+            ObjTypeKind::Class => "class",
+            ObjTypeKind::Union => "union",
+            ObjTypeKind::Enum => "enum",
+            ObjTypeKind::Typedef => "typedef",
+        }
+    }
+}
+
+/// User-defined type.
+#[derive(Debug, Clone)]
+pub struct ObjTypeInfo {
+    pub kind: ObjTypeKind,
+    /// Name of the type, or of the typedef naming it if anonymous
+    pub name: String,
+    /// Whether the type is anonymous and named by a typedef, as in
+    /// `typedef struct { ... } Name;`
+    pub anonymous: bool,
+    pub size: Option<u64>,
+    /// Names of base classes
+    pub bases: Vec<String>,
+    /// Data members of a struct, class or union
+    pub members: Vec<ObjMember>,
+    /// Constants of an enum, in declaration order
+    pub enumerators: Vec<(String, i64)>,
+    /// Declaration of a typedef's name as the aliased type, e.g. `int (*Callback)(void)`
+    pub typedef: Option<String>,
+    /// Types named by the typedef, bases and members
+    pub uses: Vec<ObjTypeRef>,
+}
+
+/// Use of a user-defined type by a declaration, identifying the type by its
+/// kind and name as in [`ObjDebugInfo::types`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ObjTypeRef {
+    pub kind: ObjTypeKind,
+    /// Name of the type, or of the typedef naming it if anonymous
+    pub name: String,
+    /// Whether the type is used by value, needing its definition, rather
+    /// than through a pointer or reference
+    pub by_value: bool,
+}
+
+/// Data member of a struct, class or union.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ObjMember {
+    pub name: String,
+    /// C declaration, e.g. `char* name`
+    pub declaration: String,
+    /// Offset from the start of the type, if known
+    pub offset: Option<u64>,
+    /// Width of a bit field
+    pub bit_size: Option<u64>,
+    /// Position of a bit field within its storage unit, as recorded by the compiler
+    pub bit_offset: Option<u64>,
+}
+
+/// Function inlined into another.
+#[derive(Debug, Clone)]
+pub struct ObjInlinedCall {
+    pub name: String,
+    pub ranges: Vec<Range<u64>>,
+    /// Source position of the call, if recorded
+    pub call_file: Option<String>,
+    pub call_line: Option<u64>,
+    /// Number of inlined calls containing this one
+    pub depth: usize,
+}
+
+/// Table of code addresses in a data section, loaded by a function and
+/// dispatched through an indirect branch.
+#[derive(Debug, Clone)]
+pub struct ObjJumpTable {
+    /// Address of the instruction referencing the table
+    pub load_address: u64,
+    /// Name of the symbol containing the table
+    pub name: String,
+    /// Target address of each entry
+    pub targets: Vec<u64>,
+}
+
+/// Base register a GP- or SDA-relative reference is relative to, which
+/// depends on the small data area of the target.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ObjSdaBase {
+    /// MIPS `$gp`
+    Gp,
+    /// PowerPC `r13`, for .sdata and .sbss
+    Sda,
+    /// PowerPC `r2`, for .sdata2 and .sbss2
+    Sda2,
+    /// PowerPC `r0`, for .PPC.EMB.sdata0 and .PPC.EMB.sbss0
+    Sda0,
+}
+
+impl ObjSdaBase {
+    pub fn register(&self) -> &'static str {
+        match self {
+            ObjSdaBase::Gp => "$gp",
+            ObjSdaBase::Sda => "r13",
+            ObjSdaBase::Sda2 => "r2",
+            ObjSdaBase::Sda0 => "r0",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ObjReloc {
-    pub kind: ObjRelocKind,
+    pub kind: RelocationKind,
     pub address: u64,
     pub target: ObjSymbol,
     pub target_section: Option<String>,
+    /// Half of a relocation pair (e.g. MIPS `%hi`/`%lo`) without its counterpart
+    pub unpaired: bool,
+    /// Base register of a GP- or SDA-relative relocation
+    pub sda_base: Option<ObjSdaBase>,
 }
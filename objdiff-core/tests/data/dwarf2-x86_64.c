@@ -0,0 +1,19 @@
+struct Vec {
+    float x, y;
+};
+
+typedef unsigned int u32;
+
+u32 gCount;
+static int sTable[4] = {1, 2, 3, 4};
+
+static inline float dot(const struct Vec* a, const struct Vec* b) { return a->x * b->x + a->y * b->y; }
+
+float length_sq(const struct Vec* v, int n) {
+    float sum = 0.0f;
+    for (int i = 0; i < n; i++) {
+        sum += dot(&v[i], &v[i]) * sTable[i & 3];
+    }
+    gCount++;
+    return sum;
+}
@@ -0,0 +1,11 @@
+extern int counter;
+extern int table[];
+extern void callee(int);
+int sum(int n) {
+    int total = 0;
+    for (int i = 0; i < n; i++) total += table[i];
+    callee(total);
+    return total + counter;
+}
+long wide(void) { return 0x123456789abcdefL; }
+void store(void) { table[2] = 0x12345678; }
@@ -12,7 +12,11 @@ use std::{
 use filetime::FileTime;
 use globset::{Glob, GlobSet};
 use notify::{RecursiveMode, Watcher};
-use objdiff_core::diff::DiffAlg;
+use objdiff_core::{
+    arch::objdump::ObjdumpConfig,
+    diff::{equiv::EquivalenceRule, DiffAlg, RelocDiffOptions, ScoreWeights},
+    obj::demangle::Demangler,
+};
 use time::UtcOffset;
 
 use crate::{
@@ -30,6 +34,7 @@ use crate::{
         },
         data_diff::data_diff_ui,
         debug::debug_window,
+        debug_info_diff::debug_info_diff_ui,
         demangle::{demangle_window, DemangleViewState},
         frame_history::FrameHistory,
         function_diff::function_diff_ui,
@@ -61,6 +66,8 @@ pub struct ObjectConfig {
     pub reverse_fn_order: Option<bool>,
     pub complete: Option<bool>,
     pub scratch: Option<ScratchConfig>,
+    /// Overrides [`AppConfig::reloc_diffs`] for this unit
+    pub reloc_diffs: Option<RelocDiffOptions>,
 }
 
 #[derive(Clone, Eq, PartialEq)]
@@ -72,6 +79,9 @@ pub struct ProjectConfigInfo {
 #[inline]
 fn bool_true() -> bool { true }
 
+#[inline]
+fn default_diff_time_limit() -> u64 { 5 }
+
 #[inline]
 fn default_watch_patterns() -> Vec<Glob> {
     DEFAULT_WATCH_PATTERNS.iter().map(|s| Glob::new(s).unwrap()).collect()
@@ -113,12 +123,29 @@ pub struct AppConfig {
     pub data_alg: DiffAlg,
     #[serde(default)]
     pub relax_reloc_diffs: bool,
+    #[serde(default)]
+    pub reloc_diffs: RelocDiffOptions,
+    #[serde(default)]
+    pub block_diff: bool,
+    #[serde(default)]
+    pub group_delay_slots: bool,
+    #[serde(default)]
+    pub score_weights: ScoreWeights,
+    #[serde(default)]
+    pub demangler: Demangler,
+    /// Time limit in seconds for diffing a single symbol, or 0 for none
+    #[serde(default = "default_diff_time_limit")]
+    pub diff_time_limit: u64,
 
     #[serde(skip)]
     pub objects: Vec<ProjectObject>,
     #[serde(skip)]
     pub object_nodes: Vec<ProjectObjectNode>,
     #[serde(skip)]
+    pub objdump: Option<ObjdumpConfig>,
+    #[serde(skip)]
+    pub equivalences: Vec<EquivalenceRule>,
+    #[serde(skip)]
     pub watcher_change: bool,
     #[serde(skip)]
     pub config_change: bool,
@@ -132,6 +159,9 @@ pub struct AppConfig {
     pub queue_scratch: bool,
     #[serde(skip)]
     pub project_config_info: Option<ProjectConfigInfo>,
+    /// Whether [`AppConfig::reloc_diffs`] is set by the project configuration
+    #[serde(skip)]
+    pub project_reloc_diffs: bool,
 }
 
 impl Default for AppConfig {
@@ -153,8 +183,16 @@ impl Default for AppConfig {
             code_alg: Default::default(),
             data_alg: Default::default(),
             relax_reloc_diffs: false,
+            reloc_diffs: Default::default(),
+            block_diff: false,
+            group_delay_slots: false,
+            score_weights: Default::default(),
+            demangler: Default::default(),
+            diff_time_limit: default_diff_time_limit(),
             objects: vec![],
             object_nodes: vec![],
+            objdump: None,
+            equivalences: vec![],
             watcher_change: false,
             config_change: false,
             obj_change: false,
@@ -162,6 +200,7 @@ impl Default for AppConfig {
             queue_reload: false,
             queue_scratch: false,
             project_config_info: None,
+            project_reloc_diffs: false,
         }
     }
 }
@@ -185,6 +224,7 @@ impl AppConfig {
         self.obj_change = true;
         self.queue_build = false;
         self.project_config_info = None;
+        self.project_reloc_diffs = false;
     }
 
     pub fn set_target_obj_dir(&mut self, path: PathBuf) {
@@ -387,11 +427,14 @@ impl App {
         // Don't clear `queue_build` if a build is running. A file may have been modified during
         // the build, so we'll start another build after the current one finishes.
         if config.queue_build && config.selected_obj.is_some() && !jobs.is_running(Job::ObjDiff) {
-            jobs.push(start_build(ctx, ObjDiffConfig::from_config(config)));
+            jobs.push(start_build(
+                ctx,
+                ObjDiffConfig::from_config(config, diff_state.diff_cache.clone()),
+            ));
             config.queue_build = false;
             config.queue_reload = false;
         } else if config.queue_reload && !jobs.is_running(Job::ObjDiff) {
-            let mut diff_config = ObjDiffConfig::from_config(config);
+            let mut diff_config = ObjDiffConfig::from_config(config, diff_state.diff_cache.clone());
             // Don't build, just reload the current files
             diff_config.build_base = false;
             diff_config.build_target = false;
@@ -499,6 +542,11 @@ impl eframe::App for App {
                         &mut diff_state.symbol_state.show_hidden_symbols,
                         "Show hidden symbols",
                     );
+                    ui.checkbox(
+                        &mut diff_state.symbol_state.sort_by_score,
+                        "Sort functions by score",
+                    )
+                    .on_hover_text("Lists the closest non-matching functions first.");
                     if ui
                         .checkbox(&mut config.relax_reloc_diffs, "Relax relocation diffs")
                         .on_hover_text(
@@ -508,6 +556,25 @@ impl eframe::App for App {
                     {
                         config.queue_reload = true;
                     }
+                    if ui
+                        .checkbox(&mut config.block_diff, "Match basic blocks")
+                        .on_hover_text(
+                            "Keeps an extra basic block from misaligning the rest of the function.",
+                        )
+                        .changed()
+                    {
+                        config.queue_reload = true;
+                    }
+                    if ui
+                        .checkbox(&mut config.group_delay_slots, "Group delay slots")
+                        .on_hover_text(
+                            "Aligns branches with their delay slots, so an instruction moved \
+                            into a delay slot counts as a single difference.",
+                        )
+                        .changed()
+                    {
+                        config.queue_reload = true;
+                    }
                 });
             });
         });
@@ -521,6 +588,10 @@ impl eframe::App for App {
             egui::CentralPanel::default().show(ctx, |ui| {
                 data_diff_ui(ui, diff_state, appearance);
             });
+        } else if diff_state.current_view == View::DebugInfoDiff && build_success {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                debug_info_diff_ui(ui, diff_state, appearance);
+            });
         } else {
             egui::SidePanel::left("side_panel").show(ctx, |ui| {
                 egui::ScrollArea::both().show(ui, |ui| {
@@ -7,6 +7,11 @@ use std::{
 use anyhow::{ensure, Result};
 use filetime::FileTime;
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use objdiff_core::{
+    arch::objdump::ObjdumpConfig,
+    diff::{equiv::EquivalenceRule, RelocDiffOptions},
+    obj::demangle::Demangler,
+};
 
 use crate::{
     app::{AppConfig, ProjectConfigInfo},
@@ -34,6 +39,14 @@ pub struct ProjectConfig {
     pub watch_patterns: Option<Vec<Glob>>,
     #[serde(default, alias = "units")]
     pub objects: Vec<ProjectObject>,
+    #[serde(default)]
+    pub objdump: Option<ObjdumpConfig>,
+    #[serde(default)]
+    pub equivalences: Vec<EquivalenceRule>,
+    #[serde(default)]
+    pub reloc_diffs: Option<RelocDiffOptions>,
+    #[serde(default)]
+    pub demangler: Option<Demangler>,
 }
 
 #[derive(Default, Clone, serde::Deserialize)]
@@ -52,6 +65,8 @@ pub struct ProjectObject {
     pub complete: Option<bool>,
     #[serde(default)]
     pub scratch: Option<ScratchConfig>,
+    #[serde(default)]
+    pub reloc_diffs: Option<RelocDiffOptions>,
 }
 
 #[derive(Default, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -178,6 +193,15 @@ pub fn load_project_config(config: &mut AppConfig) -> Result<()> {
         });
         config.watcher_change = true;
         config.objects = project_config.objects;
+        config.objdump = project_config.objdump;
+        config.equivalences = project_config.equivalences;
+        config.project_reloc_diffs = project_config.reloc_diffs.is_some();
+        if let Some(reloc_diffs) = project_config.reloc_diffs {
+            config.reloc_diffs = reloc_diffs;
+        }
+        if let Some(demangler) = project_config.demangler {
+            config.demangler = demangler;
+        }
         config.object_nodes =
             build_nodes(&config.objects, project_dir, &config.target_obj_dir, &config.base_obj_dir);
         config.project_config_info = Some(info);
@@ -1,6 +1,5 @@
-use std::sync::mpsc::Receiver;
-
 use anyhow::{Context, Result};
+use objdiff_core::diff::CancellationToken;
 use self_update::{cargo_crate_version, update::Release};
 
 use crate::{
@@ -14,7 +13,10 @@ pub struct CheckUpdateResult {
     pub found_binary: bool,
 }
 
-fn run_check_update(context: &JobContext, cancel: Receiver<()>) -> Result<Box<CheckUpdateResult>> {
+fn run_check_update(
+    context: &JobContext,
+    cancel: CancellationToken,
+) -> Result<Box<CheckUpdateResult>> {
     update_status(context, "Fetching latest release".to_string(), 0, 1, &cancel)?;
     let updater = build_updater().context("Failed to create release updater")?;
     let latest_release = updater.get_latest_release()?;
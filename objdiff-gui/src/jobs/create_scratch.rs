@@ -1,7 +1,8 @@
-use std::{fs, path::PathBuf, sync::mpsc::Receiver};
+use std::{fs, path::PathBuf};
 
 use anyhow::{anyhow, bail, Context, Result};
 use const_format::formatcp;
+use objdiff_core::diff::CancellationToken;
 
 use crate::{
     app::AppConfig,
@@ -72,7 +73,7 @@ const WEB_HOST: &str = "http://localhost:8080";
 
 fn run_create_scratch(
     status: &JobContext,
-    cancel: Receiver<()>,
+    cancel: CancellationToken,
     config: CreateScratchConfig,
 ) -> Result<Box<CreateScratchResult>> {
     let project_dir =
@@ -1,22 +1,23 @@
 use std::{
     sync::{
         atomic::{AtomicUsize, Ordering},
-        mpsc::{Receiver, Sender, TryRecvError},
         Arc, RwLock,
     },
     thread::JoinHandle,
 };
 
 use anyhow::Result;
+use objdiff_core::diff::CancellationToken;
 
 use crate::jobs::{
     check_update::CheckUpdateResult, create_scratch::CreateScratchResult, objdiff::ObjDiffResult,
-    update::UpdateResult,
+    prev_diff::PrevDiffResult, update::UpdateResult,
 };
 
 pub mod check_update;
 pub mod create_scratch;
 pub mod objdiff;
+pub mod prev_diff;
 pub mod update;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -25,6 +26,7 @@ pub enum Job {
     CheckUpdate,
     Update,
     CreateScratch,
+    PrevDiff,
 }
 pub static JOB_ID: AtomicUsize = AtomicUsize::new(0);
 
@@ -106,7 +108,7 @@ pub struct JobState {
     pub kind: Job,
     pub handle: Option<JoinHandle<JobResult>>,
     pub context: JobContext,
-    pub cancel: Sender<()>,
+    pub cancel: CancellationToken,
     pub should_remove: bool,
 }
 
@@ -125,20 +127,14 @@ pub enum JobResult {
     CheckUpdate(Option<Box<CheckUpdateResult>>),
     Update(Box<UpdateResult>),
     CreateScratch(Option<Box<CreateScratchResult>>),
-}
-
-fn should_cancel(rx: &Receiver<()>) -> bool {
-    match rx.try_recv() {
-        Ok(_) | Err(TryRecvError::Disconnected) => true,
-        Err(_) => false,
-    }
+    PrevDiff(Option<PrevDiffResult>),
 }
 
 fn start_job(
     ctx: &egui::Context,
     title: &str,
     kind: Job,
-    run: impl FnOnce(JobContext, Receiver<()>) -> Result<JobResult> + Send + 'static,
+    run: impl FnOnce(JobContext, CancellationToken) -> Result<JobResult> + Send + 'static,
 ) -> JobState {
     let status = Arc::new(RwLock::new(JobStatus {
         title: title.to_string(),
@@ -149,9 +145,10 @@ fn start_job(
     }));
     let context = JobContext { status: status.clone(), egui: ctx.clone() };
     let context_inner = JobContext { status: status.clone(), egui: ctx.clone() };
-    let (tx, rx) = std::sync::mpsc::channel();
+    let cancel = CancellationToken::default();
+    let cancel_inner = cancel.clone();
     let handle = std::thread::spawn(move || {
-        return match run(context_inner, rx) {
+        return match run(context_inner, cancel_inner) {
             Ok(state) => state,
             Err(e) => {
                 if let Ok(mut w) = status.write() {
@@ -163,7 +160,7 @@ fn start_job(
     });
     let id = JOB_ID.fetch_add(1, Ordering::Relaxed);
     log::info!("Started job {}", id);
-    JobState { id, kind, handle: Some(handle), context, cancel: tx, should_remove: true }
+    JobState { id, kind, handle: Some(handle), context, cancel, should_remove: true }
 }
 
 fn update_status(
@@ -171,13 +168,13 @@ fn update_status(
     str: String,
     count: u32,
     total: u32,
-    cancel: &Receiver<()>,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     let mut w =
         context.status.write().map_err(|_| anyhow::Error::msg("Failed to lock job status"))?;
     w.progress_items = Some([count, total]);
     w.progress_percent = count as f32 / total as f32;
-    if should_cancel(cancel) {
+    if cancel.is_cancelled() {
         w.status = "Cancelled".to_string();
         return Err(anyhow::Error::msg("Cancelled"));
     } else {
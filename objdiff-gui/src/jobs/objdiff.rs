@@ -1,14 +1,24 @@
 use std::{
+    collections::HashMap,
+    fs,
     path::{Path, PathBuf},
     process::Command,
     str::from_utf8,
-    sync::mpsc::Receiver,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use anyhow::{anyhow, Context, Error, Result};
 use objdiff_core::{
-    diff::{diff_objs, DiffAlg, DiffObjConfig},
-    obj::{elf, ObjInfo},
+    arch::objdump::ObjdumpConfig,
+    diff::{
+        cache::DiffCache,
+        debug_info::{diff_debug_info, DebugInfoDiff},
+        diff_objs_cached,
+        equiv::EquivalenceRule,
+        CancellationToken, DiffAlg, DiffObjConfig, RelocDiffOptions, ScoreWeights,
+    },
+    obj::{demangle::Demangler, elf, ObjInfo},
 };
 use time::OffsetDateTime;
 
@@ -60,10 +70,19 @@ pub struct ObjDiffConfig {
     pub code_alg: DiffAlg,
     pub data_alg: DiffAlg,
     pub relax_reloc_diffs: bool,
+    pub reloc_diffs: RelocDiffOptions,
+    pub block_diff: bool,
+    pub group_delay_slots: bool,
+    pub score_weights: ScoreWeights,
+    pub deadline: Option<Duration>,
+    pub objdump: Option<ObjdumpConfig>,
+    pub demangler: Demangler,
+    pub equivalences: Vec<EquivalenceRule>,
+    pub diff_cache: Arc<Mutex<DiffCache>>,
 }
 
 impl ObjDiffConfig {
-    pub(crate) fn from_config(config: &AppConfig) -> Self {
+    pub(crate) fn from_config(config: &AppConfig, diff_cache: Arc<Mutex<DiffCache>>) -> Self {
         Self {
             build_config: BuildConfig::from_config(config),
             build_base: config.build_base,
@@ -72,6 +91,22 @@ impl ObjDiffConfig {
             code_alg: config.code_alg,
             data_alg: config.data_alg,
             relax_reloc_diffs: config.relax_reloc_diffs,
+            reloc_diffs: config
+                .selected_obj
+                .as_ref()
+                .and_then(|obj| obj.reloc_diffs)
+                .unwrap_or(config.reloc_diffs),
+            block_diff: config.block_diff,
+            group_delay_slots: config.group_delay_slots,
+            score_weights: config.score_weights,
+            deadline: match config.diff_time_limit {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            objdump: config.objdump.clone(),
+            demangler: config.demangler,
+            equivalences: config.equivalences.clone(),
+            diff_cache,
         }
     }
 }
@@ -79,11 +114,47 @@ impl ObjDiffConfig {
 pub struct ObjDiffResult {
     pub first_status: BuildStatus,
     pub second_status: BuildStatus,
-    pub first_obj: Option<ObjInfo>,
-    pub second_obj: Option<ObjInfo>,
+    pub first_obj: Option<Arc<ObjInfo>>,
+    pub second_obj: Option<Arc<ObjInfo>>,
+    /// Base object of an earlier build, if it changed since
+    pub prev_base: Option<Arc<ObjInfo>>,
+    /// Diff of the target's and base's debug information, if either has any
+    pub debug_diff: Option<DebugInfoDiff>,
+    /// Settings used for the diff
+    pub diff_config: DiffObjConfig,
+    /// Source files referenced by the base's line info
+    pub base_sources: SourceFiles,
     pub time: OffsetDateTime,
 }
 
+/// Lines of source files by path as listed in [`ObjLineInfo::files`], or
+/// `None` for files that couldn't be read.
+///
+/// [`ObjLineInfo::files`]: objdiff_core::obj::ObjLineInfo::files
+pub type SourceFiles = HashMap<String, Option<Vec<String>>>;
+
+/// Reads the source files referenced by an object's line info, resolving
+/// relative paths from the project directory.
+fn read_source_files(obj: &ObjInfo, project_dir: Option<&Path>) -> SourceFiles {
+    let Some(line_info) = &obj.line_info else {
+        return SourceFiles::new();
+    };
+    line_info
+        .files
+        .iter()
+        .map(|path| {
+            let full_path = match project_dir {
+                Some(dir) => dir.join(path),
+                None => PathBuf::from(path),
+            };
+            let lines = fs::read_to_string(full_path)
+                .ok()
+                .map(|text| text.lines().map(|line| line.replace('\t', "    ")).collect());
+            (path.clone(), lines)
+        })
+        .collect()
+}
+
 pub(crate) fn run_make(config: &BuildConfig, arg: &Path) -> BuildStatus {
     let Some(cwd) = &config.project_dir else {
         return BuildStatus {
@@ -149,7 +220,7 @@ fn run_make_cmd(config: &BuildConfig, cwd: &Path, arg: &Path) -> Result<BuildSta
 
 fn run_build(
     context: &JobContext,
-    cancel: Receiver<()>,
+    cancel: CancellationToken,
     config: ObjDiffConfig,
 ) -> Result<Box<ObjDiffResult>> {
     let obj_config = config.selected_obj.as_ref().ok_or_else(|| Error::msg("Missing obj path"))?;
@@ -218,6 +289,21 @@ fn run_build(
 
     let time = OffsetDateTime::now_utc();
 
+    let diff_config = DiffObjConfig {
+        code_alg: config.code_alg,
+        data_alg: config.data_alg,
+        relax_reloc_diffs: config.relax_reloc_diffs,
+        reloc_diffs: config.reloc_diffs,
+        block_diff: config.block_diff,
+        group_delay_slots: config.group_delay_slots,
+        score_weights: config.score_weights,
+        equivalences: config.equivalences,
+        objdump: config.objdump,
+        demangler: config.demangler,
+        deadline: config.deadline,
+        cancel: cancel.clone(),
+    };
+
     let mut first_obj =
         match &obj_config.target_path {
             Some(target_path) if first_status.success => {
@@ -228,7 +314,7 @@ fn run_build(
                     total,
                     &cancel,
                 )?;
-                Some(elf::read(target_path).with_context(|| {
+                Some(elf::read(target_path, &diff_config).with_context(|| {
                     format!("Failed to read object '{}'", target_path.display())
                 })?)
             }
@@ -245,7 +331,7 @@ fn run_build(
                 &cancel,
             )?;
             Some(
-                elf::read(base_path)
+                elf::read(base_path, &diff_config)
                     .with_context(|| format!("Failed to read object '{}'", base_path.display()))?,
             )
         }
@@ -253,15 +339,37 @@ fn run_build(
     };
 
     update_status(context, "Performing diff".to_string(), 4, total, &cancel)?;
-    let diff_config = DiffObjConfig {
-        code_alg: config.code_alg,
-        data_alg: config.data_alg,
-        relax_reloc_diffs: config.relax_reloc_diffs,
+    let mut diff_cache =
+        config.diff_cache.lock().map_err(|_| anyhow!("Failed to lock diff cache"))?;
+    diff_objs_cached(&diff_config, first_obj.as_mut(), second_obj.as_mut(), &mut diff_cache)?;
+    let debug_diff = match (
+        first_obj.as_ref().and_then(|obj| obj.debug_info.as_ref()),
+        second_obj.as_ref().and_then(|obj| obj.debug_info.as_ref()),
+    ) {
+        (None, None) => None,
+        (left, right) => Some(diff_debug_info(
+            left.unwrap_or(&Default::default()),
+            right.unwrap_or(&Default::default()),
+        )),
     };
-    diff_objs(&diff_config, first_obj.as_mut(), second_obj.as_mut())?;
+
+    let base_sources = second_obj
+        .as_ref()
+        .map(|obj| read_source_files(obj, config.build_config.project_dir.as_deref()))
+        .unwrap_or_default();
 
     update_status(context, "Complete".to_string(), total, total, &cancel)?;
-    Ok(Box::new(ObjDiffResult { first_status, second_status, first_obj, second_obj, time }))
+    Ok(Box::new(ObjDiffResult {
+        first_status,
+        second_status,
+        first_obj: first_obj.map(Arc::new),
+        second_obj: second_obj.map(Arc::new),
+        prev_base: None,
+        debug_diff,
+        diff_config,
+        base_sources,
+        time,
+    }))
 }
 
 pub fn start_build(ctx: &egui::Context, config: ObjDiffConfig) -> JobState {
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use objdiff_core::{
+    diff::{
+        diff_objs, diff_symbol,
+        three_way::{align_three_way, ThreeWayDiff},
+        CancellationToken, DiffObjConfig,
+    },
+    obj::{ObjInfo, ObjSymbol},
+};
+
+use crate::{
+    jobs::{start_job, update_status, Job, JobContext, JobResult, JobState},
+    views::symbol_diff::{CompareMode, View},
+};
+
+pub struct PrevDiffConfig {
+    pub mode: CompareMode,
+    /// Function to diff, or `None` for the whole unit
+    pub symbol_name: Option<String>,
+    /// Target object, for the three-way diff
+    pub target_obj: Option<Arc<ObjInfo>>,
+    pub prev_obj: Arc<ObjInfo>,
+    pub base_obj: Arc<ObjInfo>,
+    pub diff_config: DiffObjConfig,
+}
+
+/// Diff of the current base object against its previous build.
+pub struct PrevDiff {
+    pub mode: CompareMode,
+    pub symbol_name: Option<String>,
+    pub prev_obj: ObjInfo,
+    pub base_obj: ObjInfo,
+}
+
+impl PrevDiff {
+    /// Whether the given view shows this diff instead of the target diff.
+    pub fn is_active(&self, view: View) -> bool {
+        self.mode == CompareMode::PrevUnit || view == View::FunctionDiff
+    }
+}
+
+/// Three-way diff of the selected function.
+pub struct ThreeWayState {
+    pub symbol_name: String,
+    pub diff: ThreeWayDiff,
+}
+
+pub enum PrevDiffResult {
+    Prev(Box<PrevDiff>),
+    ThreeWay(ThreeWayState),
+}
+
+fn run_prev_diff(
+    context: &JobContext,
+    cancel: CancellationToken,
+    config: PrevDiffConfig,
+) -> Result<Option<PrevDiffResult>> {
+    let PrevDiffConfig { mode, symbol_name, target_obj, prev_obj, base_obj, mut diff_config } =
+        config;
+    diff_config.cancel = cancel.clone();
+    if mode == CompareMode::ThreeWay {
+        let (Some(target_obj), Some(symbol_name)) = (target_obj, symbol_name) else {
+            return Ok(None);
+        };
+        return run_three_way(
+            context,
+            &cancel,
+            &diff_config,
+            symbol_name,
+            &target_obj,
+            &prev_obj,
+            &base_obj,
+        );
+    }
+    update_status(context, "Copying objects".to_string(), 0, 2, &cancel)?;
+    let mut prev_obj = ObjInfo::clone(&prev_obj);
+    let mut base_obj = ObjInfo::clone(&base_obj);
+
+    update_status(context, "Performing diff".to_string(), 1, 2, &cancel)?;
+    match &symbol_name {
+        Some(name) => diff_symbol(&diff_config, &mut prev_obj, &mut base_obj, name)?,
+        None => diff_objs(&diff_config, Some(&mut prev_obj), Some(&mut base_obj))?,
+    }
+
+    update_status(context, "Complete".to_string(), 2, 2, &cancel)?;
+    Ok(Some(PrevDiffResult::Prev(Box::new(PrevDiff { mode, symbol_name, prev_obj, base_obj }))))
+}
+
+/// Diffs the target against the previous base, and aligns the result with the
+/// current diff of the target against the base.
+fn run_three_way(
+    context: &JobContext,
+    cancel: &CancellationToken,
+    diff_config: &DiffObjConfig,
+    symbol_name: String,
+    target_obj: &ObjInfo,
+    prev_obj: &ObjInfo,
+    base_obj: &ObjInfo,
+) -> Result<Option<PrevDiffResult>> {
+    update_status(context, "Copying objects".to_string(), 0, 3, cancel)?;
+    let mut prev_target_obj = target_obj.clone();
+    let mut prev_obj = prev_obj.clone();
+
+    update_status(context, "Performing diff".to_string(), 1, 3, cancel)?;
+    diff_symbol(diff_config, &mut prev_target_obj, &mut prev_obj, &symbol_name)?;
+
+    update_status(context, "Aligning diffs".to_string(), 2, 3, cancel)?;
+    // The function may not exist in every object
+    let (Some(target), Some(base), Some(prev_target), Some(prev)) = (
+        find_symbol(target_obj, &symbol_name),
+        find_symbol(base_obj, &symbol_name),
+        find_symbol(&prev_target_obj, &symbol_name),
+        find_symbol(&prev_obj, &symbol_name),
+    ) else {
+        return Ok(None);
+    };
+    let diff = align_three_way(
+        (&target.instructions, &base.instructions),
+        (&prev_target.instructions, &prev.instructions),
+    );
+
+    update_status(context, "Complete".to_string(), 3, 3, cancel)?;
+    Ok(Some(PrevDiffResult::ThreeWay(ThreeWayState { symbol_name, diff })))
+}
+
+fn find_symbol<'a>(obj: &'a ObjInfo, name: &str) -> Option<&'a ObjSymbol> {
+    obj.sections.iter().find_map(|section| section.symbols.iter().find(|s| s.name == name))
+}
+
+pub fn start_prev_diff(ctx: &egui::Context, config: PrevDiffConfig) -> JobState {
+    start_job(ctx, "Previous build diff", Job::PrevDiff, move |context, cancel| {
+        match run_prev_diff(&context, cancel.clone(), config) {
+            Ok(result) => Ok(JobResult::PrevDiff(result)),
+            // Superseded by a change of selection or compare mode
+            Err(_) if cancel.is_cancelled() => Ok(JobResult::PrevDiff(None)),
+            Err(e) => Err(e),
+        }
+    })
+}
@@ -2,11 +2,11 @@ use std::{
     env::{current_dir, current_exe},
     fs::File,
     path::PathBuf,
-    sync::mpsc::Receiver,
 };
 
 use anyhow::{Context, Result};
 use const_format::formatcp;
+use objdiff_core::diff::CancellationToken;
 
 use crate::{
     jobs::{start_job, update_status, Job, JobContext, JobResult, JobState},
@@ -17,7 +17,7 @@ pub struct UpdateResult {
     pub exe_path: PathBuf,
 }
 
-fn run_update(status: &JobContext, cancel: Receiver<()>) -> Result<Box<UpdateResult>> {
+fn run_update(status: &JobContext, cancel: CancellationToken) -> Result<Box<UpdateResult>> {
     update_status(status, "Fetching latest release".to_string(), 0, 3, &cancel)?;
     let updater = build_updater().context("Failed to create release updater")?;
     let latest_release = updater.get_latest_release()?;
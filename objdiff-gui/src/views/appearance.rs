@@ -28,6 +28,8 @@ pub struct Appearance {
     pub insert_color: Color32, // GREEN
     #[serde(skip)]
     pub delete_color: Color32, // RED
+    #[serde(skip)]
+    pub moved_color: Color32, // GOLD
 
     // Global
     #[serde(skip)]
@@ -64,6 +66,7 @@ impl Default for Appearance {
             replace_color: Color32::LIGHT_BLUE,
             insert_color: Color32::GREEN,
             delete_color: Color32::from_rgb(200, 40, 41),
+            moved_color: Color32::GOLD,
             utc_offset: UtcOffset::UTC,
             fonts: FontState::default(),
             next_ui_font: None,
@@ -107,6 +110,7 @@ impl Appearance {
                 self.replace_color = Color32::LIGHT_BLUE;
                 self.insert_color = Color32::GREEN;
                 self.delete_color = Color32::from_rgb(200, 40, 41);
+                self.moved_color = Color32::GOLD;
             }
             eframe::Theme::Light => {
                 style.visuals = egui::Visuals::light();
@@ -117,6 +121,7 @@ impl Appearance {
                 self.replace_color = Color32::DARK_BLUE;
                 self.insert_color = Color32::DARK_GREEN;
                 self.delete_color = Color32::from_rgb(200, 40, 41);
+                self.moved_color = Color32::from_rgb(160, 120, 0);
             }
         }
         ctx.set_style(style);
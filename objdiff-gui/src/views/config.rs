@@ -14,7 +14,10 @@ use egui::{
     SelectableLabel, TextFormat, Widget, WidgetText,
 };
 use globset::Glob;
-use objdiff_core::diff::DiffAlg;
+use objdiff_core::{
+    diff::{DiffAlg, ScoreWeights},
+    obj::demangle::Demangler,
+};
 use self_update::cargo_crate_version;
 
 use crate::{
@@ -94,6 +97,7 @@ impl ConfigViewState {
                             reverse_fn_order: None,
                             complete: None,
                             scratch: None,
+                            reloc_diffs: None,
                         });
                     } else if let Ok(obj_path) = path.strip_prefix(target_dir) {
                         let base_path = base_dir.join(obj_path);
@@ -104,6 +108,7 @@ impl ConfigViewState {
                             reverse_fn_order: None,
                             complete: None,
                             scratch: None,
+                            reloc_diffs: None,
                         });
                     }
                 }
@@ -396,6 +401,7 @@ fn display_object(
             reverse_fn_order: object.reverse_fn_order,
             complete: object.complete,
             scratch: object.scratch.clone(),
+            reloc_diffs: object.reloc_diffs,
         });
     }
 }
@@ -888,6 +894,86 @@ fn diff_options_ui(ui: &mut egui::Ui, config: &mut AppConfig, appearance: &Appea
     if diff_alg_ui(ui, "Data diff algorithm", &mut config.data_alg) {
         config.queue_reload = true;
     }
+    if demangler_ui(ui, &mut config.demangler) {
+        config.queue_reload = true;
+    }
+    ui.horizontal(|ui| {
+        ui.label("Time limit per symbol");
+        if ui
+            .add(egui::DragValue::new(&mut config.diff_time_limit).suffix("s"))
+            .on_hover_text("Symbols exceeding the limit are marked as truncated. 0 to disable.")
+            .changed()
+        {
+            config.queue_reload = true;
+        }
+    });
+    ui.separator();
+    ui.label("Score weights").on_hover_text(
+        "Penalties for the weighted diff score, modelled on asm-differ. Lower scores are closer.",
+    );
+    let weights = &mut config.score_weights;
+    let mut changed = false;
+    egui::Grid::new("score_weights").num_columns(2).show(ui, |ui| {
+        for (label, value) in [
+            ("Register swap", &mut weights.regswap),
+            ("Immediate mismatch", &mut weights.immediate),
+            ("Reorder", &mut weights.reorder),
+            ("Insertion", &mut weights.insertion),
+            ("Deletion", &mut weights.deletion),
+        ] {
+            ui.label(label);
+            changed |= ui.add(egui::DragValue::new(value)).changed();
+            ui.end_row();
+        }
+    });
+    if ui.button("Reset to defaults").clicked() {
+        *weights = ScoreWeights::default();
+        changed = true;
+    }
+    if changed {
+        config.queue_reload = true;
+    }
+    ui.separator();
+    ui.label("Relocation differences").on_hover_text(
+        "Applies when \"Relax relocation diffs\" is off. \
+        Units can override these in the project configuration.",
+    );
+    let overridden = config.project_reloc_diffs
+        || config.selected_obj.as_ref().is_some_and(|obj| obj.reloc_diffs.is_some());
+    let options = &mut config.reloc_diffs;
+    let mut changed = false;
+    for (label, hover, value) in [
+        (
+            "Check addends",
+            "Report the same target symbol with a different addend.",
+            &mut options.check_addend,
+        ),
+        (
+            "Check local vs global",
+            "Report the same target symbol, local on one side and global on the other.",
+            &mut options.check_binding,
+        ),
+        (
+            "Ignore target names",
+            "Ignore different target symbols in the same section.",
+            &mut options.relax_target_name,
+        ),
+        (
+            "Ignore extern addends and binding",
+            "Ignore addend and binding differences between extern targets of the same name, \
+            even if checked above.",
+            &mut options.relax_extern_targets,
+        ),
+    ] {
+        changed |= ui
+            .add_enabled(!overridden, egui::Checkbox::new(value, label))
+            .on_hover_text(hover)
+            .on_disabled_hover_text(CONFIG_DISABLED_TEXT)
+            .changed();
+    }
+    if changed {
+        config.queue_reload = true;
+    }
 }
 
 fn diff_alg_ui(ui: &mut egui::Ui, label: impl Into<WidgetText>, alg: &mut DiffAlg) -> bool {
@@ -902,6 +988,19 @@ fn diff_alg_ui(ui: &mut egui::Ui, label: impl Into<WidgetText>, alg: &mut DiffAl
     response.inner.unwrap_or(false)
 }
 
+fn demangler_ui(ui: &mut egui::Ui, demangler: &mut Demangler) -> bool {
+    let response =
+        egui::ComboBox::from_label("Demangler").selected_text(demangler.name()).show_ui(ui, |ui| {
+            let mut changed = false;
+            for value in Demangler::ALL {
+                changed |= ui.selectable_value(demangler, value, value.name()).changed();
+            }
+            changed
+        });
+    response.response.on_hover_text("Auto detects the mangling scheme of each symbol name.");
+    response.inner.unwrap_or(false)
+}
+
 const fn diff_alg_to_string(alg: DiffAlg) -> &'static str {
     match alg {
         DiffAlg::Patience => "Patience",
@@ -175,6 +175,11 @@ pub fn data_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance: &A
     else {
         return;
     };
+    let prev_diff = state.prev_diff.as_ref().filter(|prev| prev.is_active(View::DataDiff));
+    let (left_obj, right_obj) = match prev_diff {
+        Some(prev_diff) => (Some(&prev_diff.prev_obj), Some(&prev_diff.base_obj)),
+        None => (result.first_obj.as_deref(), result.second_obj.as_deref()),
+    };
 
     // Header
     let available_width = ui.available_width();
@@ -198,7 +203,11 @@ pub fn data_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance: &A
                         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
                         ui.style_mut().wrap = Some(false);
                         ui.colored_label(appearance.highlight_color, &selected_symbol.symbol_name);
-                        ui.label("Diff target:");
+                        if prev_diff.is_some() {
+                            ui.label("Previous base:");
+                        } else {
+                            ui.label("Diff target:");
+                        }
                     });
                 },
             );
@@ -240,7 +249,16 @@ pub fn data_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance: &A
                     ui.scope(|ui| {
                         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
                         ui.style_mut().wrap = Some(false);
-                        ui.label("");
+                        let truncated = right_obj
+                            .and_then(|obj| find_section(obj, selected_symbol))
+                            .is_some_and(|section| section.truncated);
+                        if truncated {
+                            ui.colored_label(appearance.delete_color, "Timed out").on_hover_text(
+                                "Diff exceeded the time limit and may be incomplete",
+                            );
+                        } else {
+                            ui.label("");
+                        }
                         ui.label("Diff base:");
                     });
                 },
@@ -259,11 +277,5 @@ pub fn data_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance: &A
         .resizable(false)
         .auto_shrink([false, false])
         .min_scrolled_height(available_height);
-    data_table_ui(
-        table,
-        result.first_obj.as_ref(),
-        result.second_obj.as_ref(),
-        selected_symbol,
-        appearance,
-    );
+    data_table_ui(table, left_obj, right_obj, selected_symbol, appearance);
 }
@@ -0,0 +1,250 @@
+use egui::{
+    Align, CollapsingHeader, Color32, Grid, Layout, RichText, ScrollArea, TextEdit, Ui, Vec2,
+    Widget,
+};
+use objdiff_core::{
+    diff::debug_info::{DebugEntryDiff, DebugInfoDiff},
+    obj::ObjDebugInfo,
+};
+use time::format_description;
+
+use crate::views::{
+    appearance::Appearance,
+    symbol_diff::{DiffViewState, View},
+};
+
+#[derive(Default, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum DebugInfoTab {
+    #[default]
+    Functions,
+    Globals,
+    Types,
+}
+
+#[derive(Default)]
+pub struct DebugInfoViewState {
+    pub tab: DebugInfoTab,
+    pub search: String,
+    /// Hide entries matching on both sides
+    pub only_differences: bool,
+}
+
+fn tab_entries(diff: &DebugInfoDiff, tab: DebugInfoTab) -> &[DebugEntryDiff] {
+    match tab {
+        DebugInfoTab::Functions => &diff.functions,
+        DebugInfoTab::Globals => &diff.globals,
+        DebugInfoTab::Types => &diff.types,
+    }
+}
+
+/// Declaration of an entry, shown when hovering it.
+fn declaration(info: &ObjDebugInfo, tab: DebugInfoTab, index: usize) -> Option<String> {
+    match tab {
+        DebugInfoTab::Functions => info.functions.get(index).map(|f| f.prototype.clone()),
+        DebugInfoTab::Globals => info.globals.get(index).map(|g| g.declaration.clone()),
+        DebugInfoTab::Types => info.types.get(index).map(|t| t.definition()),
+    }
+}
+
+fn entry_color(entry: &DebugEntryDiff, appearance: &Appearance) -> Color32 {
+    match (entry.left, entry.right) {
+        (Some(_), None) => appearance.delete_color,
+        (None, Some(_)) => appearance.insert_color,
+        _ if entry.differences.is_empty() => appearance.text_color,
+        _ => appearance.replace_color,
+    }
+}
+
+fn entries_ui(
+    ui: &mut Ui,
+    diff: &DebugInfoDiff,
+    left: Option<&ObjDebugInfo>,
+    right: Option<&ObjDebugInfo>,
+    state: &DebugInfoViewState,
+    appearance: &Appearance,
+) {
+    let search = state.search.to_ascii_lowercase();
+    ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+        ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+        ui.style_mut().wrap = Some(false);
+        for (index, entry) in tab_entries(diff, state.tab).iter().enumerate() {
+            if state.only_differences && entry.is_match() {
+                continue;
+            }
+            if !search.is_empty() && !entry.name.to_ascii_lowercase().contains(&search) {
+                continue;
+            }
+            let color = entry_color(entry, appearance);
+            let text = match (entry.left, entry.right) {
+                (Some(_), None) => format!("{} (target only)", entry.name),
+                (None, Some(_)) => format!("{} (base only)", entry.name),
+                _ => entry.name.clone(),
+            };
+            let response = if entry.differences.is_empty() {
+                ui.colored_label(color, text)
+            } else {
+                CollapsingHeader::new(RichText::new(text).color(color))
+                    .id_source((state.tab, index))
+                    .show(ui, |ui| {
+                        Grid::new((state.tab, index)).striped(true).show(ui, |ui| {
+                            ui.label("");
+                            ui.label("Target");
+                            ui.label("Base");
+                            ui.end_row();
+                            for difference in &entry.differences {
+                                let (left, right) = difference.values();
+                                ui.colored_label(
+                                    appearance.deemphasized_text_color,
+                                    difference.label(),
+                                );
+                                ui.colored_label(appearance.replace_color, left);
+                                ui.colored_label(appearance.replace_color, right);
+                                ui.end_row();
+                            }
+                        });
+                    })
+                    .header_response
+            };
+            let left = left.zip(entry.left).and_then(|(info, i)| declaration(info, state.tab, i));
+            let right =
+                right.zip(entry.right).and_then(|(info, i)| declaration(info, state.tab, i));
+            if left.is_some() || right.is_some() {
+                response.on_hover_ui_at_pointer(|ui| {
+                    ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                    if let Some(left) = left {
+                        ui.label("Target:");
+                        ui.label(left.trim_end());
+                    }
+                    if let Some(right) = right {
+                        ui.label("Base:");
+                        ui.label(right.trim_end());
+                    }
+                });
+            }
+        }
+    });
+}
+
+pub fn debug_info_diff_ui(ui: &mut Ui, state: &mut DiffViewState, appearance: &Appearance) {
+    let Some(result) = &state.build else {
+        return;
+    };
+    let debug_state = &mut state.debug_info_state;
+
+    // Header
+    let available_width = ui.available_width();
+    let column_width = available_width / 2.0;
+    ui.allocate_ui_with_layout(
+        Vec2 { x: available_width, y: 100.0 },
+        Layout::left_to_right(Align::Min),
+        |ui| {
+            // Left column
+            ui.allocate_ui_with_layout(
+                Vec2 { x: column_width, y: 100.0 },
+                Layout::top_down(Align::Min),
+                |ui| {
+                    ui.set_width(column_width);
+
+                    if ui.button("⏴ Back").clicked() {
+                        state.current_view = View::SymbolDiff;
+                    }
+
+                    ui.scope(|ui| {
+                        ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                        ui.style_mut().wrap = Some(false);
+                        ui.colored_label(appearance.highlight_color, "Debug info");
+                    });
+
+                    ui.horizontal(|ui| {
+                        TextEdit::singleline(&mut debug_state.search)
+                            .hint_text("Filter entries")
+                            .ui(ui);
+                        let target_info =
+                            result.first_obj.as_ref().and_then(|obj| obj.debug_info.as_ref());
+                        if ui
+                            .add_enabled(target_info.is_some(), egui::Button::new("Copy header"))
+                            .on_hover_text(
+                                "Copy C declarations of the target's types, globals and functions",
+                            )
+                            .clicked()
+                        {
+                            let header = target_info.map(|info| info.header()).unwrap_or_default();
+                            ui.output_mut(|output| output.copied_text = header);
+                        }
+                    });
+                },
+            );
+
+            // Right column
+            ui.allocate_ui_with_layout(
+                Vec2 { x: column_width, y: 100.0 },
+                Layout::top_down(Align::Min),
+                |ui| {
+                    ui.set_width(column_width);
+
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!state.build_running, egui::Button::new("Build"))
+                            .clicked()
+                        {
+                            state.queue_build = true;
+                        }
+                        ui.scope(|ui| {
+                            ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                            ui.style_mut().wrap = Some(false);
+                            if state.build_running {
+                                ui.colored_label(appearance.replace_color, "Building…");
+                            } else {
+                                ui.label("Last built:");
+                                let format =
+                                    format_description::parse("[hour]:[minute]:[second]").unwrap();
+                                ui.label(
+                                    result
+                                        .time
+                                        .to_offset(appearance.utc_offset)
+                                        .format(&format)
+                                        .unwrap(),
+                                );
+                            }
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        for (tab, name) in [
+                            (DebugInfoTab::Functions, "Functions"),
+                            (DebugInfoTab::Globals, "Globals"),
+                            (DebugInfoTab::Types, "Types"),
+                        ] {
+                            let differing = result.debug_diff.as_ref().map_or(0, |diff| {
+                                tab_entries(diff, tab).iter().filter(|e| !e.is_match()).count()
+                            });
+                            ui.selectable_value(
+                                &mut debug_state.tab,
+                                tab,
+                                format!("{name} ({differing})"),
+                            )
+                            .on_hover_text("Number of differing entries");
+                        }
+                    });
+
+                    ui.checkbox(&mut debug_state.only_differences, "Only differences");
+                },
+            );
+        },
+    );
+    ui.separator();
+
+    // Entries
+    let Some(diff) = &result.debug_diff else {
+        ui.colored_label(appearance.replace_color, "No debug information");
+        return;
+    };
+    entries_ui(
+        ui,
+        diff,
+        result.first_obj.as_ref().and_then(|obj| obj.debug_info.as_ref()),
+        result.second_obj.as_ref().and_then(|obj| obj.debug_info.as_ref()),
+        debug_state,
+        appearance,
+    );
+}
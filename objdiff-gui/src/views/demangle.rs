@@ -1,4 +1,5 @@
-use egui::TextStyle;
+use egui::RichText;
+use objdiff_core::obj::demangle::Demangler;
 
 use crate::views::appearance::Appearance;
 
@@ -16,19 +17,26 @@ pub fn demangle_window(
     egui::Window::new("Demangle").open(show).show(ctx, |ui| {
         ui.text_edit_singleline(&mut state.text);
         ui.add_space(10.0);
-        if let Some(demangled) = cwdemangle::demangle(&state.text, &Default::default()) {
-            ui.scope(|ui| {
-                ui.style_mut().override_text_style = Some(TextStyle::Monospace);
-                ui.colored_label(appearance.replace_color, &demangled);
-            });
-            if ui.button("Copy").clicked() {
-                ui.output_mut(|output| output.copied_text = demangled);
+        // Names can be valid in more than one scheme
+        egui::Grid::new("demangle").num_columns(3).show(ui, |ui| {
+            for demangler in [Demangler::CodeWarrior, Demangler::GnuV2, Demangler::Itanium] {
+                ui.label(demangler.name());
+                if let Some(demangled) = demangler.demangle(&state.text, demangler) {
+                    ui.colored_label(
+                        appearance.replace_color,
+                        RichText::new(&demangled).monospace(),
+                    );
+                    if ui.button("Copy").clicked() {
+                        ui.output_mut(|output| output.copied_text = demangled);
+                    }
+                } else {
+                    ui.colored_label(
+                        appearance.deemphasized_text_color,
+                        RichText::new("[invalid]").monospace(),
+                    );
+                }
+                ui.end_row();
             }
-        } else {
-            ui.scope(|ui| {
-                ui.style_mut().override_text_style = Some(TextStyle::Monospace);
-                ui.colored_label(appearance.replace_color, "[invalid]");
-            });
-        }
+        });
     });
 }
@@ -1,22 +1,35 @@
 use std::{
     cmp::{max, Ordering},
     default::Default,
+    sync::Arc,
 };
 
 use egui::{
     text::LayoutJob, Align, Color32, Label, Layout, RichText, Sense, TextFormat, Vec2, Widget,
 };
 use egui_extras::{Column, TableBuilder, TableRow};
-use objdiff_core::obj::{
-    ObjInfo, ObjIns, ObjInsArg, ObjInsArgDiff, ObjInsArgValue, ObjInsDiff, ObjInsDiffKind,
-    ObjReloc, ObjRelocKind, ObjSymbol,
+use objdiff_core::{
+    arch::ObjArch,
+    diff::three_way::ThreeWayKind,
+    obj::{
+        ObjBlockDiff, ObjDelaySlot, ObjFunctionInfo, ObjInfo, ObjIns, ObjInsArg, ObjInsArgValue,
+        ObjInsDiff, ObjInsDiffKind, ObjInsJumpTable, ObjLineInfo, ObjLocation, ObjReloc,
+        ObjSourceLine, ObjSymbol,
+    },
 };
 use time::format_description;
 
-use crate::views::{
-    appearance::Appearance,
-    symbol_diff::{match_color_for_symbol, DiffViewState, SymbolReference, View},
-    write_text,
+use crate::{
+    jobs::{
+        objdiff::{ObjDiffResult, SourceFiles},
+        prev_diff::ThreeWayState,
+    },
+    views::{
+        appearance::Appearance,
+        function_graph::function_graph_ui,
+        symbol_diff::{match_color_for_symbol, CompareMode, DiffViewState, SymbolReference, View},
+        write_text,
+    },
 };
 
 #[derive(Default)]
@@ -32,6 +45,88 @@ pub enum HighlightKind {
 #[derive(Default)]
 pub struct FunctionViewState {
     pub highlight: HighlightKind,
+    pub show_graph: bool,
+    /// Row to scroll the instruction table to on the next frame
+    pub scroll_to_row: Option<usize>,
+    /// Interleave source lines with the base's instructions
+    pub show_source: bool,
+}
+
+fn source_line<'a>(sources: &'a SourceFiles, path: &str, line: u64) -> Option<&'a str> {
+    let lines = sources.get(path)?.as_ref()?;
+    lines.get((line as usize).checked_sub(1)?).map(String::as_str)
+}
+
+/// Lines of a skipped stretch of source shown before an instruction, at most.
+const MAX_SOURCE_GAP: u64 = 8;
+
+/// Row of the instruction table.
+#[derive(Copy, Clone)]
+enum AsmRow {
+    /// Row of [`ObjSymbol::instructions`]
+    Ins(usize),
+    /// Start of lines from another source file
+    SourceFile(usize),
+    SourceLine(ObjSourceLine),
+}
+
+/// Interleaves the base's source lines with its instructions, showing lines
+/// before the first instruction generated for them, like `objdump -S`.
+fn source_rows(symbol: &ObjSymbol, line_info: &ObjLineInfo) -> Vec<AsmRow> {
+    let mut rows = Vec::with_capacity(symbol.instructions.len());
+    let mut last: Option<ObjSourceLine> = None;
+    for (idx, ins_diff) in symbol.instructions.iter().enumerate() {
+        let location = ins_diff.ins.as_ref().and_then(|ins| line_info.location(ins.address as u64));
+        if let Some(location) = location.filter(|&location| last != Some(location)) {
+            let first = match last {
+                Some(last) if last.file == location.file => {
+                    if location.line > last.line && location.line - last.line <= MAX_SOURCE_GAP {
+                        last.line + 1
+                    } else {
+                        location.line
+                    }
+                }
+                _ => {
+                    rows.push(AsmRow::SourceFile(location.file));
+                    location.line
+                }
+            };
+            rows.extend(
+                (first..=location.line)
+                    .map(|line| AsmRow::SourceLine(ObjSourceLine { file: location.file, line })),
+            );
+            last = Some(location);
+        }
+        rows.push(AsmRow::Ins(idx));
+    }
+    rows
+}
+
+fn source_col_ui(
+    row: &mut TableRow<'_, '_>,
+    line_info: &ObjLineInfo,
+    sources: &SourceFiles,
+    asm_row: AsmRow,
+    appearance: &Appearance,
+) {
+    row.col(|ui| {
+        let text = match asm_row {
+            AsmRow::SourceFile(file) => format!("{}:", line_info.files[file]),
+            AsmRow::SourceLine(ObjSourceLine { file, line }) => {
+                let path = &line_info.files[file];
+                match source_line(sources, path, line) {
+                    Some(text) => format!("{line:>5} | {text}"),
+                    None => format!("{line:>5} | <{path}>"),
+                }
+            }
+            AsmRow::Ins(_) => String::new(),
+        };
+        ui.label(
+            RichText::new(text)
+                .font(appearance.code_font.clone())
+                .color(appearance.deemphasized_text_color),
+        );
+    });
 }
 
 fn write_reloc_name(
@@ -68,80 +163,51 @@ fn write_reloc_name(
 }
 
 fn write_reloc(
+    arch: &dyn ObjArch,
     reloc: &ObjReloc,
     color: Color32,
     background_color: Color32,
     job: &mut LayoutJob,
     appearance: &Appearance,
 ) {
-    match reloc.kind {
-        ObjRelocKind::PpcAddr16Lo => {
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text("@l", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::PpcAddr16Hi => {
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text("@h", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::PpcAddr16Ha => {
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text("@ha", color, job, appearance.code_font.clone());
+    if let Some(format) = arch.display_reloc(reloc.kind) {
+        write_text(format.prefix, color, job, appearance.code_font.clone());
+        write_reloc_name(reloc, color, background_color, job, appearance);
+        write_text(format.suffix, color, job, appearance.code_font.clone());
+        if reloc.unpaired {
+            write_text("?", appearance.delete_color, job, appearance.code_font.clone());
         }
-        ObjRelocKind::PpcEmbSda21 => {
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text("@sda21", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::MipsHi16 => {
-            write_text("%hi(", color, job, appearance.code_font.clone());
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text(")", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::MipsLo16 => {
-            write_text("%lo(", color, job, appearance.code_font.clone());
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text(")", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::MipsGot16 => {
-            write_text("%got(", color, job, appearance.code_font.clone());
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text(")", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::MipsCall16 => {
-            write_text("%call16(", color, job, appearance.code_font.clone());
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text(")", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::MipsGpRel16 => {
-            write_text("%gp_rel(", color, job, appearance.code_font.clone());
-            write_reloc_name(reloc, color, background_color, job, appearance);
-            write_text(")", color, job, appearance.code_font.clone());
-        }
-        ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14 | ObjRelocKind::Mips26 => {
-            write_reloc_name(reloc, color, background_color, job, appearance);
-        }
-        ObjRelocKind::Absolute | ObjRelocKind::MipsGpRel32 => {
-            write_text("[INVALID]", color, job, appearance.code_font.clone());
-        }
-    };
+    } else {
+        write_text("[INVALID]", color, job, appearance.code_font.clone());
+    }
+}
+
+fn row_color(ins_diff: &ObjInsDiff, appearance: &Appearance) -> Color32 {
+    if ins_diff.padding {
+        return appearance.deemphasized_text_color;
+    }
+    match ins_diff.kind {
+        ObjInsDiffKind::None
+        | ObjInsDiffKind::OpMismatch
+        | ObjInsDiffKind::ArgMismatch
+        | ObjInsDiffKind::Equivalent => appearance.text_color,
+        ObjInsDiffKind::Replace => appearance.replace_color,
+        ObjInsDiffKind::Delete => appearance.delete_color,
+        ObjInsDiffKind::Insert => appearance.insert_color,
+        ObjInsDiffKind::Moved => appearance.moved_color,
+    }
 }
 
 fn write_ins(
+    arch: &dyn ObjArch,
     ins: &ObjIns,
-    diff_kind: &ObjInsDiffKind,
-    args: &[Option<ObjInsArgDiff>],
+    ins_diff: &ObjInsDiff,
     base_addr: u32,
     ui: &mut egui::Ui,
     appearance: &Appearance,
     ins_view_state: &mut FunctionViewState,
 ) {
-    let base_color = match diff_kind {
-        ObjInsDiffKind::None | ObjInsDiffKind::OpMismatch | ObjInsDiffKind::ArgMismatch => {
-            appearance.text_color
-        }
-        ObjInsDiffKind::Replace => appearance.replace_color,
-        ObjInsDiffKind::Delete => appearance.delete_color,
-        ObjInsDiffKind::Insert => appearance.insert_color,
-    };
+    let base_color = row_color(ins_diff, appearance);
 
     let highlighted_op =
         matches!(ins_view_state.highlight, HighlightKind::Opcode(op) if op == ins.op);
@@ -150,7 +216,7 @@ fn write_ins(
         .color(if highlighted_op {
             appearance.emphasized_text_color
         } else {
-            match diff_kind {
+            match ins_diff.kind {
                 ObjInsDiffKind::OpMismatch => appearance.replace_color,
                 _ => base_color,
             }
@@ -160,6 +226,10 @@ fn write_ins(
         } else {
             Color32::TRANSPARENT
         });
+    let space_width = ui.fonts(|f| f.glyph_width(&appearance.code_font, ' '));
+    // Indent delay slots under their branch
+    let indent = if ins.delay_slot.is_some() { 1 } else { 0 };
+    ui.add_space(space_width * indent as f32);
     let response = Label::new(op_label).sense(Sense::click()).ui(ui);
     response.context_menu(|ui| ins_context_menu(ui, ins));
     if response.clicked() {
@@ -169,8 +239,8 @@ fn write_ins(
             ins_view_state.highlight = HighlightKind::Opcode(ins.op);
         }
     }
-    let space_width = ui.fonts(|f| f.glyph_width(&appearance.code_font, ' '));
-    ui.add_space(space_width * (max(11, ins.mnemonic.len()) - ins.mnemonic.len()) as f32);
+    let width = ins.mnemonic.len() + indent;
+    ui.add_space(space_width * (max(11, width) - width) as f32);
 
     let mut writing_offset = false;
     for (i, arg) in ins.args.iter().enumerate() {
@@ -194,7 +264,7 @@ fn write_ins(
         };
         let color = if highlighted_arg {
             appearance.emphasized_text_color
-        } else if let Some(diff) = args.get(i).and_then(|a| a.as_ref()) {
+        } else if let Some(diff) = ins_diff.arg_diff.get(i).and_then(|a| a.as_ref()) {
             appearance.diff_colors[diff.idx % appearance.diff_colors.len()]
         } else {
             base_color
@@ -221,6 +291,7 @@ fn write_ins(
             }
             ObjInsArg::Reloc => {
                 write_reloc(
+                    arch,
                     ins.reloc.as_ref().unwrap(),
                     base_color,
                     text_format.background,
@@ -230,6 +301,7 @@ fn write_ins(
             }
             ObjInsArg::RelocWithBase => {
                 write_reloc(
+                    arch,
                     ins.reloc.as_ref().unwrap(),
                     base_color,
                     text_format.background,
@@ -270,17 +342,42 @@ fn write_ins(
     }
 }
 
-fn ins_hover_ui(ui: &mut egui::Ui, ins: &ObjIns, appearance: &Appearance) {
+fn ins_hover_ui(
+    ui: &mut egui::Ui,
+    arch: &dyn ObjArch,
+    func: Option<&ObjFunctionInfo>,
+    ins_diff: &ObjInsDiff,
+    ins: &ObjIns,
+    appearance: &Appearance,
+) {
     ui.scope(|ui| {
         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
         ui.style_mut().wrap = Some(false);
 
         ui.label(format!("{:02X?}", ins.code.to_be_bytes()));
 
+        if ins_diff.padding {
+            ui.colored_label(appearance.deemphasized_text_color, "Alignment padding");
+        }
+
         if let Some(orig) = &ins.orig {
             ui.label(format!("Original: {}", orig));
         }
 
+        match ins.delay_slot {
+            Some(ObjDelaySlot::Always) => {
+                ui.label("Delay slot");
+            }
+            Some(ObjDelaySlot::Likely) => {
+                ui.label("Delay slot, executed only if the branch is taken");
+            }
+            None => {}
+        }
+
+        if let Some(func) = func {
+            variables_hover_ui(ui, arch, func, ins, appearance);
+        }
+
         for arg in &ins.args {
             if let ObjInsArg::Arg(arg) | ObjInsArg::ArgWithBase(arg) = arg {
                 match arg {
@@ -296,10 +393,23 @@ fn ins_hover_ui(ui: &mut egui::Ui, ins: &ObjIns, appearance: &Appearance) {
         }
 
         if let Some(reloc) = &ins.reloc {
-            ui.label(format!("Relocation type: {:?}", reloc.kind));
+            if let Some(name) = arch.reloc_name(reloc.kind) {
+                ui.label(format!("Relocation type: {name}"));
+            } else {
+                ui.label(format!("Relocation type: {:?}", reloc.kind));
+            }
             ui.colored_label(appearance.highlight_color, format!("Name: {}", reloc.target.name));
+            if reloc.unpaired {
+                ui.colored_label(appearance.delete_color, "Unpaired: no matching relocation half");
+            }
             if let Some(section) = &reloc.target_section {
                 ui.colored_label(appearance.highlight_color, format!("Section: {section}"));
+                if let Some(base) = reloc.sda_base {
+                    ui.colored_label(
+                        appearance.highlight_color,
+                        format!("Base: {} ({section})", base.register()),
+                    );
+                }
                 ui.colored_label(
                     appearance.highlight_color,
                     format!("Address: {:x}", reloc.target.address),
@@ -310,11 +420,79 @@ fn ins_hover_ui(ui: &mut egui::Ui, ins: &ObjIns, appearance: &Appearance) {
                 );
             } else {
                 ui.colored_label(appearance.highlight_color, "Extern".to_string());
+                if let Some(base) = reloc.sda_base {
+                    ui.colored_label(
+                        appearance.highlight_color,
+                        format!("Base: {} (extern)", base.register()),
+                    );
+                }
             }
         }
     });
 }
 
+/// Shows the inlined calls containing an instruction and the variables its
+/// operands hold.
+fn variables_hover_ui(
+    ui: &mut egui::Ui,
+    arch: &dyn ObjArch,
+    func: &ObjFunctionInfo,
+    ins: &ObjIns,
+    appearance: &Appearance,
+) {
+    for call in func.inlined_at(ins.address as u64) {
+        let indent = "  ".repeat(call.depth);
+        match (&call.call_file, call.call_line) {
+            (Some(file), Some(line)) => {
+                ui.label(format!("{indent}Inlined {} from {file}:{line}", call.name))
+            }
+            _ => ui.label(format!("{indent}Inlined {}", call.name)),
+        };
+    }
+    // Both halves of a memory operand hold the same variables
+    let mut variables = (0..ins.args.len())
+        .flat_map(|idx| func.operand_variables(arch, ins, idx))
+        .collect::<Vec<_>>();
+    variables.dedup_by(|a, b| std::ptr::eq(a.0, b.0) && a.1 == b.1);
+    for (var, location) in variables {
+        let location = match location {
+            ObjLocation::Register(register) => {
+                arch.dwarf_register_name(register).unwrap_or_else(|| format!("reg{register}"))
+            }
+            ObjLocation::Memory { register, offset } => {
+                let register =
+                    arch.dwarf_register_name(register).unwrap_or_else(|| format!("reg{register}"));
+                if offset < 0 {
+                    format!("-{:#x}({register})", -offset)
+                } else {
+                    format!("{offset:#x}({register})")
+                }
+            }
+            ObjLocation::Address(address) => format!("{address:#x}"),
+        };
+        let text = match &var.inlined_from {
+            Some(name) => format!("{location}: {} (inlined {name})", var.declaration),
+            None => format!("{location}: {}", var.declaration),
+        };
+        ui.colored_label(appearance.highlight_color, text);
+    }
+}
+
+fn jump_table_hover_ui(ui: &mut egui::Ui, table: &ObjInsJumpTable, appearance: &Appearance) {
+    ui.scope(|ui| {
+        ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+        ui.style_mut().wrap = Some(false);
+
+        ui.label(format!("Jump table {} ({} entries)", table.name, table.targets.len()));
+        for (i, (target, branch)) in table.targets.iter().zip(&table.branches).enumerate() {
+            let color = branch.as_ref().map_or(appearance.delete_color, |b| {
+                appearance.diff_colors[b.branch_idx % appearance.diff_colors.len()]
+            });
+            ui.colored_label(color, format!("{i}: {target:x}"));
+        }
+    });
+}
+
 fn ins_context_menu(ui: &mut egui::Ui, ins: &ObjIns) {
     ui.scope(|ui| {
         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
@@ -364,14 +542,22 @@ fn ins_context_menu(ui: &mut egui::Ui, ins: &ObjIns) {
     });
 }
 
-fn find_symbol<'a>(obj: &'a ObjInfo, selected_symbol: &SymbolReference) -> Option<&'a ObjSymbol> {
+pub fn find_symbol<'a>(
+    obj: &'a ObjInfo,
+    selected_symbol: &SymbolReference,
+) -> Option<&'a ObjSymbol> {
     obj.sections.iter().find_map(|section| {
         section.symbols.iter().find(|symbol| symbol.name == selected_symbol.symbol_name)
     })
 }
 
+fn find_function_info<'a>(obj: &'a ObjInfo, symbol: &ObjSymbol) -> Option<&'a ObjFunctionInfo> {
+    obj.debug_info.as_ref()?.function(symbol)
+}
+
 fn asm_row_ui(
     ui: &mut egui::Ui,
+    arch: &dyn ObjArch,
     ins_diff: &ObjInsDiff,
     symbol: &ObjSymbol,
     appearance: &Appearance,
@@ -387,14 +573,7 @@ fn asm_row_ui(
         return;
     };
 
-    let base_color = match ins_diff.kind {
-        ObjInsDiffKind::None | ObjInsDiffKind::OpMismatch | ObjInsDiffKind::ArgMismatch => {
-            appearance.text_color
-        }
-        ObjInsDiffKind::Replace => appearance.replace_color,
-        ObjInsDiffKind::Delete => appearance.delete_color,
-        ObjInsDiffKind::Insert => appearance.insert_color,
-    };
+    let base_color = row_color(ins_diff, appearance);
     let mut pad = 6;
     if let Some(line) = ins.line {
         let line_str = format!("{line} ");
@@ -455,7 +634,7 @@ fn asm_row_ui(
         });
     }
     Label::new(job).selectable(false).ui(ui);
-    write_ins(ins, &ins_diff.kind, &ins_diff.arg_diff, base_addr, ui, appearance, ins_view_state);
+    write_ins(arch, ins, ins_diff, base_addr, ui, appearance, ins_view_state);
     if let Some(branch) = &ins_diff.branch_to {
         let mut job = LayoutJob::default();
         write_text(
@@ -466,20 +645,60 @@ fn asm_row_ui(
         );
         Label::new(job).selectable(false).ui(ui);
     }
+    if let Some(table) = &ins_diff.jump_table {
+        let mut job = LayoutJob::default();
+        let mut branch_idxs =
+            table.branches.iter().flatten().map(|b| b.branch_idx).collect::<Vec<_>>();
+        branch_idxs.sort_unstable();
+        branch_idxs.dedup();
+        write_text(" ~>", appearance.text_color, &mut job, appearance.code_font.clone());
+        for branch_idx in branch_idxs {
+            write_text(
+                "*",
+                appearance.diff_colors[branch_idx % appearance.diff_colors.len()],
+                &mut job,
+                appearance.code_font.clone(),
+            );
+        }
+        Label::new(job)
+            .selectable(false)
+            .ui(ui)
+            .on_hover_ui(|ui| jump_table_hover_ui(ui, table, appearance));
+    }
+    if let Some(moved) = &ins_diff.moved {
+        let mut job = LayoutJob::default();
+        write_text(
+            " <>",
+            appearance.diff_colors[moved.move_idx % appearance.diff_colors.len()],
+            &mut job,
+            appearance.code_font.clone(),
+        );
+        let response =
+            Label::new(job).sense(Sense::click()).selectable(false).ui(ui).on_hover_text(format!(
+                "Moved to or from row {}, click to scroll to it",
+                moved.ins_idx
+            ));
+        if response.clicked() {
+            ins_view_state.scroll_to_row = Some(moved.ins_idx);
+        }
+    }
 }
 
 fn asm_col_ui(
     row: &mut TableRow<'_, '_>,
+    arch: &dyn ObjArch,
+    func: Option<&ObjFunctionInfo>,
     ins_diff: &ObjInsDiff,
     symbol: &ObjSymbol,
     appearance: &Appearance,
     ins_view_state: &mut FunctionViewState,
 ) {
     let (_, response) = row.col(|ui| {
-        asm_row_ui(ui, ins_diff, symbol, appearance, ins_view_state);
+        asm_row_ui(ui, arch, ins_diff, symbol, appearance, ins_view_state);
     });
     if let Some(ins) = &ins_diff.ins {
-        response.on_hover_ui_at_pointer(|ui| ins_hover_ui(ui, ins, appearance));
+        response
+            .on_hover_ui_at_pointer(|ui| ins_hover_ui(ui, arch, func, ins_diff, ins, appearance));
         // .context_menu(|ui| ins_context_menu(ui, ins));
     }
 }
@@ -490,23 +709,40 @@ fn empty_col_ui(row: &mut TableRow<'_, '_>) {
     });
 }
 
-fn asm_table_ui(
+#[allow(clippy::too_many_arguments)]
+fn asm_table_ui<'a>(
     table: TableBuilder<'_>,
-    left_obj: Option<&ObjInfo>,
-    right_obj: Option<&ObjInfo>,
+    left_obj: Option<&'a ObjInfo>,
+    right_obj: Option<&'a ObjInfo>,
     selected_symbol: &SymbolReference,
+    rows: &[AsmRow],
+    sources: &SourceFiles,
     appearance: &Appearance,
     ins_view_state: &mut FunctionViewState,
 ) -> Option<()> {
-    let left_symbol = left_obj.and_then(|obj| find_symbol(obj, selected_symbol));
-    let right_symbol = right_obj.and_then(|obj| find_symbol(obj, selected_symbol));
-    let instructions_len = left_symbol.or(right_symbol).map(|s| s.instructions.len())?;
+    let find = |obj: &'a ObjInfo| {
+        let symbol = find_symbol(obj, selected_symbol)?;
+        Some((obj.arch.as_ref(), find_function_info(obj, symbol), symbol))
+    };
+    let left_symbol = left_obj.and_then(find);
+    let right_symbol = right_obj.and_then(find);
     table.body(|body| {
-        body.rows(appearance.code_font.size, instructions_len, |mut row| {
-            let row_index = row.index();
-            if let Some(symbol) = left_symbol {
+        body.rows(appearance.code_font.size, rows.len(), |mut row| {
+            let row_index = match rows[row.index()] {
+                AsmRow::Ins(row_index) => row_index,
+                asm_row => {
+                    empty_col_ui(&mut row);
+                    if let Some(line_info) = right_obj.and_then(|obj| obj.line_info.as_ref()) {
+                        source_col_ui(&mut row, line_info, sources, asm_row, appearance);
+                    }
+                    return;
+                }
+            };
+            if let Some((arch, func, symbol)) = left_symbol {
                 asm_col_ui(
                     &mut row,
+                    arch,
+                    func,
                     &symbol.instructions[row_index],
                     symbol,
                     appearance,
@@ -515,9 +751,11 @@ fn asm_table_ui(
             } else {
                 empty_col_ui(&mut row);
             }
-            if let Some(symbol) = right_symbol {
+            if let Some((arch, func, symbol)) = right_symbol {
                 asm_col_ui(
                     &mut row,
+                    arch,
+                    func,
                     &symbol.instructions[row_index],
                     symbol,
                     appearance,
@@ -531,11 +769,112 @@ fn asm_table_ui(
     Some(())
 }
 
+fn block_summary_ui(ui: &mut egui::Ui, blocks: &[ObjBlockDiff], appearance: &Appearance) {
+    ui.scope(|ui| {
+        ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+        ui.style_mut().wrap = Some(false);
+
+        for (i, block) in blocks.iter().enumerate() {
+            let rows = format!("Block {i}: rows {}-{}", block.rows.start, block.rows.end - 1);
+            if block.matched {
+                ui.colored_label(
+                    match_color_for_symbol(block.match_percent, appearance),
+                    format!("{rows}: {:.0}%", block.match_percent),
+                );
+            } else {
+                ui.colored_label(appearance.delete_color, format!("{rows}: unmatched"));
+            }
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn three_way_col_ui(
+    row: &mut TableRow<'_, '_>,
+    arch: &dyn ObjArch,
+    func: Option<&ObjFunctionInfo>,
+    ins_diff: &ObjInsDiff,
+    symbol: &ObjSymbol,
+    kind: ThreeWayKind,
+    appearance: &Appearance,
+    ins_view_state: &mut FunctionViewState,
+) {
+    let (_, response) = row.col(|ui| {
+        let color = match kind {
+            ThreeWayKind::None => Color32::TRANSPARENT,
+            ThreeWayKind::Closer => appearance.insert_color.gamma_multiply(0.15),
+            ThreeWayKind::Further => appearance.delete_color.gamma_multiply(0.15),
+        };
+        ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, color);
+        asm_row_ui(ui, arch, ins_diff, symbol, appearance, ins_view_state);
+    });
+    if let Some(ins) = &ins_diff.ins {
+        response
+            .on_hover_ui_at_pointer(|ui| ins_hover_ui(ui, arch, func, ins_diff, ins, appearance));
+    }
+}
+
+fn three_way_table_ui(
+    table: TableBuilder<'_>,
+    result: &ObjDiffResult,
+    three_way: Option<&ThreeWayState>,
+    selected_symbol: &SymbolReference,
+    appearance: &Appearance,
+    ins_view_state: &mut FunctionViewState,
+) -> Option<()> {
+    fn find<'a>(
+        obj: &'a Option<Arc<ObjInfo>>,
+        selected_symbol: &SymbolReference,
+    ) -> Option<(&'a dyn ObjArch, Option<&'a ObjFunctionInfo>, &'a ObjSymbol)> {
+        let obj = obj.as_ref()?;
+        let symbol = find_symbol(obj, selected_symbol)?;
+        Some((obj.arch.as_ref(), find_function_info(obj, symbol), symbol))
+    }
+    let diff = &three_way?.diff;
+    let columns = [
+        (find(&result.first_obj, selected_symbol)?, &diff.target, "Target"),
+        (find(&result.prev_base, selected_symbol)?, &diff.prev, "Previous base"),
+        (find(&result.second_obj, selected_symbol)?, &diff.base, "Current base"),
+    ];
+    table
+        .header(appearance.ui_font.size * 1.5, |mut header| {
+            for (_, _, title) in &columns {
+                header.col(|ui| {
+                    ui.label(*title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(appearance.code_font.size, diff.kinds.len(), |mut row| {
+                let row_index = row.index();
+                let kind = diff.kinds[row_index];
+                for ((arch, func, symbol), instructions, _) in &columns {
+                    three_way_col_ui(
+                        &mut row,
+                        *arch,
+                        *func,
+                        &instructions[row_index],
+                        symbol,
+                        kind,
+                        appearance,
+                        ins_view_state,
+                    );
+                }
+            });
+        });
+    Some(())
+}
+
 pub fn function_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance: &Appearance) {
     let (Some(result), Some(selected_symbol)) = (&state.build, &state.symbol_state.selected_symbol)
     else {
         return;
     };
+    let prev_diff = state.prev_diff.as_ref().filter(|prev| prev.is_active(View::FunctionDiff));
+    let (left_obj, right_obj) = match prev_diff {
+        Some(prev_diff) => (Some(&prev_diff.prev_obj), Some(&prev_diff.base_obj)),
+        None => (result.first_obj.as_deref(), result.second_obj.as_deref()),
+    };
 
     // Header
     let available_width = ui.available_width();
@@ -567,6 +906,60 @@ pub fn function_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance
                         {
                             state.queue_scratch = true;
                         }
+                        ui.separator();
+                        let mut compare_prev = state.compare_mode != CompareMode::Target;
+                        if ui
+                            .add_enabled(
+                                result.prev_base.is_some(),
+                                egui::Checkbox::new(&mut compare_prev, "Previous build"),
+                            )
+                            .on_hover_text_at_pointer(
+                                "Diff the base against its previous build instead of the target",
+                            )
+                            .on_disabled_hover_text("No previous build of the base")
+                            .changed()
+                        {
+                            state.compare_mode = if compare_prev {
+                                CompareMode::PrevFunction
+                            } else {
+                                CompareMode::Target
+                            };
+                        }
+                        if compare_prev {
+                            ui.selectable_value(
+                                &mut state.compare_mode,
+                                CompareMode::PrevFunction,
+                                "Function",
+                            );
+                            ui.selectable_value(
+                                &mut state.compare_mode,
+                                CompareMode::PrevUnit,
+                                "Unit",
+                            );
+                            ui.selectable_value(
+                                &mut state.compare_mode,
+                                CompareMode::ThreeWay,
+                                "3-way",
+                            )
+                            .on_hover_text_at_pointer("Target, previous base and current base");
+                        }
+                        ui.separator();
+                        ui.add_enabled(
+                            state.compare_mode != CompareMode::ThreeWay,
+                            egui::Checkbox::new(&mut state.function_state.show_graph, "Graph"),
+                        )
+                        .on_hover_text_at_pointer(
+                            "Show the control flow graph; click a block to scroll to it",
+                        );
+                        ui.add_enabled(
+                            state.compare_mode != CompareMode::ThreeWay
+                                && right_obj.is_some_and(|obj| obj.line_info.is_some()),
+                            egui::Checkbox::new(&mut state.function_state.show_source, "Source"),
+                        )
+                        .on_hover_text_at_pointer(
+                            "Show the base's source lines above their instructions",
+                        )
+                        .on_disabled_hover_text("No line information in the base object");
                     });
 
                     let name = selected_symbol
@@ -581,11 +974,24 @@ pub fn function_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance
                     );
                     job.wrap.break_anywhere = true;
                     job.wrap.max_rows = 1;
-                    ui.label(job);
+                    let response = ui.label(job);
+                    let func = [right_obj, left_obj].into_iter().flatten().find_map(|obj| {
+                        find_function_info(obj, find_symbol(obj, selected_symbol)?)
+                    });
+                    if let Some(func) = func {
+                        response.on_hover_ui_at_pointer(|ui| {
+                            ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                            ui.label(&func.prototype);
+                        });
+                    }
 
                     ui.scope(|ui| {
                         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
-                        ui.label("Diff target:");
+                        if prev_diff.is_some() {
+                            ui.label("Previous base:");
+                        } else {
+                            ui.label("Diff target:");
+                        }
                     });
                 },
             );
@@ -626,15 +1032,43 @@ pub fn function_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance
 
                     ui.scope(|ui| {
                         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
-                        if let Some(match_percent) = result
-                            .second_obj
-                            .as_ref()
-                            .and_then(|obj| find_symbol(obj, selected_symbol))
-                            .and_then(|symbol| symbol.match_percent)
+                        let symbol = right_obj.and_then(|obj| find_symbol(obj, selected_symbol));
+                        if let Some(match_percent) = symbol.and_then(|symbol| symbol.match_percent)
                         {
-                            ui.colored_label(
-                                match_color_for_symbol(match_percent, appearance),
+                            let mut job = LayoutJob::default();
+                            write_text(
                                 &format!("{match_percent:.0}%"),
+                                match_color_for_symbol(match_percent, appearance),
+                                &mut job,
+                                appearance.code_font.clone(),
+                            );
+                            if let Some(score) = symbol.and_then(|symbol| symbol.diff_score) {
+                                write_text(
+                                    &format!(" (score {score})"),
+                                    appearance.text_color,
+                                    &mut job,
+                                    appearance.code_font.clone(),
+                                );
+                            }
+                            let blocks =
+                                symbol.map(|symbol| symbol.blocks.as_slice()).unwrap_or_default();
+                            if blocks.is_empty() {
+                                ui.label(job);
+                            } else {
+                                let matched =
+                                    blocks.iter().filter(|b| b.match_percent == 100.0).count();
+                                write_text(
+                                    &format!(" {matched}/{} blocks", blocks.len()),
+                                    appearance.text_color,
+                                    &mut job,
+                                    appearance.code_font.clone(),
+                                );
+                                ui.label(job)
+                                    .on_hover_ui(|ui| block_summary_ui(ui, blocks, appearance));
+                            }
+                        } else if symbol.is_some_and(|symbol| symbol.truncated) {
+                            ui.colored_label(appearance.delete_color, "Timed out").on_hover_text(
+                                "Diff exceeded the time limit and may be incomplete",
                             );
                         } else {
                             ui.colored_label(appearance.replace_color, "Missing");
@@ -650,18 +1084,91 @@ pub fn function_diff_ui(ui: &mut egui::Ui, state: &mut DiffViewState, appearance
     // Table
     ui.style_mut().interaction.selectable_labels = false;
     let available_height = ui.available_height();
-    let table = TableBuilder::new(ui)
+    if state.compare_mode == CompareMode::ThreeWay {
+        let table = TableBuilder::new(ui)
+            .striped(false)
+            .cell_layout(Layout::left_to_right(Align::Min))
+            .columns(Column::exact(available_width / 3.0).clip(true), 3)
+            .resizable(false)
+            .auto_shrink([false, false])
+            .min_scrolled_height(available_height);
+        three_way_table_ui(
+            table,
+            result,
+            state.three_way.as_ref(),
+            selected_symbol,
+            appearance,
+            &mut state.function_state,
+        );
+        return;
+    }
+    let mut available_height = available_height;
+    if state.function_state.show_graph {
+        let graph_height = available_height * 0.4;
+        ui.allocate_ui_with_layout(
+            Vec2 { x: available_width, y: graph_height },
+            Layout::left_to_right(Align::Min),
+            |ui| {
+                ui.set_height(graph_height);
+                for (id_source, obj) in [("left_graph", left_obj), ("right_graph", right_obj)] {
+                    ui.allocate_ui_with_layout(
+                        Vec2 { x: column_width, y: graph_height },
+                        Layout::top_down(Align::Min),
+                        |ui| {
+                            ui.set_width(column_width);
+                            if let Some((obj, symbol)) =
+                                obj.and_then(|obj| Some((obj, find_symbol(obj, selected_symbol)?)))
+                            {
+                                function_graph_ui(
+                                    ui,
+                                    id_source,
+                                    obj.arch.as_ref(),
+                                    symbol,
+                                    appearance,
+                                    &mut state.function_state.scroll_to_row,
+                                );
+                            }
+                        },
+                    );
+                }
+            },
+        );
+        ui.separator();
+        available_height = ui.available_height();
+    }
+    let right_symbol = right_obj.and_then(|obj| find_symbol(obj, selected_symbol));
+    let line_info = right_obj.and_then(|obj| obj.line_info.as_ref());
+    let rows = match (right_symbol, line_info) {
+        (Some(symbol), Some(line_info)) if state.function_state.show_source => {
+            source_rows(symbol, line_info)
+        }
+        _ => {
+            let Some(symbol) =
+                right_symbol.or_else(|| left_obj.and_then(|obj| find_symbol(obj, selected_symbol)))
+            else {
+                return;
+            };
+            (0..symbol.instructions.len()).map(AsmRow::Ins).collect()
+        }
+    };
+    let mut table = TableBuilder::new(ui)
         .striped(false)
         .cell_layout(Layout::left_to_right(Align::Min))
         .columns(Column::exact(column_width).clip(true), 2)
         .resizable(false)
         .auto_shrink([false, false])
         .min_scrolled_height(available_height);
+    if let Some(ins_row) = state.function_state.scroll_to_row.take() {
+        let row = rows.iter().position(|row| matches!(row, AsmRow::Ins(idx) if *idx == ins_row));
+        table = table.scroll_to_row(row.unwrap_or(ins_row), Some(Align::Min));
+    }
     asm_table_ui(
         table,
-        result.first_obj.as_ref(),
-        result.second_obj.as_ref(),
+        left_obj,
+        right_obj,
         selected_symbol,
+        &rows,
+        &result.base_sources,
         appearance,
         &mut state.function_state,
     );
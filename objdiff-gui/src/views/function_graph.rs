@@ -0,0 +1,152 @@
+use egui::{Pos2, Rect, Sense, Shape, Stroke, Vec2};
+use objdiff_core::{
+    arch::ObjArch,
+    diff::cfg::{row_cfg, CfgBlock},
+    obj::ObjSymbol,
+};
+
+use crate::views::{appearance::Appearance, symbol_diff::match_color_for_symbol};
+
+const NODE_PADDING: Vec2 = Vec2::new(6.0, 4.0);
+const NODE_SPACING: Vec2 = Vec2::new(16.0, 24.0);
+const ARROW_SIZE: f32 = 5.0;
+
+/// Assigns each block a layer, placing it below every block that branches
+/// forward to it. Blocks are in address order, so forward edges point to
+/// higher indices.
+fn block_layers(blocks: &[CfgBlock]) -> Vec<usize> {
+    let mut layers = vec![0usize; blocks.len()];
+    for (idx, block) in blocks.iter().enumerate() {
+        for &succ in &block.successors {
+            if succ > idx {
+                layers[succ] = layers[succ].max(layers[idx] + 1);
+            }
+        }
+    }
+    layers
+}
+
+fn block_label(symbol: &ObjSymbol, block: &CfgBlock) -> String {
+    let address = symbol.instructions[block.rows.clone()]
+        .iter()
+        .find_map(|row| row.ins.as_ref())
+        .map(|ins| ins.address as u64 - symbol.address);
+    match address {
+        Some(address) => format!("{address:x} ({})", block.rows.len()),
+        None => format!("- ({})", block.rows.len()),
+    }
+}
+
+fn draw_arrow(shapes: &mut Vec<Shape>, points: Vec<Pos2>, stroke: Stroke) {
+    let [.., from, tip] = points[..] else {
+        return;
+    };
+    let dir = (tip - from).normalized() * ARROW_SIZE;
+    let side = dir.rot90() * 0.5;
+    shapes.push(Shape::convex_polygon(
+        vec![tip, tip - dir + side, tip - dir - side],
+        stroke.color,
+        Stroke::NONE,
+    ));
+    shapes.push(Shape::line(points, stroke));
+}
+
+/// Draws the control flow graph of a diffed function, with blocks coloured by
+/// their match percentage. Clicking a block sets `scroll_to_row` to its first row.
+pub fn function_graph_ui(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    arch: &dyn ObjArch,
+    symbol: &ObjSymbol,
+    appearance: &Appearance,
+    scroll_to_row: &mut Option<usize>,
+) {
+    let blocks = row_cfg(arch, &symbol.instructions);
+    let layers = block_layers(&blocks);
+    let galleys = blocks
+        .iter()
+        .map(|block| {
+            ui.painter().layout_no_wrap(
+                block_label(symbol, block),
+                appearance.code_font.clone(),
+                appearance.text_color,
+            )
+        })
+        .collect::<Vec<_>>();
+    let node_size = galleys.iter().fold(Vec2::ZERO, |size, galley| size.max(galley.size()))
+        + NODE_PADDING * 2.0;
+    let step = node_size + NODE_SPACING;
+
+    // Place blocks left to right within their layer
+    let layer_count = layers.iter().max().map_or(0, |&max| max + 1);
+    let mut layer_width = vec![0usize; layer_count];
+    let offsets = layers
+        .iter()
+        .map(|&layer| {
+            let column = layer_width[layer];
+            layer_width[layer] += 1;
+            Vec2::new(column as f32 * step.x, layer as f32 * step.y)
+        })
+        .collect::<Vec<_>>();
+    let columns = layer_width.iter().copied().max().unwrap_or(0);
+    // Leave room on the right for back edges
+    let size = Vec2::new(
+        columns as f32 * step.x + NODE_SPACING.x * 2.0,
+        layer_count as f32 * step.y + NODE_SPACING.y,
+    );
+
+    egui::ScrollArea::both().id_source(id_source).auto_shrink([false, false]).show(ui, |ui| {
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+        let origin = rect.min + NODE_SPACING * 0.5;
+        let node_rects = offsets
+            .iter()
+            .map(|&offset| Rect::from_min_size(origin + offset, node_size))
+            .collect::<Vec<_>>();
+
+        let mut shapes = Vec::new();
+        let edge_stroke = Stroke::new(1.0, appearance.deemphasized_text_color);
+        for (idx, block) in blocks.iter().enumerate() {
+            let from = node_rects[idx];
+            for &succ in &block.successors {
+                let to = node_rects[succ];
+                let points = if succ > idx {
+                    vec![from.center_bottom(), to.center_top()]
+                } else {
+                    // Back edges loop around the right of both blocks
+                    let x = from.right().max(to.right()) + NODE_SPACING.x * 0.5;
+                    vec![
+                        from.right_center(),
+                        Pos2::new(x, from.center().y),
+                        Pos2::new(x, to.center().y),
+                        to.right_center(),
+                    ]
+                };
+                draw_arrow(&mut shapes, points, edge_stroke);
+            }
+        }
+        ui.painter().extend(shapes);
+
+        for (idx, (block, galley)) in blocks.iter().zip(galleys).enumerate() {
+            let node_rect = node_rects[idx];
+            let color = match_color_for_symbol(block.match_percent, appearance);
+            let response =
+                ui.interact(node_rect, ui.id().with(idx), Sense::click()).on_hover_text(format!(
+                    "Rows {}-{}: {:.0}%",
+                    block.rows.start,
+                    block.rows.end - 1,
+                    block.match_percent
+                ));
+            let stroke_width = if response.hovered() { 2.0 } else { 1.0 };
+            ui.painter().rect(
+                node_rect,
+                2.0,
+                color.gamma_multiply(0.15),
+                Stroke::new(stroke_width, color),
+            );
+            ui.painter().galley(node_rect.min + NODE_PADDING, galley, appearance.text_color);
+            if response.clicked() {
+                *scroll_to_row = Some(block.rows.start);
+            }
+        }
+    });
+}
@@ -16,9 +16,7 @@ pub fn jobs_ui(ui: &mut egui::Ui, jobs: &mut JobQueue, appearance: &Appearance)
                 if ui.small_button("✖").clicked() {
                     if job.handle.is_some() {
                         job.should_remove = true;
-                        if let Err(e) = job.cancel.send(()) {
-                            log::error!("Failed to cancel job: {e:?}");
-                        }
+                        job.cancel.cancel();
                     } else {
                         remove_job = Some(job.id);
                     }
@@ -4,10 +4,12 @@ pub(crate) mod appearance;
 pub(crate) mod config;
 pub(crate) mod data_diff;
 pub(crate) mod debug;
+pub(crate) mod debug_info_diff;
 pub(crate) mod demangle;
 pub(crate) mod file;
 pub(crate) mod frame_history;
 pub(crate) mod function_diff;
+pub(crate) mod function_graph;
 pub(crate) mod jobs;
 pub(crate) mod symbol_diff;
 
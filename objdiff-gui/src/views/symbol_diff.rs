@@ -1,20 +1,30 @@
-use std::mem::take;
+use std::{
+    mem::take,
+    sync::{Arc, Mutex},
+};
 
 use egui::{
     text::LayoutJob, Align, CollapsingHeader, Color32, Id, Layout, OpenUrl, ScrollArea,
     SelectableLabel, TextEdit, Ui, Vec2, Widget,
 };
 use egui_extras::{Size, StripBuilder};
-use objdiff_core::obj::{ObjInfo, ObjSection, ObjSectionKind, ObjSymbol, ObjSymbolFlags};
+use objdiff_core::{
+    diff::cache::DiffCache,
+    obj::{ObjDebugInfo, ObjInfo, ObjSection, ObjSectionKind, ObjSymbol, ObjSymbolFlags},
+};
 
 use crate::{
     app::AppConfigRef,
     jobs::{
         create_scratch::{start_create_scratch, CreateScratchConfig, CreateScratchResult},
         objdiff::{BuildStatus, ObjDiffResult},
+        prev_diff::{start_prev_diff, PrevDiff, PrevDiffConfig, PrevDiffResult, ThreeWayState},
         Job, JobQueue, JobResult,
     },
-    views::{appearance::Appearance, function_diff::FunctionViewState, write_text},
+    views::{
+        appearance::Appearance, debug_info_diff::DebugInfoViewState,
+        function_diff::FunctionViewState, write_text,
+    },
 };
 
 pub struct SymbolReference {
@@ -30,15 +40,37 @@ pub enum View {
     SymbolDiff,
     FunctionDiff,
     DataDiff,
+    DebugInfoDiff,
+}
+
+/// What the base object is compared against.
+#[derive(Default, Eq, PartialEq, Copy, Clone)]
+pub enum CompareMode {
+    #[default]
+    Target,
+    /// Previous build of the base, for the selected function only
+    PrevFunction,
+    /// Previous build of the base, for the whole unit
+    PrevUnit,
+    /// Target, previous and current base side by side, for the selected function
+    ThreeWay,
 }
 
 #[derive(Default)]
 pub struct DiffViewState {
     pub build: Option<Box<ObjDiffResult>>,
+    pub compare_mode: CompareMode,
+    pub prev_diff: Option<PrevDiff>,
+    /// Compare mode and symbol that `prev_diff` or `three_way` was last requested for
+    prev_diff_key: Option<(CompareMode, Option<String>)>,
+    pub three_way: Option<ThreeWayState>,
+    /// Function diffs from previous builds of the selected object
+    pub diff_cache: Arc<Mutex<DiffCache>>,
     pub scratch: Option<Box<CreateScratchResult>>,
     pub current_view: View,
     pub symbol_state: SymbolViewState,
     pub function_state: FunctionViewState,
+    pub debug_info_state: DebugInfoViewState,
     pub search: String,
     pub queue_build: bool,
     pub build_running: bool,
@@ -54,13 +86,40 @@ pub struct SymbolViewState {
     pub reverse_fn_order: bool,
     pub disable_reverse_fn_order: bool,
     pub show_hidden_symbols: bool,
+    pub sort_by_score: bool,
 }
 
 impl DiffViewState {
     pub fn pre_update(&mut self, jobs: &mut JobQueue, config: &AppConfigRef) {
         jobs.results.retain_mut(|result| match result {
             JobResult::ObjDiff(result) => {
+                let prev = take(&mut self.build);
                 self.build = take(result);
+                if let (Some(prev), Some(build)) = (prev, &mut self.build) {
+                    build.prev_base = snapshot_prev_base(*prev, build.second_obj.as_ref());
+                }
+                self.prev_diff = None;
+                self.prev_diff_key = None;
+                self.three_way = None;
+                false
+            }
+            JobResult::PrevDiff(result) => {
+                // Results of superseded requests are dropped
+                let key = self.prev_diff_key.as_ref();
+                match take(result) {
+                    Some(PrevDiffResult::Prev(diff))
+                        if key == Some(&(diff.mode, diff.symbol_name.clone())) =>
+                    {
+                        self.prev_diff = Some(*diff);
+                    }
+                    Some(PrevDiffResult::ThreeWay(state))
+                        if key
+                            == Some(&(CompareMode::ThreeWay, Some(state.symbol_name.clone()))) =>
+                    {
+                        self.three_way = Some(state);
+                    }
+                    _ => {}
+                }
                 false
             }
             JobResult::CreateScratch(result) => {
@@ -84,7 +143,45 @@ impl DiffViewState {
         }
     }
 
+    /// Starts diffing against the previous build when the compare mode or the
+    /// selected symbol changed. The result is picked up by `pre_update`.
+    fn update_prev_diff(&mut self, ctx: &egui::Context, jobs: &mut JobQueue) {
+        let selected = self.symbol_state.selected_symbol.as_ref().map(|s| s.symbol_name.clone());
+        let key = match self.compare_mode {
+            CompareMode::Target => None,
+            mode @ (CompareMode::PrevFunction | CompareMode::ThreeWay) => {
+                selected.map(|name| (mode, Some(name)))
+            }
+            CompareMode::PrevUnit => Some((CompareMode::PrevUnit, None)),
+        };
+        if key == self.prev_diff_key {
+            return;
+        }
+        self.prev_diff = None;
+        self.three_way = None;
+        self.prev_diff_key.clone_from(&key);
+        for job in jobs.iter_mut().filter(|job| job.kind == Job::PrevDiff) {
+            job.cancel.cancel();
+        }
+        let (Some((mode, symbol_name)), Some(result)) = (key, &self.build) else {
+            return;
+        };
+        let (Some(prev_obj), Some(base_obj)) = (&result.prev_base, &result.second_obj) else {
+            return;
+        };
+        jobs.push(start_prev_diff(ctx, PrevDiffConfig {
+            mode,
+            symbol_name,
+            target_obj: result.first_obj.clone(),
+            prev_obj: prev_obj.clone(),
+            base_obj: base_obj.clone(),
+            diff_config: result.diff_config.clone(),
+        }));
+    }
+
     pub fn post_update(&mut self, ctx: &egui::Context, jobs: &mut JobQueue, config: &AppConfigRef) {
+        self.update_prev_diff(ctx, jobs);
+
         if let Some(result) = take(&mut self.scratch) {
             ctx.output_mut(|o| o.open_url = Some(OpenUrl::new_tab(result.scratch_url)));
         }
@@ -128,7 +225,12 @@ pub fn match_color_for_symbol(match_percent: f32, appearance: &Appearance) -> Co
     }
 }
 
-fn symbol_context_menu_ui(ui: &mut Ui, symbol: &ObjSymbol) {
+fn symbol_context_menu_ui(
+    ui: &mut Ui,
+    symbol: &ObjSymbol,
+    kind: ObjSectionKind,
+    debug_info: Option<&ObjDebugInfo>,
+) {
     ui.scope(|ui| {
         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
         ui.style_mut().wrap = Some(false);
@@ -143,6 +245,25 @@ fn symbol_context_menu_ui(ui: &mut Ui, symbol: &ObjSymbol) {
             ui.output_mut(|output| output.copied_text = symbol.name.clone());
             ui.close_menu();
         }
+        // Declarations come from the target's debug information
+        let Some(debug_info) = debug_info else {
+            return;
+        };
+        if let Some(declaration) = debug_info.symbol_declaration(symbol, kind) {
+            if ui.button("Copy declaration").on_hover_text(declaration.trim_end()).clicked() {
+                ui.output_mut(|output| output.copied_text = declaration);
+                ui.close_menu();
+            }
+            if ui
+                .button("Copy declaration with types")
+                .on_hover_text("Includes the definitions of the types it uses")
+                .clicked()
+            {
+                let header = debug_info.symbol_header(symbol, kind).unwrap_or_default();
+                ui.output_mut(|output| output.copied_text = header);
+                ui.close_menu();
+            }
+        }
     });
 }
 
@@ -161,6 +282,12 @@ fn symbol_hover_ui(ui: &mut Ui, symbol: &ObjSymbol, appearance: &Appearance) {
                 format!("Size: {:x} (assumed)", symbol.size),
             );
         }
+        if let Some(score) = symbol.diff_score {
+            ui.colored_label(appearance.highlight_color, format!("Score: {score}"));
+        }
+        if symbol.truncated {
+            ui.colored_label(appearance.delete_color, "Diff exceeded the time limit");
+        }
     });
 }
 
@@ -169,6 +296,7 @@ fn symbol_ui(
     ui: &mut Ui,
     symbol: &ObjSymbol,
     section: Option<&ObjSection>,
+    debug_info: Option<&ObjDebugInfo>,
     state: &mut SymbolViewState,
     appearance: &Appearance,
 ) -> Option<View> {
@@ -198,7 +326,11 @@ fn symbol_ui(
         write_text("h", appearance.deemphasized_text_color, &mut job, appearance.code_font.clone());
     }
     write_text("] ", appearance.text_color, &mut job, appearance.code_font.clone());
-    if let Some(match_percent) = symbol.match_percent {
+    if symbol.truncated {
+        write_text("(", appearance.text_color, &mut job, appearance.code_font.clone());
+        write_text("timeout", appearance.delete_color, &mut job, appearance.code_font.clone());
+        write_text(") ", appearance.text_color, &mut job, appearance.code_font.clone());
+    } else if let Some(match_percent) = symbol.match_percent {
         write_text("(", appearance.text_color, &mut job, appearance.code_font.clone());
         write_text(
             &format!("{match_percent:.0}%"),
@@ -212,7 +344,9 @@ fn symbol_ui(
     let response = SelectableLabel::new(selected, job)
         .ui(ui)
         .on_hover_ui_at_pointer(|ui| symbol_hover_ui(ui, symbol, appearance));
-    response.context_menu(|ui| symbol_context_menu_ui(ui, symbol));
+    // Common symbols are uninitialized data
+    let kind = section.map_or(ObjSectionKind::Bss, |section| section.kind);
+    response.context_menu(|ui| symbol_context_menu_ui(ui, symbol, kind, debug_info));
     if response.clicked() {
         if let Some(section) = section {
             if section.kind == ObjSectionKind::Code {
@@ -251,6 +385,7 @@ fn symbol_matches_search(symbol: &ObjSymbol, search_str: &str) -> bool {
 fn symbol_list_ui(
     ui: &mut Ui,
     obj: &ObjInfo,
+    debug_info: Option<&ObjDebugInfo>,
     state: &mut SymbolViewState,
     lower_search: &str,
     appearance: &Appearance,
@@ -264,7 +399,7 @@ fn symbol_list_ui(
             if !obj.common.is_empty() {
                 CollapsingHeader::new(".comm").default_open(true).show(ui, |ui| {
                     for symbol in &obj.common {
-                        ret = ret.or(symbol_ui(ui, symbol, None, state, appearance));
+                        ret = ret.or(symbol_ui(ui, symbol, None, debug_info, state, appearance));
                     }
                 });
             }
@@ -274,23 +409,33 @@ fn symbol_list_ui(
                     .id_source(Id::new(section.name.clone()).with(section.index))
                     .default_open(true)
                     .show(ui, |ui| {
-                        if section.kind == ObjSectionKind::Code && state.reverse_fn_order {
-                            for symbol in section.symbols.iter().rev() {
-                                if !symbol_matches_search(symbol, lower_search) {
-                                    continue;
-                                }
-                                ret =
-                                    ret.or(symbol_ui(ui, symbol, Some(section), state, appearance));
+                        let mut symbols = section
+                            .symbols
+                            .iter()
+                            .filter(|symbol| symbol_matches_search(symbol, lower_search))
+                            .collect::<Vec<_>>();
+                        if section.kind == ObjSectionKind::Code {
+                            if state.reverse_fn_order {
+                                symbols.reverse();
                             }
-                        } else {
-                            for symbol in &section.symbols {
-                                if !symbol_matches_search(symbol, lower_search) {
-                                    continue;
-                                }
-                                ret =
-                                    ret.or(symbol_ui(ui, symbol, Some(section), state, appearance));
+                            if state.sort_by_score {
+                                // Closest non-matching first, then matching and unpaired
+                                symbols.sort_by_key(|symbol| match symbol.diff_score {
+                                    Some(0) | None => u64::MAX,
+                                    Some(score) => score,
+                                });
                             }
                         }
+                        for symbol in symbols {
+                            ret = ret.or(symbol_ui(
+                                ui,
+                                symbol,
+                                Some(section),
+                                debug_info,
+                                state,
+                                appearance,
+                            ));
+                        }
                     });
             }
         });
@@ -321,6 +466,20 @@ fn build_log_ui(ui: &mut Ui, status: &BuildStatus, appearance: &Appearance) {
     });
 }
 
+/// Picks the base object to keep as the previous build. The snapshot is kept
+/// as is when the base did not change, e.g. when only the target was rebuilt.
+fn snapshot_prev_base(
+    prev: ObjDiffResult,
+    base_obj: Option<&Arc<ObjInfo>>,
+) -> Option<Arc<ObjInfo>> {
+    match (prev.second_obj, base_obj) {
+        (Some(prev_obj), Some(obj)) if prev_obj.path != obj.path => None,
+        (Some(prev_obj), Some(obj)) if prev_obj.timestamp == obj.timestamp => prev.prev_base,
+        (Some(prev_obj), _) => Some(prev_obj),
+        (None, _) => prev.prev_base,
+    }
+}
+
 fn missing_obj_ui(ui: &mut Ui, appearance: &Appearance) {
     ui.scope(|ui| {
         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
@@ -331,10 +490,12 @@ fn missing_obj_ui(ui: &mut Ui, appearance: &Appearance) {
 }
 
 pub fn symbol_diff_ui(ui: &mut Ui, state: &mut DiffViewState, appearance: &Appearance) {
-    let DiffViewState { build, current_view, symbol_state, search, .. } = state;
+    let DiffViewState { build, prev_diff, current_view, symbol_state, search, .. } = state;
     let Some(result) = build else {
         return;
     };
+    let prev_diff = prev_diff.as_ref().filter(|prev| prev.is_active(View::SymbolDiff));
+    let mut ret = None;
 
     // Header
     let available_width = ui.available_width();
@@ -354,15 +515,20 @@ pub fn symbol_diff_ui(ui: &mut Ui, state: &mut DiffViewState, appearance: &Appea
                         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
                         ui.style_mut().wrap = Some(false);
 
-                        ui.label("Build target:");
-                        if result.first_status.success {
-                            if result.first_obj.is_none() {
-                                ui.colored_label(appearance.replace_color, "Missing");
+                        if prev_diff.is_some() {
+                            ui.label("Previous base:");
+                            ui.label("OK");
+                        } else {
+                            ui.label("Build target:");
+                            if result.first_status.success {
+                                if result.first_obj.is_none() {
+                                    ui.colored_label(appearance.replace_color, "Missing");
+                                } else {
+                                    ui.label("OK");
+                                }
                             } else {
-                                ui.label("OK");
+                                ui.colored_label(appearance.delete_color, "Fail");
                             }
-                        } else {
-                            ui.colored_label(appearance.delete_color, "Fail");
                         }
                     });
 
@@ -393,9 +559,25 @@ pub fn symbol_diff_ui(ui: &mut Ui, state: &mut DiffViewState, appearance: &Appea
                         }
                     });
 
-                    if ui.add_enabled(!state.build_running, egui::Button::new("Build")).clicked() {
-                        state.queue_build = true;
-                    }
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!state.build_running, egui::Button::new("Build"))
+                            .clicked()
+                        {
+                            state.queue_build = true;
+                        }
+                        if ui
+                            .add_enabled(
+                                result.debug_diff.is_some(),
+                                egui::Button::new("Debug info"),
+                            )
+                            .on_hover_text("Compare the target's and base's debug information")
+                            .on_disabled_hover_text("Neither object has debug information")
+                            .clicked()
+                        {
+                            ret = Some(View::DebugInfoDiff);
+                        }
+                    });
                 },
             );
         },
@@ -403,18 +585,28 @@ pub fn symbol_diff_ui(ui: &mut Ui, state: &mut DiffViewState, appearance: &Appea
     ui.separator();
 
     // Table
-    let mut ret = None;
     let lower_search = search.to_ascii_lowercase();
+    let debug_info = result.first_obj.as_ref().and_then(|obj| obj.debug_info.as_ref());
     StripBuilder::new(ui).size(Size::remainder()).vertical(|mut strip| {
         strip.strip(|builder| {
             builder.sizes(Size::remainder(), 2).horizontal(|mut strip| {
                 strip.cell(|ui| {
                     ui.push_id("left", |ui| {
-                        if result.first_status.success {
+                        if let Some(prev_diff) = prev_diff {
+                            ret = ret.or(symbol_list_ui(
+                                ui,
+                                &prev_diff.prev_obj,
+                                debug_info,
+                                symbol_state,
+                                &lower_search,
+                                appearance,
+                            ));
+                        } else if result.first_status.success {
                             if let Some(obj) = &result.first_obj {
                                 ret = ret.or(symbol_list_ui(
                                     ui,
                                     obj,
+                                    debug_info,
                                     symbol_state,
                                     &lower_search,
                                     appearance,
@@ -430,10 +622,15 @@ pub fn symbol_diff_ui(ui: &mut Ui, state: &mut DiffViewState, appearance: &Appea
                 strip.cell(|ui| {
                     ui.push_id("right", |ui| {
                         if result.second_status.success {
-                            if let Some(obj) = &result.second_obj {
+                            let obj = match prev_diff {
+                                Some(prev_diff) => Some(&prev_diff.base_obj),
+                                None => result.second_obj.as_deref(),
+                            };
+                            if let Some(obj) = obj {
                                 ret = ret.or(symbol_list_ui(
                                     ui,
                                     obj,
+                                    debug_info,
                                     symbol_state,
                                     &lower_search,
                                     appearance,